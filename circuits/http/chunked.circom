@@ -0,0 +1,123 @@
+pragma circom 2.1.9;
+
+include "circomlib/circuits/comparators.circom";
+include "../utils/operators.circom";
+include "@zk-email/circuits/utils/array.circom";
+
+/*
+DechunkBody: Removes the chunked transfer coding framing from an HTTP body.
+https://www.rfc-editor.org/rfc/rfc9112.html#name-chunked-transfer-coding
+
+Each chunk is `chunk-size CRLF chunk-data CRLF` with the size in hex, and the last chunk has size
+`0`. Chunk extensions aren't supported, and trailer fields after the last chunk are ignored.
+
+# Parameters
+- `maxContentLength`: length of the chunked body
+- `maxPayloadLength`: maximum length of the payload, i.e. all chunk data concatenated
+
+# Inputs
+- `body[maxContentLength]`: chunked body bytes, zero padded
+
+# Outputs
+- `payload[maxPayloadLength]`: chunk data bytes, zero padded
+*/
+template DechunkBody(maxContentLength, maxPayloadLength) {
+    signal input body[maxContentLength];
+    signal output payload[maxPayloadLength];
+
+    // State before reading each byte
+    signal readingSize[maxContentLength + 1];  // inside a chunk-size line
+    signal size[maxContentLength + 1];         // chunk size read so far
+    signal remaining[maxContentLength + 1];    // chunk data bytes left
+    signal done[maxContentLength + 1];         // last chunk was read
+    signal payloadIndex[maxContentLength + 1]; // chunk data bytes read so far
+
+    readingSize[0]  <== 1;
+    size[0]         <== 0;
+    remaining[0]    <== 0;
+    done[0]         <== 0;
+    payloadIndex[0] <== 0;
+
+    signal isDigit[maxContentLength];
+    signal isLowerHex[maxContentLength];
+    signal isUpperHex[maxContentLength];
+    signal digitValue[maxContentLength];
+    signal lowerHexValue[maxContentLength];
+    signal upperHexValue[maxContentLength];
+    signal isCR[maxContentLength];
+    signal isLF[maxContentLength];
+    signal isRemainingZero[maxContentLength];
+    signal isData[maxContentLength];
+    signal readSizeDigit[maxContentLength];
+    signal endSizeLine[maxContentLength];
+    signal shiftedSize[maxContentLength];
+    signal endSize[maxContentLength];
+    signal isSizeZero[maxContentLength];
+    signal lastChunk[maxContentLength];
+    signal isDataEnd[maxContentLength];
+    signal isFraming[maxContentLength];
+    signal endDataLine[maxContentLength];
+
+    for (var i = 0 ; i < maxContentLength ; i++) {
+        // hex digit value: `0-9` are 48-57, `a-f` are 97-102 and `A-F` are 65-70
+        isDigit[i]       <== InRange(8)(body[i], [48, 57]);
+        isLowerHex[i]    <== InRange(8)(body[i], [97, 102]);
+        isUpperHex[i]    <== InRange(8)(body[i], [65, 70]);
+        digitValue[i]    <== isDigit[i] * (body[i] - 48);
+        lowerHexValue[i] <== isLowerHex[i] * (body[i] - 87);
+        upperHexValue[i] <== isUpperHex[i] * (body[i] - 55);
+        var isHex    = isDigit[i] + isLowerHex[i] + isUpperHex[i];
+        var hexValue = digitValue[i] + lowerHexValue[i] + upperHexValue[i];
+
+        isCR[i] <== IsEqual()([body[i], 13]);
+        isLF[i] <== IsEqual()([body[i], 10]);
+
+        // chunk data until the current chunk has no bytes left
+        isRemainingZero[i] <== IsZero()(remaining[i]);
+        isData[i]          <== 1 - isRemainingZero[i];
+
+        // chunk-size line is hex digits followed by CRLF, and LF starts the chunk data
+        readSizeDigit[i] <== readingSize[i] * isHex;
+        endSizeLine[i]   <== readingSize[i] * isLF[i];
+        readingSize[i] * (1 - isHex - isCR[i] - isLF[i]) === 0;
+
+        shiftedSize[i] <== readSizeDigit[i] * (15 * size[i] + hexValue);
+        endSize[i]     <== endSizeLine[i] * size[i];
+
+        // chunk with size 0 is the last one
+        isSizeZero[i] <== IsZero()(size[i]);
+        lastChunk[i]  <== endSizeLine[i] * isSizeZero[i];
+
+        // CRLF after chunk data, and LF starts the next chunk-size line
+        isDataEnd[i]   <== (1 - readingSize[i]) * isRemainingZero[i];
+        isFraming[i]   <== isDataEnd[i] * (1 - done[i]);
+        isFraming[i] * (1 - isCR[i] - isLF[i]) === 0;
+        endDataLine[i] <== isFraming[i] * isLF[i];
+
+        readingSize[i + 1]  <== readingSize[i] - endSizeLine[i] + endDataLine[i];
+        size[i + 1]         <== size[i] + shiftedSize[i] - endSize[i];
+        remaining[i + 1]    <== remaining[i] - isData[i] + endSize[i];
+        done[i + 1]         <== done[i] + lastChunk[i];
+        payloadIndex[i + 1] <== payloadIndex[i] + isData[i];
+    }
+
+    // body should end after the last chunk, and its data fit in the payload
+    done[maxContentLength] === 1;
+    signal isPayloadFit <== LessEqThan(log2Ceil(maxContentLength + maxPayloadLength + 1))([payloadIndex[maxContentLength], maxPayloadLength]);
+    isPayloadFit === 1;
+
+    // move chunk data bytes to their payload index
+    signal isAtIndex[maxPayloadLength][maxContentLength];
+    signal isPayloadByte[maxPayloadLength][maxContentLength];
+    signal payloadByte[maxPayloadLength][maxContentLength];
+    for (var j = 0 ; j < maxPayloadLength ; j++) {
+        var byte = 0;
+        for (var i = 0 ; i < maxContentLength ; i++) {
+            isAtIndex[j][i]     <== IsEqual()([payloadIndex[i], j]);
+            isPayloadByte[j][i] <== isAtIndex[j][i] * isData[i];
+            payloadByte[j][i]   <== isPayloadByte[j][i] * body[i];
+            byte += payloadByte[j][i];
+        }
+        payload[j] <== byte;
+    }
+}
@@ -63,6 +63,158 @@ template HeaderFieldNameValueMatch(dataLen, nameLen, valueLen) {
     signal output out <== headerNameMatchAndNextByteColon * headerValueMatch;
 }
 
+// Same as `HeaderFieldNameMatch`, but field names are case-insensitive, so the name bytes in `data`
+// are lowercased before matching. `headerName` should be lowercase.
+// https://www.rfc-editor.org/rfc/rfc9110.html#name-field-names
+template HeaderFieldNameMatchCaseInsensitive(dataLen, nameLen) {
+    signal input data[dataLen];
+    signal input headerName[nameLen];
+    signal input index;
+
+    var logDataLen = log2Ceil(dataLen + nameLen + 1);
+
+    signal isStartLessThanMaxLength <== LessThan(logDataLen)([index, dataLen]);
+    signal nameIndex <== index * isStartLessThanMaxLength;
+    signal name[nameLen] <== SelectSubArray(dataLen, nameLen)(data, nameIndex, nameLen);
+
+    // A-Z are 65-90, and a-z are 32 above
+    signal isUppercase[nameLen];
+    signal lowercaseName[nameLen];
+    for (var i = 0 ; i < nameLen ; i++) {
+        isUppercase[i]   <== InRange(8)(name[i], [65, 90]);
+        lowercaseName[i] <== name[i] + 32 * isUppercase[i];
+    }
+
+    // is name matches
+    signal isNameMatch <== IsEqualArray(nameLen)([headerName, lowercaseName]);
+    signal headerNameMatch <== isStartLessThanMaxLength * isNameMatch;
+
+    // next byte to name should be COLON
+    signal endOfHeaderName <== IndexSelector(dataLen)(data, index + nameLen);
+    signal isNextByteColon <== IsEqual()([endOfHeaderName, 58]);
+
+    // header name matches
+    signal output out <== headerNameMatch * isNextByteColon;
+}
+
+// Same as `HeaderFieldNameValueMatch`, but field names are case-insensitive. `headerName` should be
+// lowercase.
+template HeaderFieldNameValueMatchCaseInsensitive(dataLen, nameLen, valueLen) {
+    signal input data[dataLen];
+    signal input headerName[nameLen];
+    signal input headerValue[valueLen];
+    signal input index;
+
+    signal headerNameMatch <== HeaderFieldNameMatchCaseInsensitive(dataLen, nameLen)(data, headerName, index);
+
+    // field-name: SP field-value
+    signal headerValueMatch <== SubstringMatchWithIndex(dataLen, valueLen)(data, headerValue, index + nameLen + 2);
+
+    // header name matches + header value matches
+    signal output out <== headerNameMatch * headerValueMatch;
+}
+
+// Whether a header value ending at `index` is complete, i.e. `data[index]` is CR or `index` is the end
+// of `data`
+template IsHeaderValueEnd(dataLen) {
+    signal input data[dataLen];
+    signal input index;
+    signal output out;
+
+    signal endByte <== IndexSelector(dataLen)(data, index);
+    signal isEndByteCR <== IsEqual()([endByte, 13]);
+    signal isEndOfData <== IsEqual()([index, dataLen]);
+    out <== isEndByteCR + isEndOfData;
+}
+
+// Same as `IsHeaderValueEnd`, but `data` is zero padded after its first `dataLen` bytes
+template IsHeaderValueEndPadded(maxDataLen) {
+    signal input data[maxDataLen];
+    signal input dataLen;
+    signal input index;
+    signal output out;
+
+    signal endByte <== IndexSelector(maxDataLen)(data, index);
+    signal isEndByteCR <== IsEqual()([endByte, 13]);
+    signal isEndOfData <== IsEqual()([index, dataLen]);
+    out <== isEndByteCR + isEndOfData;
+}
+
+// Whether the byte after `prevByte` starts a header line, i.e. `prevByte` is LF and the parser is in
+// the headers after reading it
+template IsHeaderLineStart() {
+    signal input prevByte;
+    signal input parsing_header;
+    signal output out;
+
+    signal isPrevByteLF <== IsEqual()([prevByte, 10]);
+    signal isNotParsingHeader <== IsZero()(parsing_header);
+    out <== isPrevByteLF * (1 - isNotParsingHeader);
+}
+
+// Whether the byte after `prevByte` starts a `name=value` pair in the query of a request target,
+// i.e. `prevByte` is the first `?` of the target or a later `&`. `inQuery` is whether the byte is
+// after the first `?`.
+// https://www.rfc-editor.org/rfc/rfc3986#section-3.4
+template QueryPairStart() {
+    signal input prevByte;
+    signal input prevInQuery;
+    signal input inTarget;
+    signal output inQuery;
+    signal output out;
+
+    signal isPrevQuestionMark <== IsEqual()([prevByte, 63]);
+    signal isPrevAmpersand    <== IsEqual()([prevByte, 38]);
+    signal startsQuery        <== isPrevQuestionMark * (1 - prevInQuery);
+    inQuery <== (prevInQuery + startsQuery) * inTarget;
+
+    signal startsNextPair <== prevInQuery * isPrevAmpersand;
+    out <== (startsQuery + startsNextPair) * inTarget;
+}
+
+// Whether a query parameter value ending at `index` is complete, i.e. `data[index]` is `&` or the SP
+// ending the target
+template IsQueryValueEnd(dataLen) {
+    signal input data[dataLen];
+    signal input index;
+    signal output out;
+
+    signal endByte     <== IndexSelector(dataLen)(data, index);
+    signal isAmpersand <== IsEqual()([endByte, 38]);
+    signal isSP        <== IsEqual()([endByte, 32]);
+    out <== isAmpersand + isSP;
+}
+
+// Whether `data` has the query parameter name `name` followed by `=` at `index`
+template QueryParamNameMatch(dataLen, nameLen) {
+    signal input data[dataLen];
+    signal input name[nameLen];
+    signal input index;
+
+    signal nameMatch <== SubstringMatchWithIndex(dataLen, nameLen)(data, name, index);
+
+    // next byte to name should be `=`
+    signal endOfName        <== IndexSelector(dataLen)(data, index + nameLen);
+    signal isNextByteEquals <== IsEqual()([endOfName, 61]);
+
+    signal output out <== nameMatch * isNextByteEquals;
+}
+
+// Whether `data` has the query parameter `name=value` at `index`, with `value` being the whole value
+template QueryParamMatch(dataLen, nameLen, valueLen) {
+    signal input data[dataLen];
+    signal input name[nameLen];
+    signal input value[valueLen];
+    signal input index;
+
+    signal nameMatch  <== QueryParamNameMatch(dataLen, nameLen)(data, name, index);
+    signal valueMatch <== SubstringMatchWithIndex(dataLen, valueLen)(data, value, index + nameLen + 1);
+    signal valueEnd   <== IsQueryValueEnd(dataLen)(data, index + nameLen + 1 + valueLen);
+
+    signal nameValueMatch <== nameMatch * valueMatch;
+    signal output out <== nameValueMatch * valueEnd;
+}
+
 // https://www.rfc-editor.org/rfc/rfc9112.html#name-field-syntax
 template HeaderFieldNameValueMatchPadded(dataLen, maxNameLen, maxValueLen) {
     signal input data[dataLen];
@@ -83,8 +83,11 @@ template StateChange() {
 
     // GreaterEqThan(2) because start line can have at most 3 values for request or response
     signal isParsingStart <== GreaterEqThan(2)([state[0], 1]);
+    // the last value of a status line (reason phrase) may contain SP, so only the first two SP are separators
+    signal isBeforeLastValue <== LessThan(2)([state[0], 3]);
+    signal readSeparatorSP <== readSP * isBeforeLastValue;
     // increment parsing start counter on reading SP
-    signal incrementParsingStart <== readSP * isParsingStart;
+    signal incrementParsingStart <== readSeparatorSP * isParsingStart;
     // disable parsing start on reading CRLF
     signal disableParsingStart <== readCRLF * state[0];
 
@@ -476,4 +476,102 @@ template KeyMatchAtIndex(dataLen, maxKeyLen, index) {
     // log("keyMatchAtIndex", isParsingCorrectKey, isSubstringMatch.out);
 
     signal output out <== isSubstringMatch.out * isParsingCorrectKey;
-}
\ No newline at end of file
+}
+
+/// Same as `KeyMatchAtDepth`, but `key` is zero padded after its first `keyLen` bytes, so that any
+/// key of at most `maxKeyLen` bytes is matched with `MatchPaddedKey`
+///
+/// # Arguments
+/// - `dataLen`: parsed data length
+/// - `n`: maximum stack height
+/// - `maxKeyLen`: maximum key length
+/// - `index`: data index to match from
+/// - `depth`: depth of key to be matched
+///
+/// # Inputs
+/// - `data`: data bytes
+/// - `key`: key bytes, zero padded
+/// - `keyLen`: key length
+/// - `parsing_key`: if current byte is inside a key
+/// - `stack`: parser stack output
+///
+/// # Output
+/// - `out`: Returns `1` if `key` matches `data` at `index`
+template KeyMatchAtDepthPadded(dataLen, n, maxKeyLen, index, depth) {
+    signal input data[dataLen];
+    signal input key[maxKeyLen];
+    signal input keyLen;
+    signal input parsing_key;
+    signal input stack[n][2];
+
+    component topOfStack = GetTopOfStack(n);
+    topOfStack.stack <== stack;
+    signal pointer <== topOfStack.pointer;
+    _ <== topOfStack.value;
+
+    signal paddedKey[maxKeyLen + 1];
+    for (var i = 0 ; i < maxKeyLen ; i++) {
+        paddedKey[i] <== key[i];
+    }
+    paddedKey[maxKeyLen] <== 0;
+
+    // bytes past the end of `data` can't match the closing quote
+    signal window[maxKeyLen + 1];
+    for (var i = 0 ; i < maxKeyLen + 1 ; i++) {
+        if (index + i < dataLen) {
+            window[i] <== data[index + i];
+        } else {
+            window[i] <== 0;
+        }
+    }
+
+    // start of key equals `"`
+    signal is_start_of_key_equal_to_quote;
+    if (index > 0) {
+        is_start_of_key_equal_to_quote <== IsEqual()([data[index - 1], 34]);
+    } else {
+        is_start_of_key_equal_to_quote <== 0;
+    }
+
+    // key matches, followed by `"`
+    signal substring_match <== MatchPaddedKey(maxKeyLen + 1)([paddedKey, window], keyLen);
+
+    signal is_parsing_correct_key <== is_start_of_key_equal_to_quote * parsing_key;
+    // is the key given by index at correct depth?
+    signal is_key_at_depth <== IsEqual()([pointer-1, depth]);
+
+    signal is_parsing_correct_key_at_depth <== is_parsing_correct_key * is_key_at_depth;
+
+    signal output out <== substring_match * is_parsing_correct_key_at_depth;
+}
+
+/// Checks that a padded key is `keyLen` non-zero bytes followed by zeros, so that `MatchPaddedKey`
+/// can't skip key bytes as padding
+///
+/// # Arguments
+/// - `maxKeyLen`: maximum key length
+///
+/// # Inputs
+/// - `key`: key bytes, zero padded
+/// - `keyLen`: key length, at most `maxKeyLen`
+template AssertPaddedKey(maxKeyLen) {
+    signal input key[maxKeyLen];
+    signal input keyLen;
+
+    // `isPadding[i]` is set from index `keyLen` onwards
+    signal isKeyLen[maxKeyLen + 1];
+    signal isPadding[maxKeyLen + 1];
+    signal isZero[maxKeyLen];
+    isKeyLen[0]  <== IsEqual()([0, keyLen]);
+    isPadding[0] <== isKeyLen[0];
+    for (var i = 0 ; i < maxKeyLen ; i++) {
+        isZero[i] <== IsZero()(key[i]);
+        isZero[i] === isPadding[i];
+
+        isKeyLen[i + 1]  <== IsEqual()([i + 1, keyLen]);
+        isPadding[i + 1] <== isPadding[i] + isKeyLen[i + 1];
+    }
+
+    // `keyLen` is at most `maxKeyLen`
+    isPadding[maxKeyLen] === 1;
+}
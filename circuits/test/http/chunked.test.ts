@@ -0,0 +1,38 @@
+import { circomkit, WitnessTester, toByte } from "../common";
+
+describe("HTTP :: Chunked :: DechunkBody", async () => {
+    let circuit: WitnessTester<["body"], ["payload"]>;
+
+    function generatePassCase(body: string, payload: string, maxPayloadLength: number, desc: string) {
+        it(`(valid) witness: ${desc}`, async () => {
+            circuit = await circomkit.WitnessTester(`DechunkBody`, {
+                file: "http/chunked",
+                template: "DechunkBody",
+                params: [body.length, maxPayloadLength],
+            });
+            console.log("#constraints:", await circuit.getConstraintCount());
+
+            let expected = toByte(payload).concat(Array(maxPayloadLength - payload.length).fill(0));
+            await circuit.expectPass({ body: toByte(body) }, { payload: expected });
+        });
+    }
+
+    function generateFailCase(body: string, maxPayloadLength: number, desc: string) {
+        it(`(invalid) witness: ${desc}`, async () => {
+            circuit = await circomkit.WitnessTester(`DechunkBody`, {
+                file: "http/chunked",
+                template: "DechunkBody",
+                params: [body.length, maxPayloadLength],
+            });
+
+            await circuit.expectFail({ body: toByte(body) });
+        });
+    }
+
+    generatePassCase("5\r\nhello\r\n0\r\n\r\n", "hello", 5, "single chunk");
+    generatePassCase("5\r\nhello\r\nA\r\n, chunked!\r\n0\r\n\r\n", "hello, chunked!", 15, "hex chunk size");
+    generatePassCase("2\r\n{}\r\n0\r\n\r\n", "{}", 4, "zero padded payload");
+    generateFailCase("5\r\nhello\r\n", 5, "no last chunk");
+    generateFailCase("3\r\nhello\r\n0\r\n\r\n", 5, "chunk longer than its size");
+    generateFailCase("5\r\nhello\r\nA\r\n, chunked!\r\n0\r\n\r\n", 10, "payload longer than maxPayloadLength");
+});
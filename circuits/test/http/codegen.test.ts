@@ -30,7 +30,7 @@ export function executeCodegen(circuitName: string, inputFileName: string, lockf
 }
 
 describe("HTTP :: Codegen :: Request", async () => {
-    let circuit: WitnessTester<["data", "method", "target", "version", "header1", "value1", "header2", "value2"], []>;
+    let circuit: WitnessTester<["data", "method", "target", "version", "header1", "value1", "header2", "value2"], ["body"]>;
 
     it("(valid) GET:", async () => {
         let lockfile = "request.lock";
@@ -43,10 +43,11 @@ describe("HTTP :: Codegen :: Request", async () => {
         const lockData = readLockFile<Request>(`${lockfile}.json`);
         console.log("lockData: ", JSON.stringify(lockData));
 
-        const input = readHTTPInputFile(`${inputfile}`).input;
+        const http = readHTTPInputFile(`${inputfile}`);
+        const input = http.input;
 
         const headers = getHeaders(lockData);
-        const params = [input.length, lockData.method.length, lockData.target.length, lockData.version.length];
+        const params = [input.length, http.bodyBytes.length, lockData.method.length, lockData.target.length, lockData.version.length];
         headers.forEach(header => {
             params.push(header[0].length);
             params.push(header[1].length);
@@ -85,10 +86,11 @@ describe("HTTP :: Codegen :: Request", async () => {
 
         const lockData = readLockFile<Request>(`${lockfile}.json`);
 
-        const input = readHTTPInputFile(`${inputfile}`).input
+        const http = readHTTPInputFile(`${inputfile}`);
+        const input = http.input;
 
         const headers = getHeaders(lockData);
-        const params = [input.length, lockData.method.length, lockData.target.length, lockData.version.length];
+        const params = [input.length, http.bodyBytes.length, lockData.method.length, lockData.target.length, lockData.version.length];
         headers.forEach(header => {
             params.push(header[0].length);
             params.push(header[1].length);
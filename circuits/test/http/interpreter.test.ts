@@ -108,4 +108,111 @@ describe("HeaderFieldNameValueMatchPadded", async () => {
         index: 16,
     }
     generatePassCase(input2, { out: 0 }, "incorrect index");
-});
\ No newline at end of file
+});
+
+describe("HeaderFieldNameValueMatchCaseInsensitive", async () => {
+    let circuit: WitnessTester<["data", "headerName", "headerValue", "index"], ["out"]>;
+
+    let header_name = toByte("content-type");
+    let header_value = toByte("application/json; charset=utf-8");
+
+    before(async () => {
+        circuit = await circomkit.WitnessTester(`HeaderFieldNameValueMatchCaseInsensitive`, {
+            file: "http/interpreter",
+            template: "HeaderFieldNameValueMatchCaseInsensitive",
+            params: [http_response_plaintext.length, header_name.length, header_value.length],
+        });
+    });
+
+    function generatePassCase(input: any, expected: any, desc: string) {
+        it(`(valid) witness: ${desc}`, async () => {
+            await circuit.expectPass(input, expected);
+        });
+    }
+
+    generatePassCase({ data: http_response_plaintext, headerName: header_name, headerValue: header_value, index: 17 }, { out: 1 }, "lowercase header name matches");
+
+    // `Content-Type` in the data matches `content-type`
+    let data = http_response_plaintext.slice();
+    data[17] = toByte("C")[0];
+    data[25] = toByte("T")[0];
+    generatePassCase({ data: data, headerName: header_name, headerValue: header_value, index: 17 }, { out: 1 }, "mixed case header name matches");
+
+    // values are still case-sensitive
+    generatePassCase({ data: data, headerName: header_name, headerValue: toByte("Application/json; charset=utf-8"), index: 17 }, { out: 0 }, "value case mismatch");
+    generatePassCase({ data: data, headerName: header_name, headerValue: header_value, index: 16 }, { out: 0 }, "incorrect index");
+});
+
+describe("IsHeaderValueEnd", async () => {
+    let circuit: WitnessTester<["data", "index"], ["out"]>;
+
+    before(async () => {
+        circuit = await circomkit.WitnessTester(`IsHeaderValueEnd`, {
+            file: "http/interpreter",
+            template: "IsHeaderValueEnd",
+            params: [http_response_plaintext.length],
+        });
+    });
+
+    function generatePassCase(input: any, expected: any, desc: string) {
+        it(`(valid) witness: ${desc}`, async () => {
+            await circuit.expectPass(input, expected);
+        });
+    }
+
+    // `content-type` value at 31 is `application/json; charset=utf-8`
+    generatePassCase({ data: http_response_plaintext, index: 62 }, { out: 1 }, "whole value ends at CR");
+    generatePassCase({ data: http_response_plaintext, index: 47 }, { out: 0 }, "value prefix doesn't end at CR");
+    generatePassCase({ data: http_response_plaintext, index: http_response_plaintext.length }, { out: 1 }, "value ends with data");
+});
+
+describe("IsHeaderValueEndPadded", async () => {
+    let circuit: WitnessTester<["data", "dataLen", "index"], ["out"]>;
+
+    let data = toByte("HTTP/1.1 200 OK\r\nContent-Length: 19");
+    let padded = data.concat(Array(8).fill(0));
+
+    before(async () => {
+        circuit = await circomkit.WitnessTester(`IsHeaderValueEndPadded`, {
+            file: "http/interpreter",
+            template: "IsHeaderValueEndPadded",
+            params: [padded.length],
+        });
+    });
+
+    function generatePassCase(input: any, expected: any, desc: string) {
+        it(`(valid) witness: ${desc}`, async () => {
+            await circuit.expectPass(input, expected);
+        });
+    }
+
+    generatePassCase({ data: padded, dataLen: data.length, index: 15 }, { out: 1 }, "value ends at CR");
+    generatePassCase({ data: padded, dataLen: data.length, index: data.length }, { out: 1 }, "value ends with data before padding");
+    generatePassCase({ data: padded, dataLen: data.length, index: data.length - 1 }, { out: 0 }, "value prefix doesn't end");
+});
+
+describe("QueryParamMatch", async () => {
+    let circuit: WitnessTester<["data", "name", "value", "index"], ["out"]>;
+
+    let data = toByte("GET /v1/accounts?account=123&limit=50 HTTP/1.1\r\n");
+    let name = toByte("account");
+    let value = toByte("123");
+
+    before(async () => {
+        circuit = await circomkit.WitnessTester(`QueryParamMatch`, {
+            file: "http/interpreter",
+            template: "QueryParamMatch",
+            params: [data.length, name.length, value.length],
+        });
+    });
+
+    function generatePassCase(input: any, expected: any, desc: string) {
+        it(`(valid) witness: ${desc}`, async () => {
+            await circuit.expectPass(input, expected);
+        });
+    }
+
+    generatePassCase({ data: data, name: name, value: value, index: 17 }, { out: 1 }, "value ends at `&`");
+    generatePassCase({ data: data, name: name, value: toByte("124"), index: 17 }, { out: 0 }, "value mismatch");
+    generatePassCase({ data: data, name: name, value: value, index: 16 }, { out: 0 }, "incorrect index");
+});
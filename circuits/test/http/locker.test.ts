@@ -91,6 +91,13 @@ describe("HTTP :: Locker :: Status Line", async () => {
         generateFailCase(parsedHttp.input, toByte("HTTP/1.1"), toByte("404"), toByte("OK"), "");
         generateFailCase(parsedHttp.input, toByte("HTTP/1.1"), toByte("200"), toByte("Not Found"), "");
     });
+
+    describe("Not Found", async () => {
+        let parsedHttp = readHTTPInputFile("not_found_response.http");
+        generatePassCase(parsedHttp.input, toByte("HTTP/1.1"), toByte("404"), toByte("Not Found"), "");
+        generateFailCase(parsedHttp.input, toByte("HTTP/1.1"), toByte("404"), toByte("Not"), "");
+        generateFailCase(parsedHttp.input, toByte("HTTP/1.1"), toByte("404 Not"), toByte("Found"), "");
+    });
 });
 
 describe("HTTP :: Locker :: Header", async () => {
@@ -387,6 +387,73 @@ describe("Interpreter", async () => {
         generatePassCase(input6, { out: 0 }, 1, "wrong depth");
     });
 
+    describe("KeyMatchAtDepthPadded", async () => {
+        let circuit: WitnessTester<["data", "key", "keyLen", "parsing_key", "stack"], ["out"]>;
+        let maxKeyLen = 3;
+
+        function generatePassCase(input: any, expected: any, index: number, depth: number, desc: string) {
+            const description = generateDescription(input);
+
+            it(`(valid) witness: ${description} ${desc}`, async () => {
+                // pad key with 0's
+                input.keyLen = input.key.length;
+                input.key = input.key.concat(Array(maxKeyLen - input.key.length).fill(0));
+
+                circuit = await circomkit.WitnessTester(`KeyMatchAtDepthPadded`, {
+                    file: "json/interpreter",
+                    template: "KeyMatchAtDepthPadded",
+                    params: [input.data.length, 4, maxKeyLen, index, depth],
+                });
+                console.log("#constraints:", await circuit.getConstraintCount());
+
+                await circuit.expectPass(input, expected);
+            });
+        }
+
+        let input = readJSONInputFile("value_array_object.json", ["a", 0, "b", 0]);
+
+        let input1 = { data: input[0], key: input[1][0], parsing_key: 1, stack: [[1, 0], [0, 0], [0, 0], [0, 0]] };
+        generatePassCase(input1, { out: 1 }, 2, 0, "");
+
+        let input2 = { data: input[0], key: input[1][2], parsing_key: 1, stack: [[1, 1], [2, 0], [1, 0], [0, 0]] };
+        generatePassCase(input2, { out: 1 }, 8, 2, "");
+
+        // fail cases
+
+        let input3 = { data: input[0], key: [98], parsing_key: 1, stack: [[1, 0], [0, 0], [0, 0], [0, 0]] };
+        generatePassCase(input3, { out: 0 }, 2, 0, "wrong key");
+
+        let input4 = { data: input[0], key: [97, 34], parsing_key: 1, stack: [[1, 0], [0, 0], [0, 0], [0, 0]] };
+        generatePassCase(input4, { out: 0 }, 2, 0, "key longer than data key");
+
+        let input5 = { data: input[0], key: input[1][0], parsing_key: 1, stack: [[1, 0], [0, 0], [0, 0], [0, 0]] };
+        generatePassCase(input5, { out: 0 }, 2, 1, "wrong depth");
+    });
+
+    describe("AssertPaddedKey", async () => {
+        let circuit: WitnessTester<["key", "keyLen"], []>;
+
+        before(async () => {
+            circuit = await circomkit.WitnessTester(`AssertPaddedKey`, {
+                file: "json/interpreter",
+                template: "AssertPaddedKey",
+                params: [4],
+            });
+            console.log("#constraints:", await circuit.getConstraintCount());
+        });
+
+        it("witness: key followed by zeros", async () => {
+            await circuit.expectPass({ key: [107, 101, 121, 0], keyLen: 3 });
+            await circuit.expectPass({ key: [107, 101, 121, 49], keyLen: 4 });
+        });
+
+        it("witness: invalid padding", async () => {
+            await circuit.expectFail({ key: [107, 0, 121, 0], keyLen: 3 });
+            await circuit.expectFail({ key: [107, 101, 121, 49], keyLen: 3 });
+            await circuit.expectFail({ key: [107, 101, 121, 0], keyLen: 5 });
+        });
+    });
+
     describe("KeyMatchAtIndex", async () => {
         let circuit: WitnessTester<["data", "key", "keyLen", "parsing_key"], ["out"]>;
         let maxKeyLen = 3;
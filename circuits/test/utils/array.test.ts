@@ -152,4 +152,35 @@ describe("GenericArrayAdd", () => {
         );
     });
 
-});
\ No newline at end of file
+});
+describe("AssertZeroPadded", () => {
+    let circuit: WitnessTester<["in", "length"], []>;
+    before(async () => {
+        circuit = await circomkit.WitnessTester(`AssertZeroPadded`, {
+            file: "utils/array",
+            template: "AssertZeroPadded",
+            params: [4],
+        });
+        console.log("#constraints:", await circuit.getConstraintCount());
+    });
+
+    it("witness: in = [1,2,0,0], length = 2", async () => {
+        await circuit.expectPass({ in: [1, 2, 0, 0], length: 2 });
+    });
+
+    it("witness: in = [1,2,3,4], length = 4", async () => {
+        await circuit.expectPass({ in: [1, 2, 3, 4], length: 4 });
+    });
+
+    it("witness: in = [0,0,0,0], length = 0", async () => {
+        await circuit.expectPass({ in: [0, 0, 0, 0], length: 0 });
+    });
+
+    it("witness: in = [1,2,3,0], length = 2", async () => {
+        await circuit.expectFail({ in: [1, 2, 3, 0], length: 2 });
+    });
+
+    it("witness: in = [1,2,3,4], length = 5", async () => {
+        await circuit.expectFail({ in: [1, 2, 3, 4], length: 5 });
+    });
+});
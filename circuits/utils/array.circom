@@ -198,6 +198,38 @@ template ScalarArrayMul(n) {
 
 // TODO: Below really needs documentation.
 
+/*
+This template checks that an array is zero padded after its first `length` elements.
+
+# Params:
+ - `n`: the length of `in`
+
+# Inputs:
+ - `in[n]`: `length` elements followed by zeros
+ - `length`: number of elements before the padding, at most `n`
+*/
+template AssertZeroPadded(n) {
+    signal input in[n];
+    signal input length;
+
+    // `isPadding[i]` is set from index `length` onwards
+    signal isLength[n + 1];
+    signal isPadding[n + 1];
+    isLength[0]  <== IsEqual()([0, length]);
+    isPadding[0] <== isLength[0];
+    for (var i = 1 ; i < n + 1 ; i++) {
+        isLength[i]  <== IsEqual()([i, length]);
+        isPadding[i] <== isPadding[i - 1] + isLength[i];
+    }
+
+    // `length` is at most `n`
+    isPadding[n] === 1;
+
+    for (var i = 0 ; i < n ; i++) {
+        isPadding[i] * in[i] === 0;
+    }
+}
+
 // from: https://github.com/pluto/aes-proof/blob/main/circuits/aes-gcm/helper_functions.circom
 template SumMultiple(n) {
     signal input nums[n];
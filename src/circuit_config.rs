@@ -10,6 +10,9 @@ pub struct CircomkitCircuitConfig {
     pub template: String,
     /// circuit parameters
     pub params: Vec<usize>,
+    /// public circuit inputs
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pubs: Vec<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -1,5 +1,5 @@
-use crate::{circuit_config::CircomkitCircuitConfig, ExtractorArgs, FileType};
-use regex::Regex;
+use super::{Commitment, MaxSizes};
+use crate::{circuit_config::CircomkitCircuitConfig, machine, ExtractorArgs, FileType};
 use serde::{Deserialize, Serialize};
 
 use std::{
@@ -23,7 +23,16 @@ pub struct Request {
     pub version: String,
     #[serde(flatten)]
     #[serde(deserialize_with = "deserialize_headers")]
-    pub headers: BTreeMap<String, String>,
+    pub headers: Vec<Header>,
+    /// Matches header names ignoring ASCII case
+    #[serde(default, rename = "caseInsensitiveHeaders")]
+    pub case_insensitive_headers: bool,
+    /// Header names that mustn't appear in the message, matched ignoring ASCII case
+    #[serde(default, rename = "forbiddenHeaders")]
+    pub forbidden_headers: Vec<String>,
+    /// Query parameters locked or extracted independently, in which case `target` is only the path
+    #[serde(default, rename = "queryParams")]
+    pub query_params: Vec<QueryParam>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -33,14 +42,286 @@ pub struct Response {
     pub message: String,
     #[serde(flatten)]
     #[serde(deserialize_with = "deserialize_headers")]
-    pub headers: BTreeMap<String, String>,
+    pub headers: Vec<Header>,
+    /// Matches header names ignoring ASCII case
+    #[serde(default, rename = "caseInsensitiveHeaders")]
+    pub case_insensitive_headers: bool,
+    /// Header names that mustn't appear in the message, matched ignoring ASCII case
+    #[serde(default, rename = "forbiddenHeaders")]
+    pub forbidden_headers: Vec<String>,
+    /// Outputs a commitment to the body instead of its bytes
+    #[serde(default)]
+    pub commitment: Option<Commitment>,
+    /// Body uses the chunked transfer coding, and its framing is removed before JSON extraction
+    #[serde(default)]
+    pub chunked: bool,
+    /// Constrains the locked `Content-Length` header value to equal the number of body bytes
+    #[serde(default, rename = "checkContentLength")]
+    pub check_content_length: bool,
+}
+
+/// How a locked header is matched
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum HeaderMode {
+    /// value is `headerValue`
+    #[default]
+    Exact,
+    /// value starts with `headerValue`
+    Prefix,
+    /// header is present with any value
+    Present,
+    /// header is present, and its value is a circuit output
+    Extract,
+}
+
+impl HeaderMode {
+    /// Whether the lockfile has a value for the header, passed to the circuit as `valueN`
+    pub fn has_value(&self) -> bool {
+        matches!(self, HeaderMode::Exact | HeaderMode::Prefix)
+    }
+}
+
+impl std::str::FromStr for HeaderMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "exact" => Ok(HeaderMode::Exact),
+            "prefix" => Ok(HeaderMode::Prefix),
+            "present" => Ok(HeaderMode::Present),
+            "extract" => Ok(HeaderMode::Extract),
+            _ => Err(format!(
+                "header mode `{}` should be one of `exact`, `prefix`, `present`, `extract`",
+                s
+            )),
+        }
+    }
+}
+
+/// Query parameter `name=value` in a request target
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct QueryParam {
+    pub name: String,
+    /// value to lock, or `None` to output the value from the circuit
+    #[serde(default)]
+    pub value: Option<String>,
+}
+
+/// Header locked by `headerNameN`, `headerValueN` and `headerModeN` in a lockfile
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Header {
+    pub name: String,
+    /// empty for `present` and `extract` modes
+    pub value: String,
+    pub mode: HeaderMode,
+}
+
+impl Header {
+    pub fn exact(name: String, value: String) -> Self {
+        Self {
+            name,
+            value,
+            mode: HeaderMode::Exact,
+        }
+    }
+
+    /// Whether the input header `name: value` matches
+    fn matches(&self, name: &[u8], value: &[u8], case_insensitive: bool) -> bool {
+        let name_matches = match case_insensitive {
+            true => self.name.as_bytes().eq_ignore_ascii_case(name),
+            false => self.name.as_bytes() == name,
+        };
+        name_matches
+            && match self.mode {
+                HeaderMode::Exact => self.value.as_bytes() == value,
+                HeaderMode::Prefix => value.starts_with(self.value.as_bytes()),
+                HeaderMode::Present | HeaderMode::Extract => true,
+            }
+    }
 }
 
 impl HttpData {
-    pub fn headers(&self) -> BTreeMap<String, String> {
+    /// Locked headers in lockfile order, locked as `header1..N` and `value1..N`
+    pub fn headers(&self) -> &[Header] {
+        match self {
+            HttpData::Request(request) => &request.headers,
+            HttpData::Response(response) => &response.headers,
+        }
+    }
+
+    /// Locked start line, i.e. method, target and version of a request, or version, status and
+    /// message of a response
+    pub fn start_line(&self) -> [&str; 3] {
+        match self {
+            HttpData::Request(request) => [&request.method, &request.target, &request.version],
+            HttpData::Response(response) => {
+                [&response.version, &response.status, &response.message]
+            }
+        }
+    }
+
+    /// Whether header names are matched ignoring ASCII case
+    pub fn case_insensitive_headers(&self) -> bool {
+        match self {
+            HttpData::Request(request) => request.case_insensitive_headers,
+            HttpData::Response(response) => response.case_insensitive_headers,
+        }
+    }
+
+    /// Names of headers proven absent from the message
+    pub fn forbidden_headers(&self) -> &[String] {
+        match self {
+            HttpData::Request(request) => &request.forbidden_headers,
+            HttpData::Response(response) => &response.forbidden_headers,
+        }
+    }
+
+    /// Query parameters of a request target, locked as `queryName1..N` and `queryValue1..N`
+    pub fn query_params(&self) -> &[QueryParam] {
+        match self {
+            HttpData::Request(request) => &request.query_params,
+            HttpData::Response(_) => &[],
+        }
+    }
+
+    /// Value of each query parameter in the request target of `parts`, failing if the target's path
+    /// isn't the locked one or a parameter doesn't match exactly one `name=value` pair, like the
+    /// circuit would
+    pub fn query_values<'a>(
+        &self,
+        parts: &machine::http::HttpParts<'a>,
+    ) -> Result<Vec<&'a [u8]>, Box<dyn Error>> {
+        let HttpData::Request(request) = self else {
+            return Ok(vec![]);
+        };
+        if request.query_params.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let (path, pairs) = machine::http::split_target(parts.start_line[1]);
+        let pairs = match pairs {
+            Some(pairs) if path == request.target.as_bytes() => pairs,
+            _ => {
+                return Err(format!(
+                    "request target should be `{}` followed by a query",
+                    request.target
+                )
+                .into())
+            }
+        };
+
+        let mut values = vec![];
+        for param in request.query_params.iter() {
+            let matches: Vec<&[u8]> = pairs
+                .iter()
+                .filter(|(name, value)| {
+                    *name == param.name.as_bytes()
+                        && param
+                            .value
+                            .as_ref()
+                            .is_none_or(|locked| *value == locked.as_bytes())
+                })
+                .map(|(_, value)| *value)
+                .collect();
+            match matches[..] {
+                [value] => values.push(value),
+                _ => {
+                    return Err(format!(
+                        "request target should have exactly one query parameter matching `{}`, found {}",
+                        param.name,
+                        matches.len()
+                    )
+                    .into())
+                }
+            }
+        }
+
+        Ok(values)
+    }
+
+    /// Template matching a header name and value in the generated circuit
+    fn header_match_template(&self) -> &'static str {
+        match self.case_insensitive_headers() {
+            true => "HeaderFieldNameValueMatchCaseInsensitive",
+            false => "HeaderFieldNameValueMatch",
+        }
+    }
+
+    /// Template matching only a header name in the generated circuit
+    fn header_name_match_template(&self) -> &'static str {
+        match self.case_insensitive_headers() {
+            true => "HeaderFieldNameMatchCaseInsensitive",
+            false => "HeaderFieldNameMatch",
+        }
+    }
+
+    /// Value of each locked header in `input`, failing if a header doesn't match exactly one header
+    /// line, like the circuit would
+    pub fn header_values<'a>(
+        &self,
+        parts: &machine::http::HttpParts<'a>,
+    ) -> Result<Vec<&'a [u8]>, Box<dyn Error>> {
+        let mut values = vec![];
+        for header in self.headers() {
+            let matches: Vec<&[u8]> = parts
+                .headers
+                .iter()
+                .filter(|(name, value)| {
+                    header.matches(name, value, self.case_insensitive_headers())
+                })
+                .map(|(_, value)| *value)
+                .collect();
+            match matches[..] {
+                [value] => values.push(value),
+                _ => {
+                    return Err(format!(
+                        "input should have exactly one header line matching `{}` in {:?} mode, found {}",
+                        header.name,
+                        header.mode,
+                        matches.len()
+                    )
+                    .into())
+                }
+            }
+        }
+
+        Ok(values)
+    }
+
+    /// Whether the response body is chunked
+    pub fn chunked(&self) -> bool {
         match self {
-            HttpData::Request(request) => request.headers.clone(),
-            HttpData::Response(response) => response.headers.clone(),
+            HttpData::Request(_) => false,
+            HttpData::Response(response) => response.chunked,
+        }
+    }
+
+    /// Index of the locked `Content-Length` header checked against the body length, if the lockfile
+    /// sets `checkContentLength`
+    pub fn content_length_header(&self) -> Result<Option<usize>, Box<dyn Error>> {
+        match self {
+            HttpData::Response(response) if response.check_content_length => response
+                .headers
+                .iter()
+                .position(|header| {
+                    // a prefix or no value can't be checked against the body length
+                    header.name.eq_ignore_ascii_case("content-length")
+                        && matches!(header.mode, HeaderMode::Exact | HeaderMode::Extract)
+                })
+                .map(Some)
+                .ok_or_else(|| {
+                    String::from("`checkContentLength` needs a locked `Content-Length` header")
+                        .into()
+                }),
+            _ => Ok(None),
+        }
+    }
+
+    /// Commitment to the response body, if any
+    pub fn commitment(&self) -> Option<Commitment> {
+        match self {
+            HttpData::Request(_) => None,
+            HttpData::Response(response) => response.commitment,
         }
     }
 
@@ -49,6 +330,7 @@ impl HttpData {
         match self {
             HttpData::Request(_) => {
                 params.append(&mut vec![
+                    "maxContentLength".to_string(),
                     "methodLen".to_string(),
                     "targetLen".to_string(),
                     "versionLen".to_string(),
@@ -64,9 +346,18 @@ impl HttpData {
             }
         };
 
-        for i in 0..self.headers().len() {
+        for (i, header) in self.headers().iter().enumerate() {
             params.push(format!("headerNameLen{}", i + 1));
-            params.push(format!("headerValueLen{}", i + 1));
+            if header.mode != HeaderMode::Present {
+                params.push(format!("headerValueLen{}", i + 1));
+            }
+        }
+        for i in 0..self.forbidden_headers().len() {
+            params.push(format!("forbiddenHeaderNameLen{}", i + 1));
+        }
+        for i in 0..self.query_params().len() {
+            params.push(format!("queryNameLen{}", i + 1));
+            params.push(format!("queryValueLen{}", i + 1));
         }
 
         params
@@ -88,9 +379,20 @@ impl HttpData {
             ]),
         };
 
-        for (i, _header) in self.headers().iter().enumerate() {
+        for (i, header) in self.headers().iter().enumerate() {
             inputs.push(format!("header{}", i + 1));
-            inputs.push(format!("value{}", i + 1));
+            if header.mode.has_value() {
+                inputs.push(format!("value{}", i + 1));
+            }
+        }
+        for i in 0..self.forbidden_headers().len() {
+            inputs.push(format!("forbiddenHeader{}", i + 1));
+        }
+        for (i, param) in self.query_params().iter().enumerate() {
+            inputs.push(format!("queryName{}", i + 1));
+            if param.value.is_some() {
+                inputs.push(format!("queryValue{}", i + 1));
+            }
         }
 
         inputs
@@ -100,76 +402,159 @@ impl HttpData {
         &self,
         input: Vec<u8>,
     ) -> Result<(HttpData, Vec<u8>), Box<dyn std::error::Error>> {
-        let input_string = String::from_utf8(input)?;
-
-        let parts: Vec<&str> = input_string.split("\r\n\r\n").collect();
-        assert!(parts.len() <= 2);
-
-        let mut body = vec![];
-        if parts.len() == 2 {
-            body = parts[1].as_bytes().to_vec();
-        }
-
-        let headers: Vec<&str> = parts[0].split("\r\n").collect();
-        let start_line: Vec<&str> = headers[0].split(" ").collect();
-        assert_eq!(start_line.len(), 3);
-
-        let (_, headers) = headers.split_at(1);
-        let mut headers_map = BTreeMap::<String, String>::new();
-        let re = Regex::new(r":\s+").unwrap();
-        for &header in headers {
-            let key_value: Vec<&str> = re.split(header).collect();
-            assert_eq!(key_value.len(), 2);
-            headers_map.insert(key_value[0].to_string(), key_value[1].to_string());
+        let parts = machine::http::parse(&input)?;
+
+        let [first, second, third] = parts.start_line;
+        let start_line = [
+            String::from_utf8(first.to_vec())?,
+            String::from_utf8(second.to_vec())?,
+            String::from_utf8(third.to_vec())?,
+        ];
+
+        let mut headers = vec![];
+        for (name, value) in parts.headers {
+            let name = String::from_utf8(name.to_vec())?;
+            headers.push(Header::exact(
+                header_name(&name, self.case_insensitive_headers()),
+                String::from_utf8(value.to_vec())?,
+            ));
         }
+        let body = parts.body.to_vec();
 
         let http_data = match self {
-            HttpData::Request(_) => HttpData::Request(Request {
-                method: start_line[0].to_string(),
-                target: start_line[1].to_string(),
-                version: start_line[2].to_string(),
-                headers: headers_map,
-            }),
-            HttpData::Response(_) => HttpData::Response(Response {
-                version: start_line[0].to_string(),
-                status: start_line[1].to_string(),
-                message: start_line[2].to_string(),
-                headers: headers_map,
+            HttpData::Request(request) => {
+                // only the path is locked as the target if query parameters are
+                let (target, query_params) = match request.query_params.is_empty() {
+                    true => (start_line[1].clone(), vec![]),
+                    false => {
+                        let (path, pairs) = machine::http::split_target(parts.start_line[1]);
+                        let mut query_params = vec![];
+                        for (name, value) in pairs.unwrap_or_default() {
+                            query_params.push(QueryParam {
+                                name: String::from_utf8(name.to_vec())?,
+                                value: Some(String::from_utf8(value.to_vec())?),
+                            });
+                        }
+                        (String::from_utf8(path.to_vec())?, query_params)
+                    }
+                };
+                HttpData::Request(Request {
+                    method: start_line[0].clone(),
+                    target,
+                    version: start_line[2].clone(),
+                    headers: headers.clone(),
+                    case_insensitive_headers: self.case_insensitive_headers(),
+                    forbidden_headers: self.forbidden_headers().to_vec(),
+                    query_params,
+                })
+            }
+            HttpData::Response(response) => HttpData::Response(Response {
+                version: start_line[0].clone(),
+                status: start_line[1].clone(),
+                message: start_line[2].clone(),
+                headers,
+                case_insensitive_headers: response.case_insensitive_headers,
+                forbidden_headers: response.forbidden_headers.clone(),
+                commitment: response.commitment,
+                chunked: response.chunked,
+                check_content_length: response.check_content_length,
             }),
         };
 
         Ok((http_data, body))
     }
 
+    /// Body as passed to the JSON extractor, with the chunk framing removed if the lockfile sets
+    /// `chunked`
+    pub fn parse_payload(&self, input: Vec<u8>) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        let parts = machine::http::parse(&input)?;
+
+        match self.chunked() {
+            true if !parts.is_chunked() => Err(String::from(
+                "lockfile sets `chunked`, but the response isn't `Transfer-Encoding: chunked`",
+            )
+            .into()),
+            true => machine::http::dechunk(parts.body),
+            false => Ok(parts.body.to_vec()),
+        }
+    }
+
     pub fn populate_params(
         &self,
         input: Vec<u8>,
     ) -> Result<Vec<usize>, Box<dyn std::error::Error>> {
         let (_, http_body) = self.parse_input(input.clone())?;
 
+        // fail before compilation if the circuit wouldn't match a header, or would find a forbidden
+        // one
+        let parts = machine::http::parse(&input)?;
+        let header_values = self.header_values(&parts)?;
+        let query_values = self.query_values(&parts)?;
+        for forbidden in self.forbidden_headers() {
+            if parts
+                .headers
+                .iter()
+                .any(|(name, _)| name.eq_ignore_ascii_case(forbidden.as_bytes()))
+            {
+                return Err(format!("input has forbidden header `{}`", forbidden).into());
+            }
+        }
+
         let mut params = vec![input.len()];
 
         match self {
             HttpData::Request(request) => {
+                params.push(http_body.len());
                 params.push(request.method.len());
                 params.push(request.target.len());
                 params.push(request.version.len());
-                for (key, value) in request.headers.iter() {
-                    params.push(key.len());
-                    params.push(value.len());
-                }
             }
             HttpData::Response(response) => {
+                // fail before compilation if the circuit's check would
+                if let Some(index) = self.content_length_header()? {
+                    let value = String::from_utf8(header_values[index].to_vec())?;
+                    let content_length: usize = match value.bytes().all(|b| b.is_ascii_digit()) {
+                        true => value.parse().ok(),
+                        false => None,
+                    }
+                    .ok_or_else(|| {
+                        format!(
+                            "`Content-Length` value `{}` should be a decimal number",
+                            value
+                        )
+                    })?;
+                    if content_length != http_body.len() {
+                        return Err(format!(
+                            "`Content-Length` is {}, but the body has {} bytes",
+                            content_length,
+                            http_body.len()
+                        )
+                        .into());
+                    }
+                }
+
                 params.push(http_body.len());
                 params.push(response.version.len());
                 params.push(response.status.len());
                 params.push(response.message.len());
-                for (key, value) in response.headers.iter() {
-                    params.push(key.len());
-                    params.push(value.len());
-                }
             }
         }
+        // extracted values are as long as in the input
+        for (header, value) in self.headers().iter().zip(header_values) {
+            params.push(header.name.len());
+            match header.mode {
+                HeaderMode::Exact | HeaderMode::Prefix => params.push(header.value.len()),
+                HeaderMode::Extract => params.push(value.len()),
+                HeaderMode::Present => {}
+            }
+        }
+        for name in self.forbidden_headers() {
+            params.push(name.len());
+        }
+        for (param, value) in self.query_params().iter().zip(query_values) {
+            params.push(param.name.len());
+            params.push(value.len());
+        }
 
         Ok(params)
     }
@@ -183,6 +568,9 @@ impl HttpData {
 
         let circuit_template_name = match self {
             HttpData::Request(_) => String::from("LockHTTPRequest"),
+            HttpData::Response(response) if response.commitment.is_some() => {
+                String::from("LockHTTPResponseCommitment")
+            }
             HttpData::Response(_) => String::from("LockHTTPResponse"),
         };
 
@@ -190,6 +578,7 @@ impl HttpData {
             file: format!("main/{}", codegen_filename),
             template: circuit_template_name,
             params: self.populate_params(input)?,
+            pubs: vec![],
         })
     }
 }
@@ -209,15 +598,30 @@ impl Serialize for Request {
         S: serde::Serializer,
     {
         use serde::ser::SerializeMap;
-        let mut map = serializer.serialize_map(Some(3 + self.headers.len() * 2))?;
+        let mut map = serializer.serialize_map(Some(
+            3 + self.headers.len() * 2 + self.forbidden_headers.len() + self.query_params.len() * 2,
+        ))?;
 
         map.serialize_entry("method", self.method.as_bytes())?;
         map.serialize_entry("target", self.target.as_bytes())?;
         map.serialize_entry("version", self.version.as_bytes())?;
 
-        for (i, (key, value)) in self.headers.iter().enumerate() {
-            map.serialize_entry(&format!("header{}", i + 1), key.as_bytes())?;
-            map.serialize_entry(&format!("value{}", i + 1), value.as_bytes())?;
+        for (i, header) in self.headers.iter().enumerate() {
+            let name = header_name(&header.name, self.case_insensitive_headers);
+            map.serialize_entry(&format!("header{}", i + 1), name.as_bytes())?;
+            if header.mode.has_value() {
+                map.serialize_entry(&format!("value{}", i + 1), header.value.as_bytes())?;
+            }
+        }
+        for (i, name) in self.forbidden_headers.iter().enumerate() {
+            let name = header_name(name, true);
+            map.serialize_entry(&format!("forbiddenHeader{}", i + 1), name.as_bytes())?;
+        }
+        for (i, param) in self.query_params.iter().enumerate() {
+            map.serialize_entry(&format!("queryName{}", i + 1), param.name.as_bytes())?;
+            if let Some(value) = &param.value {
+                map.serialize_entry(&format!("queryValue{}", i + 1), value.as_bytes())?;
+            }
         }
         map.end()
     }
@@ -229,47 +633,108 @@ impl Serialize for Response {
         S: serde::Serializer,
     {
         use serde::ser::SerializeMap;
-        let mut map = serializer.serialize_map(Some(3 + self.headers.len() * 2))?;
+        let mut map = serializer.serialize_map(Some(
+            3 + self.headers.len() * 2 + self.forbidden_headers.len(),
+        ))?;
 
         map.serialize_entry("version", self.version.as_bytes())?;
         map.serialize_entry("status", self.status.as_bytes())?;
         map.serialize_entry("message", self.message.as_bytes())?;
 
-        for (i, (key, value)) in self.headers.iter().enumerate() {
-            map.serialize_entry(&format!("header{}", i + 1), key.as_bytes())?;
-            map.serialize_entry(&format!("value{}", i + 1), value.as_bytes())?;
+        for (i, header) in self.headers.iter().enumerate() {
+            let name = header_name(&header.name, self.case_insensitive_headers);
+            map.serialize_entry(&format!("header{}", i + 1), name.as_bytes())?;
+            if header.mode.has_value() {
+                map.serialize_entry(&format!("value{}", i + 1), header.value.as_bytes())?;
+            }
+        }
+        for (i, name) in self.forbidden_headers.iter().enumerate() {
+            let name = header_name(name, true);
+            map.serialize_entry(&format!("forbiddenHeader{}", i + 1), name.as_bytes())?;
         }
         map.end()
     }
 }
 
-fn deserialize_headers<'de, D>(deserializer: D) -> Result<BTreeMap<String, String>, D::Error>
+/// Header name as matched by the circuit, lowercased if names are case-insensitive
+fn header_name(name: &str, case_insensitive: bool) -> String {
+    match case_insensitive {
+        true => name.to_ascii_lowercase(),
+        false => name.to_string(),
+    }
+}
+
+fn deserialize_headers<'de, D>(deserializer: D) -> Result<Vec<Header>, D::Error>
 where
     D: serde::Deserializer<'de>,
 {
-    let mut map = BTreeMap::new();
+    use serde::de::Error;
+
+    let mut headers = vec![];
     let mut temp_map: BTreeMap<String, String> = BTreeMap::deserialize(deserializer)?;
 
     let mut i = 1;
-    while let (Some(name), Some(value)) = (
-        temp_map.remove(&format!("headerName{}", i)),
-        temp_map.remove(&format!("headerValue{}", i)),
-    ) {
-        map.insert(name, value);
+    while let Some(name) = temp_map.remove(&format!("headerName{}", i)) {
+        let mode: HeaderMode = match temp_map.remove(&format!("headerMode{}", i)) {
+            Some(mode) => mode.parse().map_err(D::Error::custom)?,
+            None => HeaderMode::default(),
+        };
+        let value = match (
+            mode.has_value(),
+            temp_map.remove(&format!("headerValue{}", i)),
+        ) {
+            (true, Some(value)) => value,
+            (false, None) => String::new(),
+            (true, None) => {
+                return Err(D::Error::custom(format!(
+                    "header `{}` in {:?} mode needs `headerValue{}`",
+                    name, mode, i
+                )))
+            }
+            (false, Some(_)) => {
+                return Err(D::Error::custom(format!(
+                    "header `{}` in {:?} mode shouldn't have `headerValue{}`",
+                    name, mode, i
+                )))
+            }
+        };
+        headers.push(Header { name, value, mode });
         i += 1;
     }
 
-    Ok(map)
+    // a gap in the numbering would silently drop the headers after it
+    if let Some(key) = temp_map.keys().find(|key| key.starts_with("header")) {
+        return Err(D::Error::custom(format!(
+            "`{}` should be numbered after header {}",
+            key,
+            i - 1
+        )));
+    }
+
+    Ok(headers)
 }
 
+/// Builds the HTTP circuit. With `padded`, `data` is zero padded after its first `dataLen` bytes.
 fn build_http_circuit(
     config: &CircomkitCircuitConfig,
     data: &HttpData,
     output_filename: &str,
+    padded: bool,
     debug: bool,
 ) -> Result<(), Box<dyn std::error::Error>> {
+    let content_length_header = data.content_length_header()?;
     let mut circuit_buffer = String::new();
 
+    let data_len = match padded {
+        true => "dataLen",
+        false => "DATA_BYTES",
+    };
+    // header values can also end with the unpadded data
+    let header_value_end = match padded {
+        true => "IsHeaderValueEndPadded(DATA_BYTES)(data, dataLen",
+        false => "IsHeaderValueEnd(DATA_BYTES)(data",
+    };
+
     // Dump out the contents of the lockfile used into the circuit
     circuit_buffer += "/*\n";
     circuit_buffer += &format!("{:#?}", data);
@@ -282,7 +747,11 @@ fn build_http_circuit(
     circuit_buffer += "include \"../utils/bytes.circom\";\n";
     circuit_buffer += "include \"../utils/search.circom\";\n";
     circuit_buffer += "include \"circomlib/circuits/gates.circom\";\n";
-    circuit_buffer += "include \"@zk-email/circuits/utils/array.circom\";\n\n";
+    circuit_buffer += "include \"@zk-email/circuits/utils/array.circom\";\n";
+    if data.commitment().is_some() {
+        circuit_buffer += "include \"../utils/hash.circom\";\n";
+    }
+    circuit_buffer += "\n";
 
     {
         let params = data.params();
@@ -294,6 +763,15 @@ fn build_http_circuit(
     // Raw HTTP bytestream
     signal input data[DATA_BYTES];
 "#;
+        if padded {
+            circuit_buffer += r#"
+    // Number of bytes in `data` before the zero padding
+    signal input dataLen;
+    component zeroPadding = AssertZeroPadded(DATA_BYTES);
+    zeroPadding.in     <== data;
+    zeroPadding.length <== dataLen;
+"#;
+        }
 
         // Start line signals
         {
@@ -321,29 +799,94 @@ fn build_http_circuit(
 
         // Header signals
         circuit_buffer += "    // Header names and values to lock\n";
-        for (i, _header) in data.headers().iter().enumerate() {
+        for (i, header) in data.headers().iter().enumerate() {
             circuit_buffer += &format!(
                 "    signal input header{}[headerNameLen{}];\n",
                 i + 1,
                 i + 1
             );
+            if header.mode.has_value() {
+                circuit_buffer += &format!(
+                    "    signal input value{}[headerValueLen{}];\n",
+                    i + 1,
+                    i + 1
+                );
+            }
+        }
+        if !data.forbidden_headers().is_empty() {
+            circuit_buffer += "\n    // Header names proven absent, in lowercase\n";
+        }
+        for i in 0..data.forbidden_headers().len() {
             circuit_buffer += &format!(
-                "    signal input value{}[headerValueLen{}];\n",
+                "    signal input forbiddenHeader{}[forbiddenHeaderNameLen{}];\n",
                 i + 1,
                 i + 1
             );
         }
+
+        // Query parameter signals
+        if !data.query_params().is_empty() {
+            circuit_buffer += "\n    // Query parameter names and values to lock\n";
+        }
+        for (i, param) in data.query_params().iter().enumerate() {
+            circuit_buffer += &format!(
+                "    signal input queryName{i}[queryNameLen{i}];\n",
+                i = i + 1
+            );
+            if param.value.is_some() {
+                circuit_buffer += &format!(
+                    "    signal input queryValue{i}[queryValueLen{i}];\n",
+                    i = i + 1
+                );
+            }
+        }
     }
 
-    // Create an output if circuit is for `Response`
+    // Create an output for the body
     {
-        if let HttpData::Response(_) = data {
-            circuit_buffer += r#"
-    // Set up mask bits for where the body of response lies
-    signal output body[maxContentLength];
+        let visibility = match data.commitment() {
+            // only the commitment to the body is output
+            Some(_) => "",
+            None => "output ",
+        };
+        circuit_buffer += &format!(
+            r#"
+    // Set up mask bits for where the body lies
+    signal {visibility}body[maxContentLength];
 
     signal bodyMask[DATA_BYTES];
-"#;
+"#
+        );
+    }
+
+    // Create outputs for extracted header values, after the body
+    {
+        if data.headers().iter().any(|h| h.mode == HeaderMode::Extract) {
+            circuit_buffer += "\n    // Extracted header values\n";
+        }
+        for (i, header) in data.headers().iter().enumerate() {
+            if header.mode == HeaderMode::Extract {
+                circuit_buffer += &format!(
+                    "    signal output value{}[headerValueLen{}];\n",
+                    i + 1,
+                    i + 1
+                );
+            }
+        }
+        if data
+            .query_params()
+            .iter()
+            .any(|param| param.value.is_none())
+        {
+            circuit_buffer += "\n    // Extracted query parameter values\n";
+        }
+        for (i, param) in data.query_params().iter().enumerate() {
+            if param.value.is_none() {
+                circuit_buffer += &format!(
+                    "    signal output queryValue{i}[queryValueLen{i}];\n",
+                    i = i + 1
+                );
+            }
         }
     }
 
@@ -386,14 +929,74 @@ fn build_http_circuit(
             }
         }
 
+        // Headers are only matched at the start of header lines
+        if !data.headers().is_empty() || !data.forbidden_headers().is_empty() {
+            circuit_buffer += "    signal isHeaderLineStart[DATA_BYTES];\n";
+            circuit_buffer += "    isHeaderLineStart[0] <== 0;\n\n";
+        }
+
         // Create header match signals
         {
-            for (i, _header) in data.headers().iter().enumerate() {
-                circuit_buffer +=
-                    &format!("    signal headerNameValueMatch{}[DATA_BYTES];\n", i + 1);
-                circuit_buffer += &format!("    var hasMatchedHeaderValue{} = 0;\n\n", i + 1);
+            for (i, header) in data.headers().iter().enumerate() {
+                let raw_match = match header.mode {
+                    HeaderMode::Exact | HeaderMode::Prefix => "headerNameValueMatch",
+                    HeaderMode::Present | HeaderMode::Extract => "headerNameMatch",
+                };
+                circuit_buffer += &format!("    signal {raw_match}{i}[DATA_BYTES];\n", i = i + 1);
+                circuit_buffer += &format!("    {raw_match}{i}[0] <== 0;\n", i = i + 1);
+                if header.mode == HeaderMode::Exact {
+                    circuit_buffer += &format!(
+                        "    signal headerValueEnd{i}[DATA_BYTES];\n    signal headerExactMatch{i}[DATA_BYTES];\n    headerValueEnd{i}[0] <== 0;\n    headerExactMatch{i}[0] <== 0;\n",
+                        i = i + 1
+                    );
+                }
+                circuit_buffer += &format!(
+                    "    signal headerMatch{i}[DATA_BYTES];\n    headerMatch{i}[0] <== 0;\n",
+                    i = i + 1
+                );
+                circuit_buffer += &format!("    var hasMatchedHeader{} = 0;\n", i + 1);
+                if header.mode == HeaderMode::Extract {
+                    circuit_buffer += &format!(
+                        "    signal headerValueIndex{i}[DATA_BYTES];\n    headerValueIndex{i}[0] <== 0;\n    var headerValueStart{i} = 0;\n",
+                        i = i + 1
+                    );
+                }
+                circuit_buffer += "\n";
             }
         }
+
+        // Create forbidden header match signals
+        for i in 0..data.forbidden_headers().len() {
+            circuit_buffer += &format!("    signal forbiddenHeaderMatch{}[DATA_BYTES];\n", i + 1);
+            circuit_buffer += &format!(
+                "    signal forbiddenHeaderLineMatch{}[DATA_BYTES];\n",
+                i + 1
+            );
+            circuit_buffer += &format!("    forbiddenHeaderMatch{}[0] <== 0;\n", i + 1);
+            circuit_buffer += &format!("    forbiddenHeaderLineMatch{}[0] <== 0;\n", i + 1);
+            circuit_buffer += &format!("    var hasForbiddenHeader{} = 0;\n\n", i + 1);
+        }
+
+        // Create query parameter match signals, for pairs after the first `?` and each `&`
+        if !data.query_params().is_empty() {
+            circuit_buffer += "    signal inQuery[DATA_BYTES];\n";
+            circuit_buffer += "    signal isQueryPairStart[DATA_BYTES];\n";
+            circuit_buffer += "    inQuery[0] <== 0;\n";
+            circuit_buffer += "    isQueryPairStart[0] <== 0;\n\n";
+        }
+        for (i, param) in data.query_params().iter().enumerate() {
+            circuit_buffer += &format!(
+                "    signal queryMatch{i}[DATA_BYTES];\n    signal queryPairMatch{i}[DATA_BYTES];\n    queryMatch{i}[0] <== 0;\n    queryPairMatch{i}[0] <== 0;\n    var hasMatchedQuery{i} = 0;\n",
+                i = i + 1
+            );
+            if param.value.is_none() {
+                circuit_buffer += &format!(
+                    "    signal queryValueIndex{i}[DATA_BYTES];\n    queryValueIndex{i}[0] <== 0;\n    var queryValueStart{i} = 0;\n",
+                    i = i + 1
+                );
+            }
+            circuit_buffer += "\n";
+        }
     }
 
     circuit_buffer += r#"    component State[DATA_BYTES];
@@ -407,13 +1010,14 @@ fn build_http_circuit(
     State[0].line_status         <== 0;
 "#;
 
-    // If parsing a `Response`, create a mask of the body bytes
+    // Create a mask of the body bytes
     {
-        if let HttpData::Response(_) = data {
-            circuit_buffer += r#"
-    // Mask if parser is in the body of response
+        circuit_buffer += r#"
+    // Mask if parser is in the body
     bodyMask[0] <== data[0] * State[0].next_parsing_body;
 "#;
+        if content_length_header.is_some() {
+            circuit_buffer += "    var body_length = State[0].next_parsing_body;\n";
         }
     }
 
@@ -459,18 +1063,6 @@ fn build_http_circuit(
 "#;
             }
         }
-
-        // Header matches
-        {
-            for (i, _header) in data.headers().iter().enumerate() {
-                circuit_buffer += &format!("    headerNameValueMatch{}[0]    <== HeaderFieldNameValueMatch(DATA_BYTES, headerNameLen{}, headerValueLen{})(data, header{}, value{}, 0);\n", i + 1, i + 1, i + 1, i + 1, i + 1);
-                circuit_buffer += &format!(
-                    "    hasMatchedHeaderValue{}      += headerNameValueMatch{}[0];\n",
-                    i + 1,
-                    i + 1
-                );
-            }
-        }
     }
 
     // Intro loop
@@ -489,13 +1081,14 @@ fn build_http_circuit(
 "#;
     }
 
-    // If parsing a `Response`, create a mask of the body bytes
+    // Create a mask of the body bytes
     {
-        if let HttpData::Response(_) = data {
-            circuit_buffer += r#"
-        // Mask if parser is in the body of response
+        circuit_buffer += r#"
+        // Mask if parser is in the body
         bodyMask[data_idx] <== data[data_idx] * State[data_idx].next_parsing_body;
 "#;
+        if content_length_header.is_some() {
+            circuit_buffer += "        body_length += State[data_idx].next_parsing_body;\n";
         }
     }
 
@@ -543,18 +1136,86 @@ fn build_http_circuit(
         }
     }
 
-    // Header matches
+    // Header matches, only at the start of header lines
     {
-        for (i, _header) in data.headers().iter().enumerate() {
-            circuit_buffer += &format!("        headerNameValueMatch{}[data_idx] <== HeaderFieldNameValueMatch(DATA_BYTES, headerNameLen{}, headerValueLen{})(data, header{}, value{}, data_idx);\n", i + 1, i + 1, i + 1, i + 1, i + 1);
+        if !data.headers().is_empty() || !data.forbidden_headers().is_empty() {
+            circuit_buffer += "        isHeaderLineStart[data_idx] <== IsHeaderLineStart()(data[data_idx - 1], State[data_idx].parsing_header);\n";
+        }
+        for (i, header) in data.headers().iter().enumerate() {
+            let raw_match = match header.mode {
+                HeaderMode::Exact | HeaderMode::Prefix => {
+                    circuit_buffer += &format!("        headerNameValueMatch{i}[data_idx] <== {}(DATA_BYTES, headerNameLen{i}, headerValueLen{i})(data, header{i}, value{i}, data_idx);\n", data.header_match_template(), i = i + 1);
+                    "headerNameValueMatch"
+                }
+                HeaderMode::Present | HeaderMode::Extract => {
+                    circuit_buffer += &format!("        headerNameMatch{i}[data_idx] <== {}(DATA_BYTES, headerNameLen{i})(data, header{i}, data_idx);\n", data.header_name_match_template(), i = i + 1);
+                    "headerNameMatch"
+                }
+            };
+            let raw_match = match header.mode {
+                // exact values end where the header line does
+                HeaderMode::Exact => {
+                    circuit_buffer += &format!("        headerValueEnd{i}[data_idx] <== {}, data_idx + headerNameLen{i} + 2 + headerValueLen{i});\n", header_value_end, i = i + 1);
+                    circuit_buffer += &format!("        headerExactMatch{i}[data_idx] <== headerNameValueMatch{i}[data_idx] * headerValueEnd{i}[data_idx];\n", i = i + 1);
+                    "headerExactMatch"
+                }
+                _ => raw_match,
+            };
             circuit_buffer += &format!(
-                "        hasMatchedHeaderValue{} += headerNameValueMatch{}[data_idx];\n",
-                i + 1,
-                i + 1
+                "        headerMatch{i}[data_idx] <== isHeaderLineStart[data_idx] * {raw_match}{i}[data_idx];\n",
+                i = i + 1
+            );
+            circuit_buffer += &format!(
+                "        hasMatchedHeader{i} += headerMatch{i}[data_idx];\n",
+                i = i + 1
+            );
+            if header.mode == HeaderMode::Extract {
+                circuit_buffer += &format!("        headerValueIndex{i}[data_idx] <== headerMatch{i}[data_idx] * (data_idx + headerNameLen{i} + 2);\n", i = i + 1);
+                circuit_buffer += &format!(
+                    "        headerValueStart{i} += headerValueIndex{i}[data_idx];\n",
+                    i = i + 1
+                );
+            }
+        }
+    }
+
+    // Forbidden header matches, only at the start of header lines
+    {
+        for i in 0..data.forbidden_headers().len() {
+            circuit_buffer += &format!("        forbiddenHeaderMatch{i}[data_idx] <== HeaderFieldNameMatchCaseInsensitive(DATA_BYTES, forbiddenHeaderNameLen{i})(data, forbiddenHeader{i}, data_idx);\n", i = i + 1);
+            circuit_buffer += &format!("        forbiddenHeaderLineMatch{i}[data_idx] <== isHeaderLineStart[data_idx] * forbiddenHeaderMatch{i}[data_idx];\n", i = i + 1);
+            circuit_buffer += &format!(
+                "        hasForbiddenHeader{i} += forbiddenHeaderLineMatch{i}[data_idx];\n",
+                i = i + 1
             );
         }
     }
 
+    // Query parameter matches, only at the start of `name=value` pairs
+    {
+        if !data.query_params().is_empty() {
+            circuit_buffer += "        (inQuery[data_idx], isQueryPairStart[data_idx]) <== QueryPairStart()(data[data_idx - 1], inQuery[data_idx - 1], targetMask[data_idx]);\n";
+        }
+        for (i, param) in data.query_params().iter().enumerate() {
+            match param.value {
+                Some(_) => circuit_buffer += &format!("        queryMatch{i}[data_idx] <== QueryParamMatch(DATA_BYTES, queryNameLen{i}, queryValueLen{i})(data, queryName{i}, queryValue{i}, data_idx);\n", i = i + 1),
+                None => circuit_buffer += &format!("        queryMatch{i}[data_idx] <== QueryParamNameMatch(DATA_BYTES, queryNameLen{i})(data, queryName{i}, data_idx);\n", i = i + 1),
+            }
+            circuit_buffer += &format!("        queryPairMatch{i}[data_idx] <== isQueryPairStart[data_idx] * queryMatch{i}[data_idx];\n", i = i + 1);
+            circuit_buffer += &format!(
+                "        hasMatchedQuery{i} += queryPairMatch{i}[data_idx];\n",
+                i = i + 1
+            );
+            if param.value.is_none() {
+                circuit_buffer += &format!("        queryValueIndex{i}[data_idx] <== queryPairMatch{i}[data_idx] * (data_idx + queryNameLen{i} + 1);\n", i = i + 1);
+                circuit_buffer += &format!(
+                    "        queryValueStart{i} += queryValueIndex{i}[data_idx];\n",
+                    i = i + 1
+                );
+            }
+        }
+    }
+
     // debugging
     if debug {
         circuit_buffer += r#"
@@ -594,8 +1255,7 @@ fn build_http_circuit(
 
     // Get the output body bytes
     {
-        if let HttpData::Response(_) = data {
-            circuit_buffer += r#"
+        circuit_buffer += r#"
 
     signal bodyStartingIndex[DATA_BYTES];
     signal isZeroMask[DATA_BYTES];
@@ -609,7 +1269,16 @@ fn build_http_circuit(
         bodyStartingIndex[i] <== bodyStartingIndex[i-1] + i * (1-isZeroMask[i]) * isPrevStartingIndex[i];
     }
 
-    body <== SelectSubArray(DATA_BYTES, maxContentLength)(bodyMask, bodyStartingIndex[DATA_BYTES-1]+1, DATA_BYTES - bodyStartingIndex[DATA_BYTES-1]);
+"#;
+        // padding is masked as body too, but zero
+        circuit_buffer += &format!(
+            "    body <== SelectSubArray(DATA_BYTES, maxContentLength)(bodyMask, bodyStartingIndex[DATA_BYTES-1]+1, {} - bodyStartingIndex[DATA_BYTES-1]);\n",
+            data_len
+        );
+
+        if let Some(Commitment::Poseidon) = data.commitment() {
+            circuit_buffer += r#"
+    signal output bodyCommitment <== PoseidonModular(maxContentLength)(body);
 "#;
         }
     }
@@ -625,7 +1294,7 @@ fn build_http_circuit(
     // Verify all start line has matched
     {
         match data {
-            HttpData::Request(_) => {
+            HttpData::Request(request) => {
                 circuit_buffer += r#"
     // Verify method had correct length
     methodLen === target_start_counter;
@@ -633,7 +1302,13 @@ fn build_http_circuit(
     // Check target is correct by substring match and length check
     signal targetMatch <== SubstringMatchWithIndex(DATA_BYTES, targetLen)(data, target, target_start_counter + 1);
     targetMatch        === 1;
-    targetLen          === target_end_counter - target_start_counter - 1;
+"#;
+                circuit_buffer += match request.query_params.is_empty() {
+                    true => "    targetLen          === target_end_counter - target_start_counter - 1;\n",
+                    // the target is only the path, and the query follows it
+                    false => "    signal queryStart <== IndexSelector(DATA_BYTES)(data, target_start_counter + 1 + targetLen);\n    queryStart         === 63;\n",
+                };
+                circuit_buffer += r#"
 
     // Check version is correct by substring match and length check
     signal versionMatch <== SubstringMatchWithIndex(DATA_BYTES, versionLen)(data, version, target_end_counter + 1);
@@ -652,9 +1327,12 @@ fn build_http_circuit(
     statusMatch        === 1;
     statusLen          === status_end_counter - status_start_counter - 1;
 
-    // Check message is correct by substring match and length check
-    signal messageMatch <== SubstringMatchWithIndex(DATA_BYTES, messageLen)(data, message, status_end_counter + 1);
-    messageMatch        === 1;
+    // Check message is correct by substring match and length check. The message (reason phrase)
+    // runs until CRLF, so it may contain SP or be empty.
+    if (messageLen > 0) {
+        signal messageMatch <== SubstringMatchWithIndex(DATA_BYTES, messageLen)(data, message, status_end_counter + 1);
+        messageMatch        === 1;
+    }
     // -2 here for the CRLF
     messageLen          === message_end_counter - status_end_counter - 2;
 "#;
@@ -662,12 +1340,78 @@ fn build_http_circuit(
         }
     }
 
-    // Verify all headers have matched
+    // Verify all headers have matched exactly one header line
     {
         for (i, _header) in data.headers().iter().enumerate() {
-            circuit_buffer += &format!("    hasMatchedHeaderValue{} === 1;\n", i + 1);
+            circuit_buffer += &format!("    hasMatchedHeader{} === 1;\n", i + 1);
+        }
+    }
+
+    // Output extracted header values, which end where their header line does
+    {
+        for (i, header) in data.headers().iter().enumerate() {
+            if header.mode == HeaderMode::Extract {
+                circuit_buffer += &format!(
+                    r#"
+    signal headerValueEnd{i} <== {header_value_end}, headerValueStart{i} + headerValueLen{i});
+    headerValueEnd{i} === 1;
+    value{i} <== SelectSubArray(DATA_BYTES, headerValueLen{i})(data, headerValueStart{i}, headerValueLen{i});
+"#,
+                    i = i + 1
+                );
+            }
         }
     }
+
+    // Verify no header line has a forbidden name
+    {
+        for i in 0..data.forbidden_headers().len() {
+            circuit_buffer += &format!("    hasForbiddenHeader{} === 0;\n", i + 1);
+        }
+    }
+
+    // Verify all query parameters have matched exactly one pair, and output extracted values, which
+    // end at the next `&` or the end of the target
+    {
+        for (i, param) in data.query_params().iter().enumerate() {
+            circuit_buffer += &format!("    hasMatchedQuery{} === 1;\n", i + 1);
+            if param.value.is_none() {
+                circuit_buffer += &format!(
+                    r#"
+    signal queryValueEnd{i} <== IsQueryValueEnd(DATA_BYTES)(data, queryValueStart{i} + queryValueLen{i});
+    queryValueEnd{i} === 1;
+    queryValue{i} <== SelectSubArray(DATA_BYTES, queryValueLen{i})(data, queryValueStart{i}, queryValueLen{i});
+"#,
+                    i = i + 1
+                );
+            }
+        }
+    }
+
+    // Verify the locked `Content-Length` value is the body length
+    if let Some(index) = content_length_header {
+        circuit_buffer += &format!(
+            r#"
+    // Parse `Content-Length` as a decimal number
+    signal contentLengthIsDigit[headerValueLen{i}];
+    var content_length = 0;
+    for (var i = 0 ; i < headerValueLen{i} ; i++) {{
+        contentLengthIsDigit[i] <== InRange(8)(value{i}[i], [48, 57]);
+        contentLengthIsDigit[i] === 1;
+        content_length = content_length * 10 + value{i}[i] - 48;
+    }}
+
+    // LF ending the headers is masked as body too
+    content_length === {body_length} - 1;
+"#,
+            i = index + 1,
+            // padding is parsed as body too
+            body_length = match padded {
+                true => "body_length - (DATA_BYTES - dataLen)",
+                false => "body_length",
+            }
+        );
+    }
     // End file
     circuit_buffer += "\n}";
 
@@ -697,8 +1441,13 @@ pub fn http_circuit_from_args(
 
     let codegen_filename = format!("http_{}", args.circuit_name);
 
-    let config =
-        http_circuit_from_lockfile(&args.input_file, &http_data, &codegen_filename, args.debug)?;
+    let config = http_circuit_from_lockfile(
+        &args.input_file,
+        &http_data,
+        &codegen_filename,
+        &args.max_sizes,
+        args.debug,
+    )?;
 
     config.write(&args.circuit_name)?;
 
@@ -709,11 +1458,19 @@ pub fn http_circuit_from_lockfile(
     input_file: &Path,
     http_data: &HttpData,
     codegen_filename: &str,
+    max_sizes: &MaxSizes,
     debug: bool,
 ) -> Result<CircomkitCircuitConfig, Box<dyn std::error::Error>> {
-    let config = http_data.build_circuit_config(input_file, codegen_filename)?;
+    let mut config = http_data.build_circuit_config(input_file, codegen_filename)?;
+    max_sizes.apply(&http_data.params(), &mut config.params)?;
 
-    build_http_circuit(&config, http_data, codegen_filename, debug)?;
+    build_http_circuit(
+        &config,
+        http_data,
+        codegen_filename,
+        max_sizes.is_padded(),
+        debug,
+    )?;
 
     Ok(config)
 }
@@ -721,6 +1478,7 @@ pub fn http_circuit_from_lockfile(
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::codegen::integrated::ExtendedLockfile;
 
     #[test]
     fn params() {
@@ -762,8 +1520,30 @@ mod test {
 
         let params = lockfile.populate_params(input.to_vec()).unwrap();
 
-        assert_eq!(params.len(), 8);
-        assert_eq!(params, [input.len(), 3, 4, 8, 6, 16, 4, 9]);
+        assert_eq!(params.len(), 9);
+        assert_eq!(params, [input.len(), 0, 3, 4, 8, 4, 9, 6, 16]);
+    }
+
+    #[test]
+    fn request_body() {
+        let lockfile: HttpData = serde_json::from_slice(include_bytes!(
+            "../../examples/http/lockfile/post_request.lock.json"
+        ))
+        .unwrap();
+        assert_eq!(
+            lockfile.params()[..3],
+            ["DATA_BYTES", "maxContentLength", "methodLen"]
+        );
+
+        let input = include_bytes!("../../examples/http/post_request.http");
+        let (_, body) = lockfile.parse_input(input.to_vec()).unwrap();
+        assert_eq!(
+            body,
+            b"name=Joe%20User&request=Send%20me%20one%20of%20your%20catalogue"
+        );
+
+        let params = lockfile.populate_params(input.to_vec()).unwrap();
+        assert_eq!(params, [input.len(), body.len(), 4, 17, 8, 12, 33]);
     }
 
     #[test]
@@ -778,6 +1558,354 @@ mod test {
         let (http, body) = lockfile.parse_input(input.to_vec()).unwrap();
 
         assert_eq!(body.len(), 0);
-        assert_eq!(http.headers()["Accept"], "application/json");
+        assert_eq!(
+            http.headers(),
+            [
+                Header::exact(String::from("Accept"), String::from("application/json")),
+                Header::exact(String::from("Host"), String::from("localhost")),
+            ]
+        );
+    }
+
+    #[test]
+    fn case_insensitive_headers() {
+        let lockfile: HttpData = serde_json::from_str(
+            r#"{
+                "version": "HTTP/1.1",
+                "status": "200",
+                "message": "OK",
+                "headerName1": "Content-Type",
+                "headerValue1": "application/json",
+                "caseInsensitiveHeaders": true
+            }"#,
+        )
+        .unwrap();
+        assert!(lockfile.case_insensitive_headers());
+        assert_eq!(
+            lockfile.header_match_template(),
+            "HeaderFieldNameValueMatchCaseInsensitive"
+        );
+
+        // the circuit compares against lowercase names
+        let witness = serde_json::to_value(&lockfile).unwrap();
+        assert_eq!(witness["header1"], serde_json::json!(b"content-type"));
+        assert_eq!(witness["value1"], serde_json::json!(b"application/json"));
+
+        let input = b"HTTP/1.1 200 OK\r\nCONTENT-TYPE: application/json\r\n\r\n";
+        let (http, _) = lockfile.parse_input(input.to_vec()).unwrap();
+        assert_eq!(
+            http.headers(),
+            [Header::exact(
+                String::from("content-type"),
+                String::from("application/json")
+            )]
+        );
+
+        let lockfile: HttpData = serde_json::from_slice(include_bytes!(
+            "../../examples/http/lockfile/response.lock.json"
+        ))
+        .unwrap();
+        assert!(!lockfile.case_insensitive_headers());
+        assert_eq!(
+            lockfile.header_match_template(),
+            "HeaderFieldNameValueMatch"
+        );
+    }
+
+    #[test]
+    fn parse_payload() {
+        let lockfile: ExtendedLockfile = serde_json::from_slice(include_bytes!(
+            "../../examples/http/lockfile/chunked_extended.lock.json"
+        ))
+        .unwrap();
+        assert!(lockfile.http.chunked());
+
+        let input = include_bytes!("../../examples/http/chunked_response.http");
+        let payload = lockfile.http.parse_payload(input.to_vec()).unwrap();
+        assert_eq!(
+            payload,
+            br#"{"data": {"items": [{"profile": {"name": "Taylor Swift"}}]}}"#
+        );
+
+        // the raw body is still locked by the HTTP circuit
+        let (_, body) = lockfile.http.parse_input(input.to_vec()).unwrap();
+        assert_eq!(body.len(), 77);
+
+        let lockfile: HttpData = serde_json::from_slice(include_bytes!(
+            "../../examples/http/lockfile/response.lock.json"
+        ))
+        .unwrap();
+        let input = include_bytes!("../../examples/http/get_response.http");
+        assert_eq!(
+            lockfile.parse_payload(input.to_vec()).unwrap(),
+            br#"{"success":"true"}"#
+        );
+    }
+
+    #[test]
+    fn check_content_length() {
+        let lockfile: HttpData = serde_json::from_slice(include_bytes!(
+            "../../examples/http/lockfile/content_length.lock.json"
+        ))
+        .unwrap();
+        assert_eq!(lockfile.content_length_header().unwrap(), Some(1));
+
+        let input = include_bytes!("../../examples/http/not_found_response.http");
+        let params = lockfile.populate_params(input.to_vec()).unwrap();
+        assert_eq!(params[1], 21);
+
+        // one byte more than `Content-Length`
+        let mut input = input.to_vec();
+        input.push(b' ');
+        let err = lockfile.populate_params(input).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "`Content-Length` is 21, but the body has 22 bytes"
+        );
+
+        let lockfile: HttpData = serde_json::from_str(
+            r#"{
+                "version": "HTTP/1.1",
+                "status": "404",
+                "message": "Not Found",
+                "headerName1": "Content-Type",
+                "headerValue1": "application/json",
+                "checkContentLength": true
+            }"#,
+        )
+        .unwrap();
+        assert!(lockfile.content_length_header().is_err());
+    }
+
+    #[test]
+    fn forbidden_headers() {
+        let lockfile: HttpData = serde_json::from_slice(include_bytes!(
+            "../../examples/http/lockfile/forbidden_headers.lock.json"
+        ))
+        .unwrap();
+        assert_eq!(lockfile.forbidden_headers(), ["Set-Cookie", "X-Cache"]);
+        assert_eq!(
+            lockfile.params()[lockfile.params().len() - 2..],
+            ["forbiddenHeaderNameLen1", "forbiddenHeaderNameLen2"]
+        );
+        assert_eq!(
+            lockfile.inputs()[lockfile.inputs().len() - 2..],
+            ["forbiddenHeader1", "forbiddenHeader2"]
+        );
+
+        // names are matched in lowercase
+        let witness = serde_json::to_value(&lockfile).unwrap();
+        assert_eq!(
+            witness["forbiddenHeader1"],
+            serde_json::json!(b"set-cookie")
+        );
+
+        let input = include_bytes!("../../examples/http/get_response.http");
+        let params = lockfile.populate_params(input.to_vec()).unwrap();
+        assert_eq!(params[params.len() - 2..], [10, 7]);
+
+        let input = b"HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nx-cache: HIT\r\n\r\n";
+        let err = lockfile.populate_params(input.to_vec()).unwrap_err();
+        assert_eq!(err.to_string(), "input has forbidden header `X-Cache`");
+    }
+
+    #[test]
+    fn headers_order() {
+        let lockfile: HttpData = serde_json::from_str(
+            r#"{
+                "version": "HTTP/1.1",
+                "status": "200",
+                "message": "OK",
+                "headerName1": "Set-Cookie",
+                "headerValue1": "b=2",
+                "headerName2": "Content-Type",
+                "headerValue2": "application/json",
+                "headerName3": "Set-Cookie",
+                "headerValue3": "a=1"
+            }"#,
+        )
+        .unwrap();
+
+        // lockfile order, with duplicate names kept
+        let names: Vec<&str> = lockfile
+            .headers()
+            .iter()
+            .map(|header| header.name.as_str())
+            .collect();
+        assert_eq!(names, ["Set-Cookie", "Content-Type", "Set-Cookie"]);
+
+        let witness = serde_json::to_value(&lockfile).unwrap();
+        assert_eq!(witness["header3"], serde_json::json!(b"Set-Cookie"));
+        assert_eq!(witness["value3"], serde_json::json!(b"a=1"));
+
+        let input = b"HTTP/1.1 200 OK\r\nSet-Cookie: b=2\r\nContent-Type: application/json\r\nSet-Cookie: a=1\r\n\r\n";
+        let (http, _) = lockfile.parse_input(input.to_vec()).unwrap();
+        assert_eq!(http.headers(), lockfile.headers());
+
+        let params = lockfile.populate_params(input.to_vec()).unwrap();
+        assert_eq!(params, [input.len(), 0, 8, 3, 2, 10, 3, 12, 16, 10, 3]);
+
+        let missing_value = serde_json::from_str::<HttpData>(
+            r#"{
+                "version": "HTTP/1.1",
+                "status": "200",
+                "message": "OK",
+                "headerName1": "Set-Cookie",
+                "headerName2": "Content-Type",
+                "headerValue2": "application/json"
+            }"#,
+        );
+        assert!(missing_value.is_err());
+    }
+
+    #[test]
+    fn header_modes() {
+        let lockfile: HttpData = serde_json::from_slice(include_bytes!(
+            "../../examples/http/lockfile/header_modes.lock.json"
+        ))
+        .unwrap();
+        let modes: Vec<HeaderMode> = lockfile.headers().iter().map(|h| h.mode).collect();
+        assert_eq!(modes, [HeaderMode::Prefix, HeaderMode::Extract]);
+
+        // only prefixes are inputs, and extracted values are outputs
+        assert_eq!(
+            lockfile.params()[5..],
+            [
+                "headerNameLen1",
+                "headerValueLen1",
+                "headerNameLen2",
+                "headerValueLen2"
+            ]
+        );
+        assert_eq!(lockfile.inputs()[4..], ["header1", "value1", "header2"]);
+        let witness = serde_json::to_value(&lockfile).unwrap();
+        assert_eq!(witness["value1"], serde_json::json!(b"application/"));
+        assert!(witness.get("value2").is_none());
+
+        // extracted value length comes from the input
+        let input = include_bytes!("../../examples/http/get_response.http");
+        let params = lockfile.populate_params(input.to_vec()).unwrap();
+        assert_eq!(params[params.len() - 4..], [12, 12, 14, 2]);
+        let parts = machine::http::parse(input).unwrap();
+        assert_eq!(
+            lockfile.header_values(&parts).unwrap(),
+            [&b"application/json"[..], b"19"]
+        );
+
+        // exact values match whole header values
+        let exact = HttpData::Response(Response {
+            headers: vec![Header::exact(
+                String::from("Content-Type"),
+                String::from("application/"),
+            )],
+            ..match lockfile {
+                HttpData::Response(response) => response,
+                HttpData::Request(_) => panic!("expected response"),
+            }
+        });
+        let err = exact.populate_params(input.to_vec()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "input should have exactly one header line matching `Content-Type` in Exact mode, found 0"
+        );
+
+        for (mode, value) in [
+            ("present", r#""headerValue1": "x","#),
+            ("exact", ""),
+            ("all", ""),
+        ] {
+            let lockfile = serde_json::from_str::<HttpData>(&format!(
+                r#"{{
+                    "version": "HTTP/1.1",
+                    "status": "200",
+                    "message": "OK",
+                    "headerName1": "Content-Type",
+                    {value}
+                    "headerMode1": "{mode}"
+                }}"#
+            ));
+            assert!(lockfile.is_err(), "{mode}");
+        }
+    }
+
+    #[test]
+    fn query_params() {
+        let lockfile: HttpData = serde_json::from_slice(include_bytes!(
+            "../../examples/http/lockfile/query_params.lock.json"
+        ))
+        .unwrap();
+
+        // the extracted `limit` value is an output
+        assert_eq!(
+            lockfile.params()[7..],
+            [
+                "queryNameLen1",
+                "queryValueLen1",
+                "queryNameLen2",
+                "queryValueLen2"
+            ]
+        );
+        assert_eq!(
+            lockfile.inputs()[6..],
+            ["queryName1", "queryValue1", "queryName2"]
+        );
+        let witness = serde_json::to_value(&lockfile).unwrap();
+        assert_eq!(witness["target"], serde_json::json!(b"/v1/accounts"));
+        assert_eq!(witness["queryValue1"], serde_json::json!(b"123"));
+        assert!(witness.get("queryValue2").is_none());
+
+        // target length is the path's
+        let input = include_bytes!("../../examples/http/query_request.http");
+        let params = lockfile.populate_params(input.to_vec()).unwrap();
+        assert_eq!(params, [input.len(), 0, 3, 12, 8, 4, 9, 7, 3, 5, 2]);
+
+        let (http, _) = lockfile.parse_input(input.to_vec()).unwrap();
+        match http {
+            HttpData::Request(request) => assert_eq!(request.target, "/v1/accounts"),
+            HttpData::Response(_) => panic!("expected request"),
+        }
+
+        for (target, err) in [
+            (
+                "/v1/users?account=123&limit=50",
+                "request target should be `/v1/accounts` followed by a query",
+            ),
+            (
+                "/v1/accounts",
+                "request target should be `/v1/accounts` followed by a query",
+            ),
+            (
+                "/v1/accounts?account=1234&limit=50",
+                "request target should have exactly one query parameter matching `account`, found 0",
+            ),
+            (
+                "/v1/accounts?account=123&limit=50&limit=10",
+                "request target should have exactly one query parameter matching `limit`, found 2",
+            ),
+        ] {
+            let input = format!("GET {} HTTP/1.1\r\nHost: localhost", target);
+            let result = lockfile.populate_params(input.into_bytes());
+            assert_eq!(result.unwrap_err().to_string(), err);
+        }
+    }
+
+    #[test]
+    fn parse_input_reason_phrase() {
+        let lockfile: HttpData = serde_json::from_slice(include_bytes!(
+            "../../examples/http/lockfile/not_found.lock.json"
+        ))
+        .unwrap();
+
+        let input = include_bytes!("../../examples/http/not_found_response.http");
+
+        let (http, body) = lockfile.parse_input(input.to_vec()).unwrap();
+        match http {
+            HttpData::Response(response) => assert_eq!(response.message, "Not Found"),
+            HttpData::Request(_) => panic!("expected response"),
+        }
+        assert_eq!(body, br#"{"error":"not found"}"#);
+
+        let params = lockfile.populate_params(input.to_vec()).unwrap();
+        assert_eq!(params, [input.len(), 21, 8, 3, 9, 12, 16]);
     }
 }
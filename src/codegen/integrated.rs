@@ -1,15 +1,15 @@
 use crate::{
     circuit_config::CircomkitCircuitConfig,
     codegen::{
-        http::HttpData,
-        json::{Key, Lockfile as JsonLockfile},
+        http::{HeaderMode, HttpData},
+        json::{Key, Lockfile as JsonLockfile, ValueType},
     },
     ExtractorArgs, FileType,
 };
 use serde::{Deserialize, Serialize};
 use std::path::Path;
 
-use super::{http::http_circuit_from_lockfile, json::json_circuit_from_lockfile};
+use super::{http::http_circuit_from_lockfile, json::json_circuit_from_lockfile, MaxSizes};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ExtendedLockfile {
@@ -17,14 +17,29 @@ pub struct ExtendedLockfile {
     pub json: JsonLockfile,
 }
 
+/// Params of the HTTP circuit, followed by `maxPayloadLength` for a chunked body and the JSON params
+/// without `DATA_BYTES`
+fn circuit_params(http_data: &HttpData, json_lockfile: &JsonLockfile) -> Vec<String> {
+    let mut params = http_data.params();
+    if http_data.chunked() {
+        params.push(String::from("maxPayloadLength"));
+    }
+    params.extend(json_lockfile.params().into_iter().skip(1));
+
+    params
+}
+
 fn build_integrated_circuit(
     http_data: &HttpData,
     http_circuit_config: &CircomkitCircuitConfig,
     json_lockfile: &JsonLockfile,
     json_circuit_config: &CircomkitCircuitConfig,
     integrated_circuit_config: &CircomkitCircuitConfig,
-    output_filename: &str,
-) -> Result<(), Box<dyn std::error::Error>> {
+    max_sizes: &MaxSizes,
+) -> String {
+    let padded = max_sizes.is_padded();
+    let padded_keys = max_sizes.max_key_len.is_some();
+
     let mut circuit_buffer = String::new();
 
     circuit_buffer += "pragma circom 2.1.9;\n\n";
@@ -42,19 +57,30 @@ fn build_integrated_circuit(
         .expect("improper circuit filename");
 
     circuit_buffer += &format!("include \"./{}.circom\";\n", http_circuit_filename);
-    circuit_buffer += &format!("include \"./{}.circom\";\n\n", json_circuit_filename);
+    circuit_buffer += &format!("include \"./{}.circom\";\n", json_circuit_filename);
+    if http_data.chunked() {
+        circuit_buffer += "include \"../http/chunked.circom\";\n";
+    }
+    circuit_buffer += "\n";
 
     let http_params = http_data.params();
 
-    let mut json_params = json_lockfile.params();
+    let mut json_params = max_sizes.key_param_names(json_lockfile.params());
     // remove `DATA_BYTES` from json params
     json_params.remove(0);
 
+    // JSON is extracted from the chunk data of a chunked body
+    let (json_data, json_data_bytes) = match http_data.chunked() {
+        true => ("payload", "maxPayloadLength"),
+        false => ("httpBody", "maxContentLength"),
+    };
+
     circuit_buffer += &format!(
-        "template {}({}, {}) {{\n",
+        "template {}({}) {{\n",
         integrated_circuit_config.template,
-        http_params.join(", "),
-        json_params.join(", ")
+        max_sizes
+            .key_param_names(circuit_params(http_data, json_lockfile))
+            .join(", ")
     );
 
     {
@@ -62,6 +88,9 @@ fn build_integrated_circuit(
     // Raw HTTP bytestream
     signal input data[DATA_BYTES];
 "#;
+        if padded {
+            circuit_buffer += "    signal input dataLen;\n";
+        }
 
         // Start line signals
         {
@@ -89,31 +118,96 @@ fn build_integrated_circuit(
 
         // Header signals
         circuit_buffer += "    // Header names and values to lock\n";
-        for (i, _header) in http_data.headers().iter().enumerate() {
+        for (i, header) in http_data.headers().iter().enumerate() {
             circuit_buffer += &format!(
                 "    signal input header{}[headerNameLen{}];\n",
                 i + 1,
                 i + 1
             );
+            if header.mode.has_value() {
+                circuit_buffer += &format!(
+                    "    signal input value{}[headerValueLen{}];\n",
+                    i + 1,
+                    i + 1
+                );
+            }
+        }
+        for i in 0..http_data.forbidden_headers().len() {
             circuit_buffer += &format!(
-                "    signal input value{}[headerValueLen{}];\n",
+                "    signal input forbiddenHeader{}[forbiddenHeaderNameLen{}];\n",
                 i + 1,
                 i + 1
             );
         }
+
+        // Query parameter signals of a request target
+        for (i, param) in http_data.query_params().iter().enumerate() {
+            circuit_buffer += &format!(
+                "    signal input queryName{i}[queryNameLen{i}];\n",
+                i = i + 1
+            );
+            if param.value.is_some() {
+                circuit_buffer += &format!(
+                    "    signal input queryValue{i}[queryValueLen{i}];\n",
+                    i = i + 1
+                );
+            }
+        }
     }
 
-    circuit_buffer += "\n    signal httpBody[maxContentLength];\n\n";
-    let http_inputs = http_data.inputs();
+    circuit_buffer += "\n    signal httpBody[maxContentLength];\n";
+
+    // extracted header and query parameter values are output along with the body
+    let mut http_outputs = vec![String::from("httpBody")];
+    for (i, header) in http_data.headers().iter().enumerate() {
+        if header.mode == HeaderMode::Extract {
+            circuit_buffer += &format!(
+                "    signal output value{}[headerValueLen{}];\n",
+                i + 1,
+                i + 1
+            );
+            http_outputs.push(format!("value{}", i + 1));
+        }
+    }
+    for (i, param) in http_data.query_params().iter().enumerate() {
+        if param.value.is_none() {
+            circuit_buffer += &format!(
+                "    signal output queryValue{i}[queryValueLen{i}];\n",
+                i = i + 1
+            );
+            http_outputs.push(format!("queryValue{}", i + 1));
+        }
+    }
+    let http_outputs = match http_outputs.len() {
+        1 => http_outputs.remove(0),
+        _ => format!("({})", http_outputs.join(", ")),
+    };
+
+    let mut http_inputs = http_data.inputs();
+    if padded {
+        http_inputs.insert(1, String::from("dataLen"));
+    }
     circuit_buffer += &format!(
-        "    httpBody <== {}({})({});\n\n",
+        "\n    {} <== {}({})({});\n\n",
+        http_outputs,
         http_circuit_config.template,
         http_params.join(", "),
         http_inputs.join(", "),
     );
 
+    if http_data.chunked() {
+        circuit_buffer += "    signal payload[maxPayloadLength] <== DechunkBody(maxContentLength, maxPayloadLength)(httpBody);\n\n";
+    }
+
     for (i, key) in json_lockfile.keys.iter().enumerate() {
         match key {
+            Key::String(_) if padded_keys => {
+                circuit_buffer += &format!(
+                    "    signal input key{}[maxKeyLen];\n    signal input keyLen{};\n",
+                    i + 1,
+                    i + 1
+                )
+            }
             Key::String(_) => {
                 circuit_buffer += &format!("    signal input key{}[keyLen{}];\n", i + 1, i + 1)
             }
@@ -121,34 +215,82 @@ fn build_integrated_circuit(
         }
     }
 
-    circuit_buffer += "\n    signal output value[maxValueLen];\n";
-    circuit_buffer += &format!(
-        "    value <== {}(maxContentLength, {}",
+    for input in json_lockfile.public_inputs() {
+        circuit_buffer += &format!("    signal input {};\n", input);
+    }
+
+    let mut json_inputs = json_lockfile.inputs(padded_keys);
+    json_inputs.remove(0);
+
+    let json_extractor = format!(
+        "{}({}, {})",
         json_circuit_config.template,
+        json_data_bytes,
         json_params.join(", ")
     );
 
-    let mut json_inputs = json_lockfile.inputs();
-    json_inputs.remove(0);
-    circuit_buffer += &format!(")(httpBody, {});\n", json_inputs.join(", "));
+    match json_lockfile.value_type {
+        // predicate only outputs its result
+        ValueType::Number if json_lockfile.predicate.is_some() => {
+            circuit_buffer += "\n    signal output result;\n";
+            circuit_buffer += &format!(
+                "    result <== {}({}, {});\n",
+                json_extractor,
+                json_data,
+                json_inputs.join(", ")
+            );
+        }
+        // only the commitment to the value is output
+        ValueType::String | ValueType::Raw if json_lockfile.commitment.is_some() => {
+            circuit_buffer += "\n    signal output commitment;\n";
+            circuit_buffer += &format!(
+                "    commitment <== {}({}, {});\n",
+                json_extractor,
+                json_data,
+                json_inputs.join(", ")
+            );
+        }
+        ValueType::String | ValueType::Raw => {
+            circuit_buffer += "\n    signal output value[maxValueLen];\n";
+            circuit_buffer += &format!(
+                "    value <== {}({}, {});\n",
+                json_extractor,
+                json_data,
+                json_inputs.join(", ")
+            );
+        }
+        ValueType::Number => {
+            circuit_buffer += "\n    signal output value;\n";
+            circuit_buffer += "    signal output value_sign;\n";
+            circuit_buffer += &format!(
+                "    (value, value_sign) <== {}({}, {});\n",
+                json_extractor,
+                json_data,
+                json_inputs.join(", ")
+            );
+        }
+        ValueType::Boolean => {
+            circuit_buffer += "\n    signal output value;\n";
+            circuit_buffer += &format!(
+                "    value <== {}({}, {});\n",
+                json_extractor,
+                json_data,
+                json_inputs.join(", ")
+            );
+        }
+        // null extractor only has constraints and no output
+        ValueType::Null => {
+            circuit_buffer += &format!("\n    component jsonExtractor = {};\n", json_extractor);
+            circuit_buffer += &format!("    jsonExtractor.data <== {};\n", json_data);
+            for input in json_inputs {
+                circuit_buffer += &format!("    jsonExtractor.{} <== {};\n", input, input);
+            }
+        }
+    }
 
     circuit_buffer += "}";
 
-    // write circuits to file
-    let mut file_path = std::env::current_dir()?;
-    file_path.push("circuits");
-    file_path.push("main");
-
-    // create dir if doesn't exist
-    std::fs::create_dir_all(&file_path)?;
-
-    file_path.push(format!("{}.circom", output_filename));
-
-    std::fs::write(&file_path, circuit_buffer)?;
-
-    println!("Code generated at: {}", file_path.display());
-
-    Ok(())
+    circuit_buffer
 }
 
 fn build_circuit_config(
@@ -159,24 +301,33 @@ fn build_circuit_config(
 ) -> Result<CircomkitCircuitConfig, Box<dyn std::error::Error>> {
     let input = FileType::Http.read_input(&args.input_file)?;
 
-    let (_, http_body) = http_data.parse_input(input.clone())?;
+    let payload = http_data.parse_payload(input.clone())?;
 
     // populate http params
     let mut params = http_data.populate_params(input)?;
+    if http_data.chunked() {
+        params.push(payload.len());
+    }
 
     // add json params and remove first param: `DATA_BYTES`
-    let mut json_params = json_lockfile.populate_params(&http_body)?;
+    let mut json_params = json_lockfile.populate_params(&payload)?;
     json_params.remove(0);
     params.append(&mut json_params);
 
+    let names = args
+        .max_sizes
+        .key_params(circuit_params(http_data, json_lockfile), &mut params);
+    args.max_sizes.apply(&names, &mut params)?;
+
     Ok(CircomkitCircuitConfig {
         file: format!("main/{}", output_filename),
         template: String::from("HttpJson"),
         params,
+        pubs: json_lockfile.public_inputs(),
     })
 }
 
-/// Builds a HTTP + JSON combined circuit extracting body response from HTTP response and
+/// Builds a HTTP + JSON combined circuit extracting the body from an HTTP request or response and
 /// extracting value of keys from JSON.
 pub fn integrated_circuit(args: &ExtractorArgs) -> Result<(), Box<dyn std::error::Error>> {
     let extended_lockfile: ExtendedLockfile =
@@ -185,35 +336,191 @@ pub fn integrated_circuit(args: &ExtractorArgs) -> Result<(), Box<dyn std::error
     let http_data: HttpData = extended_lockfile.http;
     let lockfile: JsonLockfile = extended_lockfile.json;
 
+    // JSON extractor needs the body bytes
+    if http_data.commitment().is_some() {
+        return Err(String::from(
+            "HTTP body commitment isn't supported in extended lockfile, set `commitment` in `json` instead",
+        )
+        .into());
+    }
+
     let http_circuit_filename = format!("{}_http", args.circuit_name);
     let http_circuit_config = http_circuit_from_lockfile(
         &args.input_file,
         &http_data,
         &http_circuit_filename,
+        &args.max_sizes,
         args.debug,
     )?;
 
-    // read http response body as json input
+    // read http body as json input
     let json_circuit_filename = format!("{}_json", args.circuit_name);
     let input = FileType::Http.read_input(&args.input_file)?;
-    let (_, http_body) = http_data.parse_input(input.clone())?;
-
-    let json_circuit_config =
-        json_circuit_from_lockfile(&http_body, &lockfile, &json_circuit_filename, args.debug)?;
+    let payload = http_data.parse_payload(input)?;
+
+    // JSON is extracted from the zero padded body, so it has no `dataLen` of its own
+    let json_max_sizes = MaxSizes {
+        max_data_bytes: None,
+        ..args.max_sizes.clone()
+    };
+    let json_circuit_config = json_circuit_from_lockfile(
+        &payload,
+        &lockfile,
+        &json_circuit_filename,
+        &json_max_sizes,
+        args.debug,
+    )?;
 
     let output_filename = format!("extended_{}", args.circuit_name);
     let config = build_circuit_config(args, &http_data, &lockfile, &output_filename)?;
 
-    build_integrated_circuit(
+    let circuit = build_integrated_circuit(
         &http_data,
         &http_circuit_config,
         &lockfile,
         &json_circuit_config,
         &config,
-        &output_filename,
-    )?;
+        &args.max_sizes,
+    );
+
+    // write circuits to file
+    let mut file_path = std::env::current_dir()?;
+    file_path.push("circuits");
+    file_path.push("main");
+
+    // create dir if doesn't exist
+    std::fs::create_dir_all(&file_path)?;
+
+    file_path.push(format!("{}.circom", output_filename));
+
+    std::fs::write(&file_path, circuit)?;
+
+    println!("Code generated at: {}", file_path.display());
 
     config.write(&args.circuit_name)?;
 
     Ok(())
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::machine;
+    use std::collections::BTreeSet;
+
+    /// Config of a component circuit, of which only the file and template are used
+    fn component(file: &str, template: &str) -> CircomkitCircuitConfig {
+        CircomkitCircuitConfig {
+            file: format!("main/{}", file),
+            template: String::from(template),
+            params: vec![],
+            pubs: vec![],
+        }
+    }
+
+    /// Names of the `signal input`s declared in `circuit`
+    fn declared_inputs(circuit: &str) -> BTreeSet<String> {
+        circuit
+            .lines()
+            .filter_map(|line| line.trim().strip_prefix("signal input "))
+            .map(|input| input.split(['[', ';']).next().unwrap().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn request_circuit() {
+        let input = FileType::Http
+            .read_input(Path::new("examples/http/post_json_request.http"))
+            .unwrap();
+        let lockfile: ExtendedLockfile = serde_json::from_slice(include_bytes!(
+            "../../examples/http/lockfile/post_json_extended.lock.json"
+        ))
+        .unwrap();
+        let args = |max_sizes: MaxSizes| ExtractorArgs {
+            subcommand: FileType::Extended,
+            circuit_name: String::from("post"),
+            input_file: "examples/http/post_json_request.http".into(),
+            lockfile: "examples/http/lockfile/post_json_extended.lock.json".into(),
+            debug: false,
+            max_sizes,
+        };
+
+        // JSON is extracted from the request body, as parsed by the HTTP parser
+        let parts = machine::http::parse(&input).unwrap();
+        let query_values = lockfile.http.query_values(&parts).unwrap();
+        let config = build_circuit_config(
+            &args(MaxSizes::default()),
+            &lockfile.http,
+            &lockfile.json,
+            "extended_post",
+        )
+        .unwrap();
+        assert_eq!(config.params[..2], [input.len(), parts.body.len()]);
+        assert_eq!(
+            config.params[7..9],
+            ["account".len(), query_values[0].len()]
+        );
+        assert_eq!(
+            config.params[9..],
+            lockfile.json.populate_params(parts.body).unwrap()[1..]
+        );
+
+        // extracted values of the simulated circuits
+        assert_eq!(query_values[0], b"123");
+        let states = machine::json::trace(parts.body, config.params[9]).unwrap();
+        let mask = machine::json::value_mask(parts.body, &states, &lockfile.json).unwrap();
+        let max_value_len = *config.params.last().unwrap();
+        let value = machine::json::select_sub_array(
+            parts.body,
+            machine::json::value_starting_index(&mask) + 1,
+            max_value_len,
+            max_value_len,
+        );
+        assert_eq!(value, b"A-1");
+        assert_eq!(value, lockfile.json.get_value_bytes(parts.body).unwrap());
+
+        // circuit declares exactly the inputs of the witness, with and without maximum sizes
+        for max_sizes in [
+            MaxSizes::default(),
+            MaxSizes {
+                max_data_bytes: Some(256),
+                max_content_length: Some(128),
+                max_key_len: Some(8),
+                ..Default::default()
+            },
+        ] {
+            let args = args(max_sizes.clone());
+            let config =
+                build_circuit_config(&args, &lockfile.http, &lockfile.json, "extended_post")
+                    .unwrap();
+            let circuit = build_integrated_circuit(
+                &lockfile.http,
+                &component("post_http", "LockHTTPRequest"),
+                &lockfile.json,
+                &component("post_json", "ExtractStringValue"),
+                &config,
+                &max_sizes,
+            );
+
+            let serde_json::Value::Object(http_inputs) =
+                serde_json::to_value(&lockfile.http).unwrap()
+            else {
+                unreachable!()
+            };
+            let mut witness_inputs: BTreeSet<String> = http_inputs.keys().cloned().collect();
+            witness_inputs.insert(String::from("data"));
+            witness_inputs.extend(
+                lockfile
+                    .json
+                    .keys_as_bytes(max_sizes.max_key_len)
+                    .unwrap()
+                    .into_keys(),
+            );
+            if max_sizes.is_padded() {
+                witness_inputs.insert(String::from("dataLen"));
+                witness_inputs.extend(lockfile.json.key_lens().into_keys());
+            }
+            assert_eq!(declared_inputs(&circuit), witness_inputs);
+        }
+    }
+}
@@ -1,13 +1,17 @@
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
+use serde_json::{value::RawValue, Value};
 use std::{
-    cmp::max_by,
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     error::Error,
     fs::{self, create_dir_all},
 };
 
-use crate::{circuit_config::CircomkitCircuitConfig, ExtractorArgs};
+use super::{pad, Commitment, MaxSizes};
+use crate::{
+    circuit_config::CircomkitCircuitConfig,
+    machine::json::{predict_value_starting_index, trace},
+    ExtractorArgs,
+};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub enum ValueType {
@@ -15,6 +19,53 @@ pub enum ValueType {
     String,
     #[serde(rename = "number")]
     Number,
+    #[serde(rename = "boolean")]
+    Boolean,
+    #[serde(rename = "null")]
+    Null,
+    /// Whole object or array, extracted as raw bytes
+    #[serde(rename = "raw", alias = "object", alias = "array")]
+    Raw,
+}
+
+/// Comparison proven about a `number` value, which is output instead of the value itself.
+///
+/// Bounds are public inputs: `bound`, or `lower_bound` and `upper_bound` (inclusive) for
+/// `in_range`. They are signed integers in the value's fixed-point `scale`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Predicate {
+    Gt,
+    Gte,
+    Lt,
+    Lte,
+    Eq,
+    InRange,
+}
+
+impl Predicate {
+    /// Names of the bound inputs
+    pub fn bounds(&self) -> &'static [&'static str] {
+        match self {
+            Predicate::InRange => &["lower_bound", "upper_bound"],
+            _ => &["bound"],
+        }
+    }
+
+    /// Evaluates the predicate on `value`, like the circuit
+    pub fn evaluate(&self, value: i128, bounds: &[i128]) -> bool {
+        match (self, bounds) {
+            (Predicate::Gt, [bound]) => value > *bound,
+            (Predicate::Gte, [bound]) => value >= *bound,
+            (Predicate::Lt, [bound]) => value < *bound,
+            (Predicate::Lte, [bound]) => value <= *bound,
+            (Predicate::Eq, [bound]) => value == *bound,
+            (Predicate::InRange, [lower_bound, upper_bound]) => {
+                *lower_bound <= value && value <= *upper_bound
+            }
+            _ => panic!("expected {} bounds for {:?}", self.bounds().len(), self),
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -26,48 +77,274 @@ pub enum Key {
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Lockfile {
+    /// Keys to the value, either as a list or as a key path string, see [`parse_key_path`]
+    #[serde(deserialize_with = "deserialize_keys")]
     pub keys: Vec<Key>,
     pub value_type: ValueType,
+    /// Fixed-point scale of a `number` value, i.e. `3.14` with scale `2` is extracted as `314`
+    #[serde(default)]
+    pub scale: usize,
+    /// Proves a predicate about a `number` value instead of outputting it
+    #[serde(default)]
+    pub predicate: Option<Predicate>,
+    /// Outputs a commitment to a `string` or `raw` value instead of its bytes
+    #[serde(default)]
+    pub commitment: Option<Commitment>,
+}
+
+fn deserialize_keys<'de, D>(deserializer: D) -> Result<Vec<Key>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Keys {
+        List(Vec<Key>),
+        Path(String),
+    }
+
+    match Keys::deserialize(deserializer)? {
+        Keys::List(keys) => Ok(keys),
+        Keys::Path(path) => parse_key_path(&path).map_err(serde::de::Error::custom),
+    }
+}
+
+/// Parses a JSONPath-like key path, e.g. `$.data.items[0].profile.name`, into [`Key`]s.
+///
+/// A path starts with `$`, followed by any of:
+/// - `.key`: object key, ends at the next `.` or `[`. Can't contain whitespace, quotes, `\` or `]`
+/// - `["key"]`, `['key']`, `."key"`: quoted object key, can contain `.`, `[` and `]`. `\`
+///   escapes the next character.
+/// - `[0]`: array index
+pub fn parse_key_path(path: &str) -> Result<Vec<Key>, Box<dyn Error>> {
+    let mut chars = path.char_indices().peekable();
+
+    match chars.next() {
+        Some((_, '$')) => (),
+        Some((i, _)) => return Err(key_path_error(path, i, "path should start with `$`")),
+        None => return Err(key_path_error(path, 0, "empty path")),
+    }
+
+    let mut keys = vec![];
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '.' => match chars.peek() {
+                Some(&(_, '"' | '\'')) => {
+                    keys.push(Key::String(parse_quoted_key(path, &mut chars)?))
+                }
+                _ => {
+                    let mut key = String::new();
+                    while let Some(&(j, c)) = chars.peek() {
+                        if c == '.' || c == '[' {
+                            break;
+                        }
+                        if c.is_whitespace() || matches!(c, ']' | '"' | '\'' | '\\') {
+                            return Err(key_path_error(
+                                path,
+                                j,
+                                "unexpected character in key, use a quoted key instead",
+                            ));
+                        }
+                        key.push(c);
+                        chars.next();
+                    }
+
+                    if key.is_empty() {
+                        return Err(key_path_error(path, i + 1, "expected key after `.`"));
+                    }
+                    keys.push(Key::String(key));
+                }
+            },
+            '[' => {
+                match chars.peek() {
+                    Some(&(_, '"' | '\'')) => {
+                        keys.push(Key::String(parse_quoted_key(path, &mut chars)?))
+                    }
+                    Some(&(start, c)) if c.is_ascii_digit() => {
+                        let mut index = String::new();
+                        while let Some(&(_, c)) = chars.peek() {
+                            if !c.is_ascii_digit() {
+                                break;
+                            }
+                            index.push(c);
+                            chars.next();
+                        }
+
+                        let index = index
+                            .parse()
+                            .map_err(|_| key_path_error(path, start, "array index too large"))?;
+                        keys.push(Key::Num(index));
+                    }
+                    Some(&(j, _)) => {
+                        return Err(key_path_error(
+                            path,
+                            j,
+                            "expected array index or quoted key after `[`",
+                        ))
+                    }
+                    None => return Err(key_path_error(path, path.len(), "unclosed `[`")),
+                }
+
+                match chars.next() {
+                    Some((_, ']')) => (),
+                    Some((j, _)) => return Err(key_path_error(path, j, "expected `]`")),
+                    None => return Err(key_path_error(path, path.len(), "unclosed `[`")),
+                }
+            }
+            _ => return Err(key_path_error(path, i, "expected `.` or `[`")),
+        }
+    }
+
+    if keys.is_empty() {
+        return Err(key_path_error(
+            path,
+            path.len(),
+            "path doesn't contain any key",
+        ));
+    }
+
+    Ok(keys)
+}
+
+/// Parses a key quoted with `"` or `'`, starting at the opening quote
+fn parse_quoted_key(
+    path: &str,
+    chars: &mut std::iter::Peekable<std::str::CharIndices>,
+) -> Result<String, Box<dyn Error>> {
+    let (start, quote) = chars.next().expect("called at a quote");
+
+    let mut key = String::new();
+    while let Some((_, c)) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some((_, c)) => key.push(c),
+                None => break,
+            },
+            c if c == quote => return Ok(key),
+            c => key.push(c),
+        }
+    }
+
+    Err(key_path_error(path, start, "unclosed quote"))
+}
+
+/// Error pointing at `position` (byte offset) in `path`
+fn key_path_error(path: &str, position: usize, reason: &str) -> Box<dyn Error> {
+    let column = path[..position].chars().count();
+    format!(
+        "invalid key path at position {}: {}\n{}\n{}^",
+        position,
+        reason,
+        path,
+        " ".repeat(column)
+    )
+    .into()
 }
 
 impl Lockfile {
-    pub fn keys_as_bytes(&self) -> HashMap<String, Vec<u8>> {
+    /// Witness inputs for the keys, named `key{i}`, zero padded to `max_key_len` if any
+    pub fn keys_as_bytes(
+        &self,
+        max_key_len: Option<usize>,
+    ) -> Result<HashMap<String, Vec<u8>>, Box<dyn Error>> {
+        self.prefixed_keys_as_bytes("", max_key_len)
+    }
+
+    fn prefixed_keys_as_bytes(
+        &self,
+        prefix: &str,
+        max_key_len: Option<usize>,
+    ) -> Result<HashMap<String, Vec<u8>>, Box<dyn Error>> {
         let mut keys = HashMap::<String, Vec<u8>>::new();
         for (i, key) in self.keys.iter().enumerate() {
             if let Key::String(key) = key {
-                let key_name = format!("key{}", i + 1);
-                keys.insert(key_name, key.as_bytes().to_vec());
+                let key_name = format!("{}key{}", prefix, i + 1);
+                keys.insert(key_name, pad(key.as_bytes(), max_key_len, "max-key-len")?);
+            }
+        }
+        Ok(keys)
+    }
+
+    /// Witness inputs for the lengths of padded keys, named `keyLen{i}`
+    pub fn key_lens(&self) -> HashMap<String, usize> {
+        self.prefixed_key_lens("")
+    }
+
+    fn prefixed_key_lens(&self, prefix: &str) -> HashMap<String, usize> {
+        let mut key_lens = HashMap::new();
+        for (i, key) in self.keys.iter().enumerate() {
+            if let Key::String(key) = key {
+                key_lens.insert(format!("{}keyLen{}", prefix, i + 1), key.len());
             }
         }
-        keys
+        key_lens
     }
+
     pub fn params(&self) -> Vec<String> {
         let mut params = vec!["DATA_BYTES".to_string(), "MAX_STACK_HEIGHT".to_string()];
+        params.append(&mut self.value_params(""));
+
+        params
+    }
+
+    /// Params specific to this extraction, i.e. `[keyLen1, depth1, ..., maxValueLen]`
+    fn value_params(&self, prefix: &str) -> Vec<String> {
+        let mut params = vec![];
 
         for (i, key) in self.keys.iter().enumerate() {
             match key {
                 Key::String(_) => {
-                    params.push(format!("keyLen{}", i + 1));
-                    params.push(format!("depth{}", i + 1));
+                    params.push(format!("{}keyLen{}", prefix, i + 1));
+                    params.push(format!("{}depth{}", prefix, i + 1));
                 }
                 Key::Num(_) => {
-                    params.push(format!("index{}", i + 1));
-                    params.push(format!("depth{}", i + 1));
+                    params.push(format!("{}index{}", prefix, i + 1));
+                    params.push(format!("{}depth{}", prefix, i + 1));
                 }
             }
         }
 
-        params.push("maxValueLen".to_string());
+        params.push(format!("{}maxValueLen", prefix));
 
         params
     }
 
-    pub fn inputs(&self) -> Vec<String> {
+    /// Inputs of the circuit, with key lengths after the keys if keys are padded
+    pub fn inputs(&self, padded_keys: bool) -> Vec<String> {
         let mut inputs = vec![String::from("data")];
+        inputs.append(&mut self.value_inputs("", padded_keys));
+        inputs.append(&mut self.bound_inputs(""));
+
+        inputs
+    }
+
+    /// Public inputs, i.e. bounds of the predicate
+    pub fn public_inputs(&self) -> Vec<String> {
+        self.bound_inputs("")
+    }
+
+    /// Bound inputs of the predicate, i.e. `[bound]`
+    fn bound_inputs(&self, prefix: &str) -> Vec<String> {
+        self.predicate
+            .iter()
+            .flat_map(|predicate| predicate.bounds())
+            .map(|bound| format!("{}{}", prefix, bound))
+            .collect()
+    }
+
+    /// Inputs specific to this extraction, i.e. `[key1, ...]`, or `[key1, keyLen1, ...]` if keys
+    /// are padded
+    fn value_inputs(&self, prefix: &str, padded_keys: bool) -> Vec<String> {
+        let mut inputs = vec![];
 
         for (i, key) in self.keys.iter().enumerate() {
             match key {
-                Key::String(_) => inputs.push(format!("key{}", i + 1)),
+                Key::String(_) => {
+                    inputs.push(format!("{}key{}", prefix, i + 1));
+                    if padded_keys {
+                        inputs.push(format!("{}keyLen{}", prefix, i + 1));
+                    }
+                }
                 Key::Num(_) => (),
             }
         }
@@ -82,21 +359,38 @@ impl Lockfile {
         output_filename: &str,
     ) -> Result<CircomkitCircuitConfig, Box<dyn Error>> {
         let circuit_template_name = match self.value_type {
+            ValueType::String if self.commitment.is_some() => {
+                String::from("ExtractStringCommitment")
+            }
+            ValueType::Raw if self.commitment.is_some() => String::from("ExtractRawCommitment"),
             ValueType::String => String::from("ExtractStringValue"),
+            ValueType::Number if self.predicate.is_some() => String::from("ExtractNumPredicate"),
             ValueType::Number => String::from("ExtractNumValue"),
+            ValueType::Boolean => String::from("ExtractBoolValue"),
+            ValueType::Null => String::from("ExtractNullValue"),
+            ValueType::Raw => String::from("ExtractRawValue"),
         };
 
         Ok(CircomkitCircuitConfig {
             file: format!("main/{}", output_filename),
             template: circuit_template_name,
             params: self.populate_params(input)?,
+            pubs: self.public_inputs(),
         })
     }
 
     /// Builds circuit arguments
     /// `[DATA_BYTES, MAX_STACK_HEIGHT, keyLen1, depth1, ..., maxValueLen]`
     pub fn populate_params(&self, input: &[u8]) -> Result<Vec<usize>, Box<dyn Error>> {
-        let mut params = vec![input.len(), json_max_stack_height(input)];
+        let mut params = vec![input.len(), json_max_stack_height(input)?];
+        params.append(&mut self.populate_value_params(input)?);
+
+        Ok(params)
+    }
+
+    /// Builds circuit arguments specific to this extraction: `[keyLen1, depth1, ..., maxValueLen]`
+    fn populate_value_params(&self, input: &[u8]) -> Result<Vec<usize>, Box<dyn Error>> {
+        let mut params = vec![];
 
         for (i, key) in self.keys.iter().enumerate() {
             match key {
@@ -107,26 +401,134 @@ impl Lockfile {
         }
 
         let current_value = self.get_value(input)?;
-        params.push(current_value.as_bytes().len());
+        match self.value_type {
+            // length of `false`, so that the circuit doesn't reveal which literal it extracts
+            ValueType::Boolean => params.push(5),
+            // bytes parsed as number, i.e. unscaled and without the `-` sign
+            ValueType::Number => {
+                // comparators work on values below 2^251
+                let digits = current_value.trim_start_matches('-').len();
+                if self.predicate.is_some() && digits > MAX_PREDICATE_DIGITS {
+                    return Err(format!(
+                        "scaled number value {} is too large for a predicate, at most {} digits are supported",
+                        current_value, MAX_PREDICATE_DIGITS
+                    )
+                    .into());
+                }
+
+                let number = self.get_raw_value(input)?.get();
+                params.push(number.trim_start_matches('-').len());
+            }
+            // extracted without unescaping
+            ValueType::String | ValueType::Raw => params.push(self.get_value_bytes(input)?.len()),
+            _ => params.push(current_value.len()),
+        }
 
         Ok(params)
     }
 
+    /// Returns the value at `keys` as output by the circuit. Numbers are returned as their
+    /// magnitude scaled by `10^scale`, prefixed with `-` if the sign bit is set.
     pub fn get_value(&self, input: &[u8]) -> Result<String, Box<dyn Error>> {
-        let mut current_value: Value = serde_json::from_slice(input)?;
+        if (self.scale != 0 || self.predicate.is_some())
+            && !matches!(self.value_type, ValueType::Number)
+        {
+            return Err(format!(
+                "scale and predicate are only supported for `number` values, not `{:?}`",
+                self.value_type
+            )
+            .into());
+        }
+        if self.commitment.is_some()
+            && !matches!(self.value_type, ValueType::String | ValueType::Raw)
+        {
+            return Err(format!(
+                "commitment is only supported for `string` and `raw` values, not `{:?}`",
+                self.value_type
+            )
+            .into());
+        }
+
+        let raw_value = self.get_raw_value(input)?;
+
+        match (&self.value_type, serde_json::from_str(raw_value.get())?) {
+            (ValueType::Number, Value::Number(_)) => {
+                let (negative, magnitude) = scaled_number(raw_value.get(), self.scale)?;
+                match negative {
+                    true => Ok(format!("-{}", magnitude)),
+                    false => Ok(magnitude),
+                }
+            }
+            (ValueType::String, Value::String(val)) => Ok(val),
+            (ValueType::Boolean, Value::Bool(val)) => Ok(val.to_string()),
+            (ValueType::Null, Value::Null) => Ok(String::from("null")),
+            // raw values are extracted byte-for-byte, including whitespace
+            (ValueType::Raw, Value::Object(_) | Value::Array(_)) => Ok(raw_value.get().to_string()),
+            (value_type, value) => Err(format!(
+                "value type `{:?}` in lockfile doesn't match input JSON value: {}",
+                value_type, value
+            )
+            .into()),
+        }
+    }
+
+    /// Returns the bytes of a `string` or `raw` value as extracted by the circuit, i.e. without
+    /// unescaping strings.
+    pub fn get_value_bytes(&self, input: &[u8]) -> Result<Vec<u8>, Box<dyn Error>> {
+        self.get_value(input)?;
+
+        let raw_value = self.get_raw_value(input)?.get();
+        match self.value_type {
+            ValueType::String => Ok(raw_value.as_bytes()[1..raw_value.len() - 1].to_vec()),
+            ValueType::Raw => Ok(raw_value.as_bytes().to_vec()),
+            _ => Err(format!("`{:?}` value isn't extracted as bytes", self.value_type).into()),
+        }
+    }
+
+    /// Evaluates the lockfile's predicate on the value at `keys` with `bounds`, returning the
+    /// circuit's output.
+    pub fn check_predicate(&self, input: &[u8], bounds: &[i128]) -> Result<bool, Box<dyn Error>> {
+        let Some(predicate) = self.predicate else {
+            return Err(String::from("lockfile doesn't contain a predicate").into());
+        };
+        if bounds.len() != predicate.bounds().len() {
+            return Err(format!(
+                "predicate `{:?}` expects {} bounds, got {}",
+                predicate,
+                predicate.bounds().len(),
+                bounds.len()
+            )
+            .into());
+        }
+
+        let value: i128 = self.get_value(input)?.parse()?;
+        Ok(predicate.evaluate(value, bounds))
+    }
+
+    /// Returns the unparsed value at `keys`, as it appears in `input`.
+    pub(crate) fn get_raw_value<'a>(
+        &self,
+        input: &'a [u8],
+    ) -> Result<&'a RawValue, Box<dyn Error>> {
+        let mut current_value: &RawValue = serde_json::from_slice(input)?;
         for key in self.keys.iter() {
             match key {
                 Key::String(key) => {
-                    if let Some(value) = current_value.get_mut(key) {
+                    let object: HashMap<String, &RawValue> =
+                        serde_json::from_str(current_value.get())
+                            .map_err(|_| String::from("provided key not present in input JSON"))?;
+                    if let Some(value) = object.get(key) {
                         // update current object value inside key
-                        current_value = value.to_owned();
+                        current_value = value;
                     } else {
                         return Err(String::from("provided key not present in input JSON").into());
                     }
                 }
                 Key::Num(index) => {
-                    if let Some(value) = current_value.get_mut(index) {
-                        current_value = value.to_owned();
+                    let array: Vec<&RawValue> = serde_json::from_str(current_value.get())
+                        .map_err(|_| String::from("provided index not present in input JSON"))?;
+                    if let Some(value) = array.get(*index) {
+                        current_value = value;
                     } else {
                         return Err(String::from("provided index not present in input JSON").into());
                     }
@@ -134,298 +536,629 @@ impl Lockfile {
             }
         }
 
-        match current_value {
-            Value::Number(num) => Ok(num.to_string()),
-            Value::String(val) => Ok(val),
-            _ => unimplemented!(),
-        }
+        Ok(current_value)
     }
 }
 
-/// Returns maximum stack height for JSON parser circuit. Tracks maximum open braces and square
-/// brackets at any position.
-///
-/// # Input
-/// - `input`: input json bytes
-/// # Output
-/// - `max_stack_height`: maximum stack height needed for JSON parser circuit
-pub fn json_max_stack_height(input: &[u8]) -> usize {
-    let mut max_stack_height = 1;
-    let mut curr_stack_height = 1;
-    let mut inside_string: bool = false;
-
-    for (i, char) in input.iter().skip(1).enumerate() {
-        match char {
-            b'"' if input[i] != b'\\' => inside_string = !inside_string,
-            b'{' | b'[' if !inside_string => {
-                curr_stack_height += 1;
-                max_stack_height = max_by(max_stack_height, curr_stack_height, |x, y| x.cmp(y));
-            }
-            b'}' | b']' if !inside_string => curr_stack_height -= 1,
-            _ => {}
-        }
+/// Maximum digits of a scaled number compared by a predicate, so that it's below 2^251
+const MAX_PREDICATE_DIGITS: usize = 75;
+
+/// Splits a JSON number into its sign and its magnitude scaled by `10^scale`, e.g. `-3.1` with
+/// scale `2` is `(true, "310")`.
+fn scaled_number(number: &str, scale: usize) -> Result<(bool, String), Box<dyn Error>> {
+    if number.contains(['e', 'E']) {
+        return Err(format!(
+            "exponent notation isn't supported in number value: {}",
+            number
+        )
+        .into());
+    }
+
+    let (negative, unsigned) = match number.strip_prefix('-') {
+        Some(unsigned) => (true, unsigned),
+        None => (false, number),
+    };
+    let (integer, fraction) = unsigned.split_once('.').unwrap_or((unsigned, ""));
+    if fraction.len() > scale {
+        return Err(format!(
+            "number value {} has more fraction digits than lockfile scale {}",
+            number, scale
+        )
+        .into());
     }
 
-    max_stack_height
+    let digits = format!(
+        "{}{}{}",
+        integer,
+        fraction,
+        "0".repeat(scale - fraction.len())
+    );
+    let magnitude = match digits.trim_start_matches('0') {
+        "" => String::from("0"),
+        magnitude => magnitude.to_string(),
+    };
+
+    Ok((negative, magnitude))
 }
 
-fn extract_string(
-    config: &CircomkitCircuitConfig,
-    data: &Lockfile,
-    circuit_buffer: &mut String,
-    debug: bool,
-) {
-    let params = data.params();
-    let inputs = data.inputs();
+/// Named value extraction inside a [`MultiLockfile`]
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Extraction {
+    pub name: String,
+    #[serde(flatten)]
+    pub lockfile: Lockfile,
+}
 
-    *circuit_buffer += &format!("template {}({}) {{\n", config.template, params.join(", "),);
+/// Lockfile extracting multiple values from the same JSON. All extractions share a single parser
+/// pass over the input, and each one outputs its value as a signal named after the extraction.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MultiLockfile {
+    pub extractions: Vec<Extraction>,
+}
 
-    *circuit_buffer += "    signal input data[DATA_BYTES];\n\n";
+/// JSON lockfile with either a single extraction or multiple named extractions
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum ExtractorLockfile {
+    Multi(MultiLockfile),
+    Single(Lockfile),
+}
 
-    for (i, key) in data.keys.iter().enumerate() {
-        match key {
-            Key::String(_) => {
-                *circuit_buffer += &format!("    signal input key{}[keyLen{}];\n", i + 1, i + 1)
-            }
-            Key::Num(_) => (),
+impl<'de> Deserialize<'de> for ExtractorLockfile {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        use serde::de::Error;
+
+        // dispatch on `extractions` instead of trying each variant, so that errors inside the
+        // lockfile aren't hidden behind a generic untagged enum error
+        let value = Value::deserialize(deserializer)?;
+        if value.get("extractions").is_some() {
+            serde_json::from_value(value)
+                .map(ExtractorLockfile::Multi)
+                .map_err(D::Error::custom)
+        } else {
+            serde_json::from_value(value)
+                .map(ExtractorLockfile::Single)
+                .map_err(D::Error::custom)
         }
     }
+}
 
-    *circuit_buffer += r#"
-    signal output value[maxValueLen];
-
-    signal value_starting_index[DATA_BYTES];
-"#;
+impl MultiLockfile {
+    /// Witness inputs for all extraction keys, named `{name}_key{i}`
+    pub fn keys_as_bytes(
+        &self,
+        max_key_len: Option<usize>,
+    ) -> Result<HashMap<String, Vec<u8>>, Box<dyn Error>> {
+        let mut keys = HashMap::new();
+        for extraction in self.extractions.iter() {
+            keys.extend(
+                extraction
+                    .lockfile
+                    .prefixed_keys_as_bytes(&extraction.prefix(), max_key_len)?,
+            );
+        }
+        Ok(keys)
+    }
 
-    // value_starting_index <== ExtractValue(DATA_BYTES, MAX_STACK_HEIGHT, keyLen1, depth1, keyLen2, depth2, index3, depth3, index4, depth4, maxValueLen)(data, key1, key2);
-    {
-        *circuit_buffer += &format!(
-            "    value_starting_index <== ExtractValue({})({});\n",
-            params.join(", "),
-            inputs.join(", "),
-        );
+    /// Witness inputs for the lengths of all padded extraction keys, named `{name}_keyLen{i}`
+    pub fn key_lens(&self) -> HashMap<String, usize> {
+        let mut key_lens = HashMap::new();
+        for extraction in self.extractions.iter() {
+            key_lens.extend(extraction.lockfile.prefixed_key_lens(&extraction.prefix()));
+        }
+        key_lens
     }
 
-    *circuit_buffer += r#"
-    value <== SelectSubArray(DATA_BYTES, maxValueLen)(data, value_starting_index[DATA_BYTES-1]+1, maxValueLen);"#;
+    pub fn params(&self) -> Vec<String> {
+        let mut params = vec!["DATA_BYTES".to_string(), "MAX_STACK_HEIGHT".to_string()];
+        for extraction in self.extractions.iter() {
+            params.append(&mut extraction.lockfile.value_params(&extraction.prefix()));
+        }
 
-    if debug {
-        *circuit_buffer += r#"
-    log("value_starting_index", value_starting_index[DATA_BYTES-1]+1);
-    for (var i=0 ; i<maxValueLen; i++) {
-        log("value[",i,"]=", value[i]);
-    }"#;
+        params
     }
 
-    *circuit_buffer += r#"
-}
-"#;
-}
+    /// Inputs of the circuit, with key lengths after the keys if keys are padded
+    pub fn inputs(&self, padded_keys: bool) -> Vec<String> {
+        let mut inputs = vec![String::from("data")];
+        for extraction in self.extractions.iter() {
+            inputs.append(
+                &mut extraction
+                    .lockfile
+                    .value_inputs(&extraction.prefix(), padded_keys),
+            );
+            inputs.append(&mut extraction.lockfile.bound_inputs(&extraction.prefix()));
+        }
 
-fn extract_number(
-    config: &CircomkitCircuitConfig,
-    data: &Lockfile,
-    circuit_buffer: &mut String,
-    debug: bool,
-) {
-    let params = data.params();
-    let inputs = data.inputs();
+        inputs
+    }
+
+    /// Public inputs, i.e. `{name}_bound` of all predicates
+    pub fn public_inputs(&self) -> Vec<String> {
+        self.extractions
+            .iter()
+            .flat_map(|extraction| extraction.lockfile.bound_inputs(&extraction.prefix()))
+            .collect()
+    }
 
-    *circuit_buffer += &format!("template {}({}) {{\n", config.template, params.join(", "),);
+    /// Builds circuit config for circomkit support.
+    pub fn build_circuit_config(
+        &self,
+        input: &[u8],
+        output_filename: &str,
+    ) -> Result<CircomkitCircuitConfig, Box<dyn Error>> {
+        self.validate()?;
 
-    *circuit_buffer += "    signal input data[DATA_BYTES];\n\n";
+        Ok(CircomkitCircuitConfig {
+            file: format!("main/{}", output_filename),
+            template: String::from("ExtractMultipleValues"),
+            params: self.populate_params(input)?,
+            pubs: self.public_inputs(),
+        })
+    }
 
-    for (i, key) in data.keys.iter().enumerate() {
-        match key {
-            Key::String(_) => {
-                *circuit_buffer += &format!("    signal input key{}[keyLen{}];\n", i + 1, i + 1)
-            }
-            Key::Num(_) => (),
+    /// Builds circuit arguments
+    /// `[DATA_BYTES, MAX_STACK_HEIGHT, {name}_keyLen1, {name}_depth1, ..., {name}_maxValueLen, ...]`
+    pub fn populate_params(&self, input: &[u8]) -> Result<Vec<usize>, Box<dyn Error>> {
+        let mut params = vec![input.len(), json_max_stack_height(input)?];
+        for extraction in self.extractions.iter() {
+            params.append(&mut extraction.lockfile.populate_value_params(input)?);
         }
+
+        Ok(params)
     }
 
-    *circuit_buffer += r#"
-    signal value_string[maxValueLen];
-    signal output value;
+    /// Returns `(name, value)` of all extractions
+    pub fn get_values(&self, input: &[u8]) -> Result<Vec<(String, String)>, Box<dyn Error>> {
+        self.extractions
+            .iter()
+            .map(|extraction| {
+                Ok((
+                    extraction.name.clone(),
+                    extraction.lockfile.get_value(input)?,
+                ))
+            })
+            .collect()
+    }
 
-    signal value_starting_index[DATA_BYTES];
-"#;
+    /// Checks that extraction names are unique and can be used as circom identifiers.
+    pub fn validate(&self) -> Result<(), Box<dyn Error>> {
+        if self.extractions.is_empty() {
+            return Err(String::from("lockfile doesn't contain any extraction").into());
+        }
 
-    // value_starting_index <== ExtractValue(DATA_BYTES, MAX_STACK_HEIGHT, keyLen1, depth1, keyLen2, depth2, index3, depth3, index4, depth4, maxValueLen)(data, key1, key2);
-    {
-        *circuit_buffer += &format!(
-            "    value_starting_index <== ExtractValue({})({});\n",
-            params.join(", "),
-            inputs.join(", "),
-        );
-    }
+        let mut names = HashSet::new();
+        for extraction in self.extractions.iter() {
+            let name = &extraction.name;
+            let is_identifier = name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+                && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+            if !is_identifier || name == "data" {
+                return Err(format!("invalid extraction name: `{}`", name).into());
+            }
+            if !names.insert(name) {
+                return Err(format!("duplicate extraction name: `{}`", name).into());
+            }
+        }
 
-    *circuit_buffer += r#"
-    value_string <== SelectSubArray(DATA_BYTES, maxValueLen)(data, value_starting_index[DATA_BYTES-1], maxValueLen);
-"#;
+        Ok(())
+    }
+}
 
-    if debug {
-        *circuit_buffer += r#"
-    log("value_starting_index", value_starting_index[DATA_BYTES-1]);
-    for (var i=0 ; i<maxValueLen; i++) {
-        log("value[",i,"]=", value_string[i]);
-    }"#;
+impl Extraction {
+    /// Prefix of params, inputs and signals belonging to this extraction
+    fn prefix(&self) -> String {
+        format!("{}_", self.name)
     }
+}
 
-    *circuit_buffer += r#"
+/// Returns maximum stack height for JSON parser circuit. Tracks maximum open braces and square
+/// brackets at any position.
+///
+/// Bytes are scanned like `StateUpdate` in the parser circuit: delimiters are ignored inside
+/// strings, and every `"` starts or ends a string. The circuit doesn't treat `\` specially, so
+/// neither does the scanner: `"\\"` is a complete string, while an escaped quote ends it.
+/// Braces and brackets inside strings don't change the stack, but the circuit still checks that
+/// pushing or popping them wouldn't overflow or underflow it.
+///
+/// # Input
+/// - `input`: input json bytes
+/// # Output
+/// - `max_stack_height`: maximum stack height needed for JSON parser circuit
+/// # Errors
+/// - closing brace or bracket without a matching opening one
+/// - input ending inside a string, or with unclosed braces or brackets
+pub fn json_max_stack_height(input: &[u8]) -> Result<usize, Box<dyn Error>> {
+    let mut max_stack_height = 0;
+    // opening delimiters with their positions
+    let mut stack: Vec<(usize, u8)> = vec![];
+    // position of the opening quote of the current string
+    let mut string_start = None;
+
+    for (i, &byte) in input.iter().enumerate() {
+        match byte {
+            b'"' => string_start = string_start.xor(Some(i)),
+            b'{' | b'[' if string_start.is_some() => {
+                max_stack_height = max_stack_height.max(stack.len() + 1);
+            }
+            b'}' | b']' if string_start.is_some() && stack.is_empty() => {
+                return Err(format!("unmatched `{}` at byte {}", byte as char, i).into())
+            }
+            _ if string_start.is_some() => {}
+            b'{' | b'[' => {
+                stack.push((i, byte));
+                max_stack_height = max_stack_height.max(stack.len());
+            }
+            b'}' | b']' => {
+                let open = if byte == b'}' { b'{' } else { b'[' };
+                match stack.pop() {
+                    Some((_, top)) if top == open => {}
+                    Some((j, top)) => {
+                        return Err(format!(
+                            "`{}` at byte {} doesn't close `{}` at byte {}",
+                            byte as char, i, top as char, j
+                        )
+                        .into())
+                    }
+                    None => {
+                        return Err(format!("unmatched `{}` at byte {}", byte as char, i).into())
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
 
-    signal number_value[maxValueLen];
-    number_value[0] <== (value_string[0]-48);
-    for (var i=1 ; i<maxValueLen ; i++) {
-        number_value[i] <== number_value[i-1] * 10 + (value_string[i]-48);
+    if let Some(i) = string_start {
+        return Err(format!("unterminated string starting at byte {}", i).into());
+    }
+    if let Some((i, top)) = stack.pop() {
+        return Err(format!("unclosed `{}` at byte {}", top as char, i).into());
     }
 
-    value <== number_value[maxValueLen-1];
+    Ok(max_stack_height)
 }
-"#;
+
+/// Value extraction generated inside a JSON extractor circuit. Params, inputs and signals of the
+/// extraction are prefixed with `prefix`, so that multiple extractions can share one circuit, and
+/// the extracted value is assigned to the `output` signal.
+struct ValueExtractor<'a> {
+    prefix: String,
+    output: String,
+    lockfile: &'a Lockfile,
+    /// `maxValueLen` is an upper bound, and shorter strings and raw values are zero padded
+    padded: bool,
+    /// keys are zero padded up to `maxKeyLen` and matched with their `keyLen{i}` inputs
+    padded_keys: bool,
 }
 
-fn build_json_circuit(
-    config: &CircomkitCircuitConfig,
-    data: &Lockfile,
-    output_filename: &str,
-    debug: bool,
-) -> Result<(), Box<dyn Error>> {
-    let mut circuit_buffer = String::new();
+impl ValueExtractor<'_> {
+    /// literals (`true`, `false`, `null`) are neither strings nor numbers for the parser, so their
+    /// bytes are tracked with a separate `parsing_literal` flag. Similarly, raw objects and arrays
+    /// are tracked with `parsing_raw`, which is set while the value's own stack entry is allocated.
+    fn parsing_flags(&self, idx: &str) -> String {
+        let p = &self.prefix;
+        match self.lockfile.value_type {
+            ValueType::Boolean | ValueType::Null => format!("{p}parsing_literal[{idx}], 0"),
+            ValueType::Raw => format!("{p}parsing_raw[{idx}], 0"),
+            ValueType::String | ValueType::Number => {
+                format!("State[{idx}].next_parsing_string, State[{idx}].next_parsing_number")
+            }
+        }
+    }
 
-    // Dump out the contents of the lockfile used into the circuit
-    circuit_buffer += "/*\n";
-    circuit_buffer += &format!("{:#?}", data);
-    circuit_buffer += "\n*/\n";
+    fn is_literal(&self) -> bool {
+        matches!(
+            self.lockfile.value_type,
+            ValueType::Boolean | ValueType::Null
+        )
+    }
 
-    circuit_buffer += "pragma circom 2.1.9;\n\n";
-    circuit_buffer += "include \"../json/interpreter.circom\";\n\n";
+    fn is_raw(&self) -> bool {
+        matches!(self.lockfile.value_type, ValueType::Raw)
+    }
 
-    // template ExtractValue(DATA_BYTES, MAX_STACK_HEIGHT, keyLen1, depth1, index2, depth2, keyLen3, depth3, index4, depth4, maxValueLen) {
-    {
-        let params = data.params();
-        circuit_buffer += &format!("template ExtractValue({}) {{\n", params.join(", "));
+    /// Raw values and numbers are contiguous spans in `data`, `ExtractValue` outputs their length.
+    /// Padded strings need their length as well.
+    fn has_value_length(&self) -> bool {
+        match self.lockfile.value_type {
+            ValueType::Raw | ValueType::Number => true,
+            ValueType::String => self.padded,
+            ValueType::Boolean | ValueType::Null => false,
+        }
     }
 
-    /*
-    signal input data[DATA_BYTES];
+    /// Outputs of `ExtractValue` belonging to this extraction
+    fn extract_value_outputs(&self) -> Vec<String> {
+        let mut outputs = vec![format!("{}value_starting_index", self.prefix)];
+        if self.has_value_length() {
+            outputs.push(format!("{}value_length", self.prefix));
+        }
+        outputs
+    }
 
-    signal input key1[keyLen1];
-    signal input key3[keyLen3];
-     */
-    {
-        circuit_buffer += "    signal input data[DATA_BYTES];\n\n";
+    /// Name of stack signal: `parsing_object{i}_value` for a key and `parsing_array{i}` for an index
+    fn parsing_key_signal(&self, i: usize) -> String {
+        match self.lockfile.keys[i] {
+            Key::String(_) => format!("{}parsing_object{}_value", self.prefix, i + 1),
+            Key::Num(_) => format!("{}parsing_array{}", self.prefix, i + 1),
+        }
+    }
 
-        for (i, key) in data.keys.iter().enumerate() {
+    fn key_inputs(&self, circuit_buffer: &mut String) {
+        let p = &self.prefix;
+        for (i, key) in self.lockfile.keys.iter().enumerate() {
             match key {
+                Key::String(_) if self.padded_keys => {
+                    *circuit_buffer += &format!(
+                        "    signal input {p}key{}[{p}maxKeyLen];\n    signal input {p}keyLen{};\n",
+                        i + 1,
+                        i + 1
+                    )
+                }
                 Key::String(_) => {
-                    circuit_buffer += &format!("    signal input key{}[keyLen{}];\n", i + 1, i + 1)
+                    *circuit_buffer += &format!(
+                        "    signal input {}key{}[{}keyLen{}];\n",
+                        self.prefix,
+                        i + 1,
+                        self.prefix,
+                        i + 1
+                    )
                 }
                 Key::Num(_) => (),
             }
         }
     }
-    circuit_buffer += r#"    // value starting index in `data`
-    signal output value_starting_index[DATA_BYTES];
-    // flag determining whether this byte is matched value
-    signal is_value_match[DATA_BYTES];
-    // final mask
-    signal mask[DATA_BYTES];
 
-    component State[DATA_BYTES];
-    State[0] = StateUpdate(MAX_STACK_HEIGHT);
-    State[0].byte           <== data[0];
-    for(var i = 0; i < MAX_STACK_HEIGHT; i++) {
-        State[0].stack[i]   <== [0,0];
+    /// Padded keys must be `keyLen{i}` non-zero bytes followed by zeros
+    fn padded_key_checks(&self, circuit_buffer: &mut String) {
+        if !self.padded_keys {
+            return;
+        }
+
+        let p = &self.prefix;
+        for (i, key) in self.lockfile.keys.iter().enumerate() {
+            if let Key::String(_) = key {
+                *circuit_buffer += &format!(
+                    "    component {p}key{i}Padding = AssertPaddedKey({p}maxKeyLen);\n    {p}key{i}Padding.key    <== {p}key{i};\n    {p}key{i}Padding.keyLen <== {p}keyLen{i};\n",
+                    i = i + 1
+                );
+            }
+        }
     }
-    State[0].parsing_string <== 0;
-    State[0].parsing_number <== 0;
 
-    signal parsing_key[DATA_BYTES];
-    signal parsing_value[DATA_BYTES];
-"#;
+    /// Whether the `i`th key matches at `idx`
+    fn key_match(&self, i: usize, idx: &str) -> String {
+        let p = &self.prefix;
+        let i = i + 1;
+        match self.padded_keys {
+            true => format!("KeyMatchAtDepthPadded(DATA_BYTES, MAX_STACK_HEIGHT, {p}maxKeyLen, {idx}, {p}depth{i})(data, {p}key{i}, {p}keyLen{i}, parsing_key[{idx}], State[{idx}].next_stack)"),
+            false => format!("KeyMatchAtDepth(DATA_BYTES, MAX_STACK_HEIGHT, {p}keyLen{i}, {p}depth{i})(data, {p}key{i}, {idx}, parsing_key[{idx}], State[{idx}].next_stack)"),
+        }
+    }
 
-    /* // signals for parsing string key and array index
-    signal parsing_key[DATA_BYTES];
-    signal parsing_value[DATA_BYTES];
-    signal parsing_object1_value[DATA_BYTES];
-    signal parsing_array2[DATA_BYTES];
-    signal is_key1_match[DATA_BYTES];
-    signal is_key1_match_for_value[DATA_BYTES];
-    is_key1_match_for_value[0] <== 0;
-    signal is_next_pair_at_depth1[DATA_BYTES];
-     */
-    {
-        for (i, key) in data.keys.iter().enumerate() {
+    /// Public bound inputs of the predicate
+    fn bound_inputs(&self, circuit_buffer: &mut String) {
+        for input in self.lockfile.bound_inputs(&self.prefix) {
+            *circuit_buffer += &format!("    signal input {};\n", input);
+        }
+    }
+
+    fn signal_declarations(&self, circuit_buffer: &mut String) {
+        let p = &self.prefix;
+
+        *circuit_buffer += &format!(
+            r#"
+    signal {p}parsing_value[DATA_BYTES];
+    // flag determining whether this byte is matched value
+    signal {p}is_value_match[DATA_BYTES];
+    // final mask
+    signal {p}mask[DATA_BYTES];
+"#
+        );
+
+        if self.is_literal() {
+            *circuit_buffer += &format!(
+                r#"
+    // literal bytes are lowercase letters outside of a string
+    signal {p}is_letter[DATA_BYTES];
+    signal {p}parsing_literal[DATA_BYTES];
+"#
+            );
+        }
+
+        if self.is_raw() {
+            *circuit_buffer += &format!(
+                r#"
+    // raw value bytes lie between opening and closing byte of the value's stack entry
+    signal {p}parsing_raw[DATA_BYTES];
+"#
+            );
+        }
+
+        /* // signals for parsing string key and array index
+        signal parsing_object1_value[DATA_BYTES];
+        signal parsing_array2[DATA_BYTES];
+        signal is_key1_match[DATA_BYTES];
+        signal is_key1_match_for_value[DATA_BYTES];
+        is_key1_match_for_value[0] <== 0;
+        signal is_next_pair_at_depth1[DATA_BYTES];
+         */
+        for i in 0..self.lockfile.keys.len() {
+            *circuit_buffer += &format!("    signal {}[DATA_BYTES];\n", self.parsing_key_signal(i));
+        }
+
+        for (i, key) in self.lockfile.keys.iter().enumerate() {
+            match key {
+                Key::String(_) => *circuit_buffer += &format!("    signal {p}is_key{}_match[DATA_BYTES];\n    signal {p}is_key{}_match_for_value[DATA_BYTES+1];\n    {p}is_key{}_match_for_value[0] <== 0;\n    signal {p}is_next_pair_at_depth{}[DATA_BYTES];\n", i+1, i+1, i+1, i+1),
+                Key::Num(_) => (),
+            }
+        }
+    }
+
+    fn initial_iteration(&self, circuit_buffer: &mut String, debug: bool) {
+        let p = &self.prefix;
+        let keys = &self.lockfile.keys;
+
+        *circuit_buffer += "\n";
+
+        if self.is_literal() {
+            *circuit_buffer += &format!(
+                r#"    {p}is_letter[0] <== InRange(8)(data[0], [97, 122]);
+    {p}parsing_literal[0] <== {p}is_letter[0] * (1 - State[0].next_parsing_string);
+
+"#
+            );
+        }
+
+        if self.is_raw() {
+            *circuit_buffer += &format!(
+                "    {p}parsing_raw[0] <== 1 - IsZero()(State[0].next_stack[{p}depth{}+1][0]);\n\n",
+                keys.len()
+            );
+        }
+
+        for (i, key) in keys.iter().enumerate() {
             match key {
                 Key::String(_) => {
-                    circuit_buffer +=
-                        &format!("    signal parsing_object{}_value[DATA_BYTES];\n", i + 1)
+                    *circuit_buffer += &format!("    {p}parsing_object{}_value[0] <== InsideValue()(State[0].next_stack[0], {});\n", i+1, self.parsing_flags("0"));
                 }
                 Key::Num(_) => {
-                    circuit_buffer += &format!("    signal parsing_array{}[DATA_BYTES];\n", i + 1)
+                    *circuit_buffer += &format!("    {p}parsing_array{}[0] <== InsideArrayIndex({p}index{})(State[0].next_stack[0], {});\n", i+1, i+1, self.parsing_flags("0"));
                 }
             }
         }
 
-        for (i, key) in data.keys.iter().enumerate() {
+        // parsing_value[0] <== MultiAND(5)([parsing_object1_value[0], parsing_object2_value[0], parsing_array3[0], parsing_object4_value[0], parsing_object5_value[0]]);
+        let parsing_keys = (0..keys.len())
+            .map(|i| format!("{}[0]", self.parsing_key_signal(i)))
+            .collect::<Vec<_>>();
+        *circuit_buffer += &format!(
+            "     // parsing correct value = AND(all individual stack values)\n    {p}parsing_value[0] <== MultiAND({})([{}]);\n\n",
+            keys.len(),
+            parsing_keys.join(", ")
+        );
+
+        // is_key{i}_match_for_value
+        let mut key_matches = vec![];
+        for (i, key) in keys.iter().enumerate() {
             match key {
-            Key::String(_) => circuit_buffer += &format!("    signal is_key{}_match[DATA_BYTES];\n    signal is_key{}_match_for_value[DATA_BYTES+1];\n    is_key{}_match_for_value[0] <== 0;\n    signal is_next_pair_at_depth{}[DATA_BYTES];\n", i+1, i+1, i+1, i+1),
-            Key::Num(_) => (),
-        }
+                Key::String(_) => {
+                    key_matches.push(format!("{p}is_key{}_match_for_value[1]", i + 1));
+                    *circuit_buffer += &format!(
+                        "    {p}is_key{}_match[0] <== {};\n",
+                        i + 1,
+                        self.key_match(i, "0")
+                    );
+                    *circuit_buffer += &format!("    {p}is_next_pair_at_depth{}[0] <== NextKVPairAtDepth(MAX_STACK_HEIGHT)(State[0].next_stack, data[0], {p}depth{});\n", i+1, i+1);
+                    *circuit_buffer += &format!("    {p}is_key{}_match_for_value[1] <== Mux1()([{p}is_key{}_match_for_value[0] * (1-{p}is_next_pair_at_depth{}[0]), {p}is_key{}_match[0] * (1-{p}is_next_pair_at_depth{}[0])], {p}is_key{}_match[0]);\n", i+1, i+1, i+1, i+1, i+1, i+1);
+                    if debug {
+                        *circuit_buffer += &format!("        // log(\"{p}is_key{}_match_for_value\", {p}is_key{}_match_for_value[1]);\n\n", i + 1, i + 1);
+                    }
+                }
+                Key::Num(_) => (),
+            }
         }
+
+        // is_value_match[data_idx] <== MultiAND(2)([is_key1_match_for_value[data_idx], is_key3_match_for_value[data_idx]]);
+        *circuit_buffer += &format!(
+            "    {p}is_value_match[0] <== {};\n",
+            multi_and(&key_matches)
+        );
+
+        *circuit_buffer += &format!(
+            r#"
+    {p}mask[0] <== {p}parsing_value[0] * {p}is_value_match[0];
+"#
+        );
     }
 
-    let mut num_objects = 0;
+    fn iteration(&self, circuit_buffer: &mut String, debug: bool) {
+        let p = &self.prefix;
+        let keys = &self.lockfile.keys;
 
-    // initialise first iteration
-    {
-        // parsing_key and parsing_object{i}_value
-        circuit_buffer += r#"
-    // initialise first iteration
-    parsing_key[0] <== InsideKeyAtTop(MAX_STACK_HEIGHT)(State[0].next_stack, State[0].next_parsing_string, State[0].next_parsing_number);
+        if self.is_literal() {
+            *circuit_buffer += &format!(
+                r#"        {p}is_letter[data_idx] <== InRange(8)(data[data_idx], [97, 122]);
+        {p}parsing_literal[data_idx] <== {p}is_letter[data_idx] * (1 - State[data_idx].next_parsing_string);
 
-"#;
+"#
+            );
+        }
 
-        for (i, key) in data.keys.iter().enumerate() {
+        // value's stack entry is allocated after reading the opening byte and until the closing byte
+        if self.is_raw() {
+            *circuit_buffer += &format!("        {p}parsing_raw[data_idx] <== 1 - IsZero()(State[data_idx].stack[{p}depth{}+1][0]) * IsZero()(State[data_idx].next_stack[{p}depth{}+1][0]);\n\n", keys.len(), keys.len());
+        }
+
+        /* Determining wheter parsing correct value and array index
+        parsing_object1_value[data_idx-1] <== InsideValue(MAX_STACK_HEIGHT, depth1)(State[data_idx].stack, State[data_idx].parsing_string, State[data_idx].parsing_number);
+        parsing_array2[data_idx-1] <== InsideArrayIndex(MAX_STACK_HEIGHT, index2, depth2)(State[data_idx].stack, State[data_idx].parsing_string, State[data_idx].parsing_number);
+         */
+        for (i, key) in keys.iter().enumerate() {
             match key {
                 Key::String(_) => {
-                    circuit_buffer += &format!("    parsing_object{}_value[0] <== InsideValue()(State[0].next_stack[0], State[0].next_parsing_string, State[0].next_parsing_number);\n", i+1);
+                    *circuit_buffer += &format!("        {p}parsing_object{}_value[data_idx] <== InsideValue()(State[data_idx].next_stack[{p}depth{}], {});\n", i+1, i+1, self.parsing_flags("data_idx"));
                 }
                 Key::Num(_) => {
-                    circuit_buffer += &format!("    parsing_array{}[0] <== InsideArrayIndex(index{})(State[0].next_stack[0], State[0].next_parsing_string, State[0].next_parsing_number);\n", i+1, i+1);
+                    *circuit_buffer += &format!("        {p}parsing_array{}[data_idx] <== InsideArrayIndex({p}index{})(State[data_idx].next_stack[{p}depth{}], {});\n", i+1, i+1, i+1, self.parsing_flags("data_idx"));
                 }
             }
         }
 
-        // parsing_value[0] <== MultiAND(5)([parsing_object1_value[0], parsing_object2_value[0], parsing_array3[0], parsing_object4_value[0], parsing_object5_value[0]]);
-        circuit_buffer += &format!(
-            "     // parsing correct value = AND(all individual stack values)\n    parsing_value[0] <== MultiAND({})([",
-            data.keys.len()
-        );
-        for (i, key) in data.keys.iter().take(data.keys.len() - 1).enumerate() {
-            match key {
-                Key::String(_) => circuit_buffer += &format!("parsing_object{}_value[0], ", i + 1),
-                Key::Num(_) => circuit_buffer += &format!("parsing_array{}[0], ", i + 1),
-            }
-        }
-        match data.keys[data.keys.len() - 1] {
-            Key::String(_) => {
-                circuit_buffer += &format!("parsing_object{}_value[0]]);\n\n", data.keys.len())
+        // parsing correct value = AND(all individual stack values)
+        //     parsing_value[data_idx-1] <== MultiAND(4)([parsing_object1_value[data_idx-1], parsing_array2[data_idx-1], parsing_object3_value[data_idx-1], parsing_array4[data_idx-1]]);
+        {
+            let parsing_keys = (0..keys.len())
+                .map(|i| format!("{}[data_idx]", self.parsing_key_signal(i)))
+                .collect::<Vec<_>>();
+            *circuit_buffer += &format!(
+                "        // parsing correct value = AND(all individual stack values)\n        {p}parsing_value[data_idx] <== MultiAND({})([{}]);\n",
+                keys.len(),
+                parsing_keys.join(", ")
+            );
+
+            // optional debug logs
+            if debug {
+                *circuit_buffer += &format!(
+                    "        // log(\"parsing value:\", {}, {p}parsing_value[data_idx]);\n\n",
+                    parsing_keys.join(", ")
+                );
             }
-            Key::Num(_) => circuit_buffer += &format!("parsing_array{}[0]]);\n\n", data.keys.len()),
         }
 
-        // is_key{i}_match_for_value
-        for (i, key) in data.keys.iter().enumerate() {
+        /*
+        to get correct value, check:
+        - key matches at current index and depth of key is as specified
+        - whether next KV pair starts
+        - whether key matched for a value (propogate key match until new KV pair of lower depth starts)
+        is_key1_match[data_idx-1] <== KeyMatchAtDepth(DATA_BYTES, MAX_STACK_HEIGHT, keyLen1, depth1)(data, key1, data_idx-1, parsing_key[data_idx-1], State[data_idx].stack);
+        is_next_pair_at_depth1[data_idx-1] <== NextKVPairAtDepth(MAX_STACK_HEIGHT, depth1)(State[data_idx].stack, data[data_idx-1]);
+        is_key1_match_for_value[data_idx] <== Mux1()([is_key1_match_for_value[data_idx-1] * (1-is_next_pair_at_depth1[data_idx-1]), is_key1_match[data_idx-1] * (1-is_next_pair_at_depth1[data_idx-1])], is_key1_match[data_idx-1]);
+        */
+        *circuit_buffer += r#"
+        // to get correct value, check:
+        // - key matches at current index and depth of key is as specified
+        // - whether next KV pair starts
+        // - whether key matched for a value (propogate key match until new KV pair of lower depth starts)
+"#;
+
+        let mut key_matches = vec![];
+        for (i, key) in keys.iter().enumerate() {
             match key {
                 Key::String(_) => {
-                    num_objects += 1;
-                    circuit_buffer += &format!("    is_key{}_match[0] <== KeyMatchAtDepth(DATA_BYTES, MAX_STACK_HEIGHT, keyLen{}, depth{})(data, key{}, 0, parsing_key[0], State[0].next_stack);\n", i+1, i+1, i+1, i+1);
-                    circuit_buffer += &format!("    is_next_pair_at_depth{}[0] <== NextKVPairAtDepth(MAX_STACK_HEIGHT)(State[0].next_stack, data[0], depth{});\n", i+1, i+1);
-                    circuit_buffer += &format!("    is_key{}_match_for_value[1] <== Mux1()([is_key{}_match_for_value[0] * (1-is_next_pair_at_depth{}[0]), is_key{}_match[0] * (1-is_next_pair_at_depth{}[0])], is_key{}_match[0]);\n", i+1, i+1, i+1, i+1, i+1, i+1);
+                    key_matches.push(format!("{p}is_key{}_match_for_value[data_idx+1]", i + 1));
+                    *circuit_buffer += &format!(
+                        "        {p}is_key{}_match[data_idx] <== {};\n",
+                        i + 1,
+                        self.key_match(i, "data_idx")
+                    );
+                    *circuit_buffer += &format!("        {p}is_next_pair_at_depth{}[data_idx] <== NextKVPairAtDepth(MAX_STACK_HEIGHT)(State[data_idx].next_stack, data[data_idx], {p}depth{});\n", i+1, i+1);
+                    *circuit_buffer += &format!("        {p}is_key{}_match_for_value[data_idx+1] <== Mux1()([{p}is_key{}_match_for_value[data_idx] * (1-{p}is_next_pair_at_depth{}[data_idx]), {p}is_key{}_match[data_idx] * (1-{p}is_next_pair_at_depth{}[data_idx])], {p}is_key{}_match[data_idx]);\n", i+1, i+1, i+1, i+1, i+1, i+1);
                     if debug {
-                        circuit_buffer += &format!("        // log(\"is_key{}_match_for_value\", is_key{}_match_for_value[1]);\n\n", i + 1, i + 1);
+                        *circuit_buffer += &format!("        // log(\"{p}is_key{}_match_for_value\", {p}is_key{}_match_for_value[data_idx+1]);\n\n", i + 1, i + 1);
                     }
                 }
                 Key::Num(_) => (),
@@ -433,26 +1166,513 @@ fn build_json_circuit(
         }
 
         // is_value_match[data_idx] <== MultiAND(2)([is_key1_match_for_value[data_idx], is_key3_match_for_value[data_idx]]);
-        {
-            circuit_buffer += &format!("    is_value_match[0] <== MultiAND({})([", num_objects);
-            for (i, key) in data.keys.iter().enumerate() {
-                match key {
-                    Key::String(_) => {
-                        circuit_buffer += &format!("is_key{}_match_for_value[1], ", i + 1)
-                    }
-                    Key::Num(_) => (),
-                }
+        *circuit_buffer += &format!(
+            "        {p}is_value_match[data_idx] <== {};\n",
+            multi_and(&key_matches)
+        );
+
+        *circuit_buffer += &format!(
+            r#"
+        // mask = currently parsing value and all subsequent keys matched
+        {p}mask[data_idx] <== {p}parsing_value[data_idx] * {p}is_value_match[data_idx];
+"#
+        );
+    }
+
+    fn starting_index(&self, circuit_buffer: &mut String) {
+        let p = &self.prefix;
+
+        *circuit_buffer += &format!(
+            r#"
+    // find starting index of value in data by matching mask
+    signal {p}is_zero_mask[DATA_BYTES];
+    signal {p}is_prev_starting_index[DATA_BYTES];
+    {p}value_starting_index[0] <== 0;
+    {p}is_prev_starting_index[0] <== 0;
+    {p}is_zero_mask[0] <== IsZero()({p}mask[0]);
+    for (var i=1 ; i<DATA_BYTES ; i++) {{
+        {p}is_zero_mask[i] <== IsZero()({p}mask[i]);
+        {p}is_prev_starting_index[i] <== IsZero()({p}value_starting_index[i-1]);
+        {p}value_starting_index[i] <== {p}value_starting_index[i-1] + i * (1-{p}is_zero_mask[i]) * {p}is_prev_starting_index[i];
+    }}
+"#
+        );
+
+        if self.is_raw() {
+            *circuit_buffer += &format!(
+                r#"
+    // raw value is a contiguous span, so its length is the number of masked bytes
+    var {p}mask_sum = 0;
+    for (var i=0 ; i<DATA_BYTES ; i++) {{
+        {p}mask_sum += {p}mask[i];
+    }}
+    {p}value_length <== {p}mask_sum;
+"#
+            );
+        }
+
+        if matches!(self.lockfile.value_type, ValueType::String) && self.padded {
+            *circuit_buffer += &format!(
+                r#"
+    // string is a contiguous span of masked bytes, starting with the opening quote
+    var {p}mask_sum = 0;
+    for (var i=0 ; i<DATA_BYTES ; i++) {{
+        {p}mask_sum += {p}mask[i];
+    }}
+    {p}value_length <== {p}mask_sum - 1;
+"#
+            );
+        }
+
+        if matches!(self.lockfile.value_type, ValueType::Number) {
+            *circuit_buffer += &format!(
+                r#"
+    // number spans from its first to its last masked digit. The parser stops `parsing_number` at
+    // the decimal point, so it isn't masked, but is inside the span.
+    signal {p}value_end[DATA_BYTES];
+    {p}value_end[0] <== 0;
+    for (var i=1 ; i<DATA_BYTES ; i++) {{
+        {p}value_end[i] <== {p}value_end[i-1] + (i - {p}value_end[i-1]) * (1 - {p}is_zero_mask[i]);
+    }}
+    {p}value_length <== {p}value_end[DATA_BYTES-1] - {p}value_starting_index[DATA_BYTES-1] + 1;
+"#
+            );
+        }
+    }
+
+    /// Selects the value from `data` using outputs of `ExtractValue` and assigns it to `output`
+    fn value(&self, circuit_buffer: &mut String, debug: bool) {
+        match self.lockfile.value_type {
+            ValueType::String => self.extract_string(circuit_buffer, debug),
+            ValueType::Number => self.extract_number(circuit_buffer, debug),
+            ValueType::Boolean => self.extract_bool(circuit_buffer, debug),
+            ValueType::Null => self.extract_null(circuit_buffer, debug),
+            ValueType::Raw => self.extract_raw(circuit_buffer, debug),
+        }
+    }
+
+    fn extract_string(&self, circuit_buffer: &mut String, debug: bool) {
+        let p = &self.prefix;
+        let (value, visibility) = self.value_bytes_signal();
+        let length = self.selected_length(circuit_buffer);
+
+        *circuit_buffer += &format!(
+            r#"
+    signal {visibility}{value}[{p}maxValueLen];
+    {value} <== SelectSubArray(DATA_BYTES, {p}maxValueLen)(data, {p}value_starting_index[DATA_BYTES-1]+1, {length});
+"#
+        );
+
+        if debug {
+            *circuit_buffer += &format!(
+                r#"
+    log("{p}value_starting_index", {p}value_starting_index[DATA_BYTES-1]+1);
+    for (var i=0 ; i<{p}maxValueLen; i++) {{
+        log("{value}[",i,"]=", {value}[i]);
+    }}
+"#
+            );
+        }
+
+        self.commitment(circuit_buffer, &value);
+    }
+
+    /// Number of bytes selected for a string or raw value. Padded values are `value_length` bytes
+    /// long, which is checked to fit in `maxValueLen`.
+    fn selected_length(&self, circuit_buffer: &mut String) -> String {
+        let p = &self.prefix;
+        if !self.padded {
+            return format!("{p}maxValueLen");
+        }
+
+        *circuit_buffer += &format!(
+            r#"
+    // value is zero padded up to `maxValueLen`
+    signal {p}is_value_fit <== LessEqThan(log2Ceil(DATA_BYTES + 1))([{p}value_length, {p}maxValueLen]);
+    {p}is_value_fit === 1;
+"#
+        );
+        format!("{p}value_length")
+    }
+
+    /// Signal holding the extracted bytes: the output itself, or `{p}value` if only a commitment
+    /// to the bytes is output
+    fn value_bytes_signal(&self) -> (String, &'static str) {
+        match self.lockfile.commitment {
+            Some(_) => (format!("{}value", self.prefix), ""),
+            None => (self.output.clone(), "output "),
+        }
+    }
+
+    /// Outputs the commitment to the bytes in `value` as `{o}`
+    fn commitment(&self, circuit_buffer: &mut String, value: &str) {
+        let (p, o) = (&self.prefix, &self.output);
+
+        match self.lockfile.commitment {
+            Some(Commitment::Poseidon) => {
+                *circuit_buffer += &format!(
+                    r#"
+    signal output {o} <== PoseidonModular({p}maxValueLen)({value});
+"#
+                );
             }
+            None => (),
+        }
+    }
+
+    /// Outputs the number's magnitude as `{o}` and its sign bit as `{o}_sign`. Decimal numbers are
+    /// output as fixed-point integers with `scale` fraction digits. With a predicate, only its
+    /// result is output as `{o}`.
+    ///
+    /// The number can be shorter than `maxValueLen`: only the first `value_length` bytes, which the
+    /// parser reads while `parsing_number` is set, are accumulated.
+    fn extract_number(&self, circuit_buffer: &mut String, debug: bool) {
+        let (p, o) = (&self.prefix, &self.output);
+        let scale = self.lockfile.scale;
+        let (value, sign, visibility) = match self.lockfile.predicate {
+            Some(_) => (format!("{p}number"), format!("{p}number_sign"), ""),
+            None => (o.clone(), format!("{o}_sign"), "output "),
+        };
+
+        // the parser starts a number at its first digit, so the byte before the value is
+        // selected as well to read the `-` sign
+        *circuit_buffer += &format!(
+            r#"
+    signal {p}value_string[{p}maxValueLen+1];
+    signal {visibility}{value};
+    signal {visibility}{sign};
+
+    {p}value_string <== SelectSubArray(DATA_BYTES, {p}maxValueLen+1)(data, {p}value_starting_index[DATA_BYTES-1]-1, {p}maxValueLen+1);
+"#
+        );
+
+        if debug {
+            *circuit_buffer += &format!(
+                r#"
+    log("{p}value_starting_index", {p}value_starting_index[DATA_BYTES-1]);
+    log("{p}value_length", {p}value_length);
+    for (var i=0 ; i<{p}maxValueLen+1; i++) {{
+        log("{o}[",i,"]=", {p}value_string[i]);
+    }}
+"#
+            );
+        }
+
+        *circuit_buffer += &format!(
+            r#"
+    {sign} <== IsEqual()([{p}value_string[0], 45]);
+
+    // `value_length` fits in `maxValueLen`, so no digit is cut off
+    signal {p}is_value_end[{p}maxValueLen+1];
+    var {p}value_ends = 0;
+    for (var i=0 ; i<{p}maxValueLen+1 ; i++) {{
+        {p}is_value_end[i] <== IsEqual()([i, {p}value_length]);
+        {p}value_ends += {p}is_value_end[i];
+    }}
+    {p}value_ends === 1;
+
+    signal {p}in_value[{p}maxValueLen+1];
+    signal {p}is_digit[{p}maxValueLen];
+    signal {p}is_decimal_point[{p}maxValueLen];
+    signal {p}decimal_point[{p}maxValueLen];
+    signal {p}seen_decimal_point[{p}maxValueLen+1];
+    signal {p}fraction_digit[{p}maxValueLen];
+    signal {p}fraction_digits[{p}maxValueLen+1];
+    signal {p}number_value[{p}maxValueLen+1];
+    {p}in_value[0] <== 1;
+    {p}seen_decimal_point[0] <== 0;
+    {p}fraction_digits[0] <== 0;
+    {p}number_value[0] <== 0;
+    for (var i=1 ; i<{p}maxValueLen+1 ; i++) {{
+        // byte is part of the number, i.e. before `value_length`
+        {p}in_value[i] <== {p}in_value[i-1] * (1 - {p}is_value_end[i-1]);
+
+        // number bytes are digits or a decimal point. Exponent notation (`e`, `E`) fails this check.
+        {p}is_digit[i-1] <== InRange(8)({p}value_string[i], [48, 57]);
+        {p}is_decimal_point[i-1] <== IsEqual()([{p}value_string[i], 46]);
+        {p}in_value[i] * (1 - {p}is_digit[i-1] - {p}is_decimal_point[i-1]) === 0;
+
+        // at most one decimal point, every digit after it is a fraction digit
+        {p}decimal_point[i-1] <== {p}in_value[i] * {p}is_decimal_point[i-1];
+        {p}decimal_point[i-1] * {p}seen_decimal_point[i-1] === 0;
+        {p}seen_decimal_point[i] <== {p}seen_decimal_point[i-1] + {p}decimal_point[i-1];
+        {p}fraction_digit[i-1] <== {p}in_value[i] * {p}seen_decimal_point[i-1];
+        {p}fraction_digits[i] <== {p}fraction_digits[i-1] + {p}fraction_digit[i-1];
+
+        // accumulate digits until `value_length`, skipping decimal point
+        {p}number_value[i] <== {p}number_value[i-1] + ({p}in_value[i] - {p}decimal_point[i-1]) * (9 * {p}number_value[i-1] + {p}value_string[i] - 48);
+    }}
+    // number starts with a digit
+    {p}in_value[1] * {p}is_digit[0] === 1;
+
+    // fixed-point scale: pad with zeros up to {scale} fraction digits, more digits are rejected
+    signal {p}fraction_digits_eq[{scale_len}];
+    var {p}valid_fraction_digits = 0;
+    var {p}scale_factor = 0;
+    for (var i=0 ; i<{scale_len} ; i++) {{
+        {p}fraction_digits_eq[i] <== IsEqual()([{p}fraction_digits[{p}maxValueLen], i]);
+        {p}valid_fraction_digits += {p}fraction_digits_eq[i];
+        {p}scale_factor += {p}fraction_digits_eq[i] * 10 ** ({scale} - i);
+    }}
+    {p}valid_fraction_digits === 1;
+
+    {value} <== {p}number_value[{p}maxValueLen] * {p}scale_factor;
+"#,
+            scale_len = scale + 1,
+        );
+
+        if let Some(predicate) = self.lockfile.predicate {
+            self.predicate(circuit_buffer, predicate, &value, &sign);
+        }
+    }
+
+    /// Outputs result of `predicate` on the signed `value` as `{o}`. Values and bounds are shifted
+    /// by 2^251 so that negative values, i.e. `p - |value|`, compare as smaller than positive ones.
+    fn predicate(
+        &self,
+        circuit_buffer: &mut String,
+        predicate: Predicate,
+        value: &str,
+        sign: &str,
+    ) {
+        let (p, o) = (&self.prefix, &self.output);
+
+        *circuit_buffer += &format!(
+            r#"
+    signal output {o};
+
+    signal {p}signed_value <== {value} * (1 - 2 * {sign});
+"#
+        );
+
+        let comparison = |comparator: &str, bound: &str| {
+            format!("{comparator}(252)([{p}signed_value + 2**251, {p}{bound} + 2**251])")
+        };
+        *circuit_buffer += &match predicate {
+            Predicate::Gt => format!("    {o} <== {};\n", comparison("GreaterThan", "bound")),
+            Predicate::Gte => format!("    {o} <== {};\n", comparison("GreaterEqThan", "bound")),
+            Predicate::Lt => format!("    {o} <== {};\n", comparison("LessThan", "bound")),
+            Predicate::Lte => format!("    {o} <== {};\n", comparison("LessEqThan", "bound")),
+            Predicate::Eq => format!("    {o} <== IsEqual()([{p}signed_value, {p}bound]);\n"),
+            Predicate::InRange => format!(
+                r#"    signal {p}above_lower_bound <== {};
+    signal {p}below_upper_bound <== {};
+    {o} <== {p}above_lower_bound * {p}below_upper_bound;
+"#,
+                comparison("GreaterEqThan", "lower_bound"),
+                comparison("LessEqThan", "upper_bound"),
+            ),
+        };
+    }
+
+    fn extract_bool(&self, circuit_buffer: &mut String, debug: bool) {
+        let (p, o) = (&self.prefix, &self.output);
 
-            // remove last 2 chars `, ` from string buffer
-            circuit_buffer.pop();
-            circuit_buffer.pop();
-            circuit_buffer += "]);\n";
+        *circuit_buffer += &format!(
+            r#"
+    signal {p}value_string[{p}maxValueLen];
+    signal output {o};
+
+    {p}value_string <== SelectSubArray(DATA_BYTES, {p}maxValueLen)(data, {p}value_starting_index[DATA_BYTES-1], {p}maxValueLen);
+"#
+        );
+
+        if debug {
+            *circuit_buffer += &format!(
+                r#"
+    log("{p}value_starting_index", {p}value_starting_index[DATA_BYTES-1]);
+    for (var i=0 ; i<{p}maxValueLen; i++) {{
+        log("{o}[",i,"]=", {p}value_string[i]);
+    }}
+"#
+            );
+        }
+
+        *circuit_buffer += &format!(
+            r#"
+    // `true` and `false` differ at first byte, `t` -> 116
+    {o} <== IsEqual()([{p}value_string[0], 116]);
+
+    // remaining bytes should match the literal selected by `{o}`
+    var {p}true_literal[4] = [116, 114, 117, 101];
+    var {p}false_literal[4] = [102, 97, 108, 115];
+    for (var i=0 ; i<4 ; i++) {{
+        {p}value_string[i] === {o} * {p}true_literal[i] + (1 - {o}) * {p}false_literal[i];
+    }}
+    // last byte of `false`, `e` -> 101
+    (1 - {o}) * ({p}value_string[4] - 101) === 0;
+"#
+        );
+    }
+
+    fn extract_null(&self, circuit_buffer: &mut String, debug: bool) {
+        let p = &self.prefix;
+
+        *circuit_buffer += &format!(
+            r#"
+    signal {p}value_string[{p}maxValueLen];
+    {p}value_string <== SelectSubArray(DATA_BYTES, {p}maxValueLen)(data, {p}value_starting_index[DATA_BYTES-1], {p}maxValueLen);
+"#
+        );
+
+        if debug {
+            *circuit_buffer += &format!(
+                r#"
+    log("{p}value_starting_index", {p}value_starting_index[DATA_BYTES-1]);
+    for (var i=0 ; i<{p}maxValueLen; i++) {{
+        log("{p}value[",i,"]=", {p}value_string[i]);
+    }}
+"#
+            );
+        }
+
+        *circuit_buffer += &format!(
+            r#"
+    // value should be `null`
+    var {p}null_literal[4] = [110, 117, 108, 108];
+    for (var i=0 ; i<4 ; i++) {{
+        {p}value_string[i] === {p}null_literal[i];
+    }}
+    // with a padded `maxValueLen`, the byte after `null` isn't a literal byte
+    if ({p}maxValueLen > 4) {{
+        signal {p}is_letter_after_null <== InRange(8)({p}value_string[4], [97, 122]);
+        {p}is_letter_after_null === 0;
+    }}
+"#
+        );
+    }
+
+    fn extract_raw(&self, circuit_buffer: &mut String, debug: bool) {
+        let p = &self.prefix;
+        let (value, visibility) = self.value_bytes_signal();
+
+        if !self.padded {
+            *circuit_buffer += &format!(
+                r#"
+    // span from opening to closing byte should be exactly the value
+    {p}value_length === {p}maxValueLen;
+"#
+            );
+        }
+        let length = self.selected_length(circuit_buffer);
+
+        *circuit_buffer += &format!(
+            r#"
+    signal {visibility}{value}[{p}maxValueLen];
+    {value} <== SelectSubArray(DATA_BYTES, {p}maxValueLen)(data, {p}value_starting_index[DATA_BYTES-1], {length});
+"#
+        );
+
+        if debug {
+            *circuit_buffer += &format!(
+                r#"
+    log("{p}value_starting_index", {p}value_starting_index[DATA_BYTES-1]);
+    log("{p}value_length", {p}value_length);
+    for (var i=0 ; i<{p}maxValueLen; i++) {{
+        log("{value}[",i,"]=", {value}[i]);
+    }}
+"#
+            );
+        }
+
+        self.commitment(circuit_buffer, &value);
+    }
+}
+
+/// `MultiAND` of all `signals`, or `1` if there are none
+fn multi_and(signals: &[String]) -> String {
+    match signals.len() {
+        0 => String::from("1"),
+        n => format!("MultiAND({})([{}])", n, signals.join(", ")),
+    }
+}
+
+/// Builds `ExtractValue`, running the parser once for all `extractors`, and the `config.template`
+/// template selecting each extracted value
+fn build_json_circuit(
+    config: &CircomkitCircuitConfig,
+    params: &[String],
+    extractors: &[ValueExtractor],
+    lockfile: &impl std::fmt::Debug,
+    padded: bool,
+    debug: bool,
+) -> String {
+    let mut circuit_buffer = String::new();
+
+    // Dump out the contents of the lockfile used into the circuit
+    circuit_buffer += "/*\n";
+    circuit_buffer += &format!("{:#?}", lockfile);
+    circuit_buffer += "\n*/\n";
+
+    circuit_buffer += "pragma circom 2.1.9;\n\n";
+    circuit_buffer += "include \"../json/interpreter.circom\";\n";
+    if extractors
+        .iter()
+        .any(|extractor| extractor.lockfile.commitment.is_some())
+    {
+        circuit_buffer += "include \"../utils/hash.circom\";\n";
+    }
+    circuit_buffer += "\n";
+
+    // template ExtractValue(DATA_BYTES, MAX_STACK_HEIGHT, keyLen1, depth1, index2, depth2, keyLen3, depth3, index4, depth4, maxValueLen) {
+    circuit_buffer += &format!("template ExtractValue({}) {{\n", params.join(", "));
+
+    /*
+    signal input data[DATA_BYTES];
+
+    signal input key1[keyLen1];
+    signal input key3[keyLen3];
+     */
+    {
+        circuit_buffer += "    signal input data[DATA_BYTES];\n\n";
+
+        for extractor in extractors {
+            extractor.key_inputs(&mut circuit_buffer);
+        }
+        for extractor in extractors {
+            extractor.padded_key_checks(&mut circuit_buffer);
+        }
+    }
+
+    circuit_buffer += "    // value starting index in `data`\n";
+    for extractor in extractors {
+        circuit_buffer += &format!(
+            "    signal output {}value_starting_index[DATA_BYTES];\n",
+            extractor.prefix
+        );
+        if extractor.has_value_length() {
+            circuit_buffer += &format!("    signal output {}value_length;\n", extractor.prefix);
         }
+    }
 
+    circuit_buffer += r#"
+    component State[DATA_BYTES];
+    State[0] = StateUpdate(MAX_STACK_HEIGHT);
+    State[0].byte           <== data[0];
+    for(var i = 0; i < MAX_STACK_HEIGHT; i++) {
+        State[0].stack[i]   <== [0,0];
+    }
+    State[0].parsing_string <== 0;
+    State[0].parsing_number <== 0;
+
+    signal parsing_key[DATA_BYTES];
+"#;
+
+    for extractor in extractors {
+        extractor.signal_declarations(&mut circuit_buffer);
+    }
+
+    // initialise first iteration
+    {
+        // parsing_key and parsing_object{i}_value
         circuit_buffer += r#"
-    mask[0] <== parsing_value[0] * is_value_match[0];
+    // initialise first iteration
+    parsing_key[0] <== InsideKeyAtTop(MAX_STACK_HEIGHT)(State[0].next_stack, State[0].next_parsing_string, State[0].next_parsing_number);
 "#;
+
+        for extractor in extractors {
+            extractor.initial_iteration(&mut circuit_buffer, debug);
+        }
     }
 
     // debugging
@@ -483,133 +1703,21 @@ fn build_json_circuit(
         // - key match (key 1, key 2)
         // - is next pair
         // - is key match for value
-        // - value_mask
-        // - mask
-
-        // check if inside key or not
-        parsing_key[data_idx] <== InsideKeyAtTop(MAX_STACK_HEIGHT)(State[data_idx].next_stack, State[data_idx].next_parsing_string, State[data_idx].next_parsing_number);
-
-"#;
-
-    /* Determining wheter parsing correct value and array index
-    parsing_object1_value[data_idx-1] <== InsideValue(MAX_STACK_HEIGHT, depth1)(State[data_idx].stack, State[data_idx].parsing_string, State[data_idx].parsing_number);
-    parsing_array2[data_idx-1] <== InsideArrayIndex(MAX_STACK_HEIGHT, index2, depth2)(State[data_idx].stack, State[data_idx].parsing_string, State[data_idx].parsing_number);
-     */
-    {
-        for (i, key) in data.keys.iter().enumerate() {
-            match key {
-                Key::String(_) => {
-                    circuit_buffer += &format!("        parsing_object{}_value[data_idx] <== InsideValue()(State[data_idx].next_stack[depth{}], State[data_idx].next_parsing_string, State[data_idx].next_parsing_number);\n", i+1, i+1);
-                }
-                Key::Num(_) => {
-                    circuit_buffer += &format!("        parsing_array{}[data_idx] <== InsideArrayIndex(index{})(State[data_idx].next_stack[depth{}], State[data_idx].next_parsing_string, State[data_idx].next_parsing_number);\n", i+1, i+1, i+1);
-                }
-            }
-        }
-    }
-
-    // parsing correct value = AND(all individual stack values)
-    //     parsing_value[data_idx-1] <== MultiAND(4)([parsing_object1_value[data_idx-1], parsing_array2[data_idx-1], parsing_object3_value[data_idx-1], parsing_array4[data_idx-1]]);
-    {
-        circuit_buffer += &format!(
-            "        // parsing correct value = AND(all individual stack values)\n        parsing_value[data_idx] <== MultiAND({})([",
-            data.keys.len()
-        );
-
-        for (i, key) in data.keys.iter().take(data.keys.len() - 1).enumerate() {
-            match key {
-                Key::String(_) => {
-                    circuit_buffer += &format!("parsing_object{}_value[data_idx], ", i + 1)
-                }
-                Key::Num(_) => circuit_buffer += &format!("parsing_array{}[data_idx], ", i + 1),
-            }
-        }
-        match data.keys[data.keys.len() - 1] {
-            Key::String(_) => {
-                circuit_buffer += &format!("parsing_object{}_value[data_idx]]);\n", data.keys.len())
-            }
-            Key::Num(_) => {
-                circuit_buffer += &format!("parsing_array{}[data_idx]]);\n", data.keys.len())
-            }
-        }
-
-        // optional debug logs
-        if debug {
-            circuit_buffer += "        // log(\"parsing value:\", ";
-            for (i, key) in data.keys.iter().enumerate() {
-                match key {
-                    Key::String(_) => {
-                        circuit_buffer += &format!("parsing_object{}_value[data_idx], ", i + 1)
-                    }
-                    Key::Num(_) => circuit_buffer += &format!("parsing_array{}[data_idx], ", i + 1),
-                }
-            }
-            circuit_buffer += "parsing_value[data_idx]);\n\n";
-        }
-    }
-
-    let mut num_objects = 0;
-
-    /*
-    to get correct value, check:
-    - key matches at current index and depth of key is as specified
-    - whether next KV pair starts
-    - whether key matched for a value (propogate key match until new KV pair of lower depth starts)
-    is_key1_match[data_idx-1] <== KeyMatchAtDepth(DATA_BYTES, MAX_STACK_HEIGHT, keyLen1, depth1)(data, key1, data_idx-1, parsing_key[data_idx-1], State[data_idx].stack);
-    is_next_pair_at_depth1[data_idx-1] <== NextKVPairAtDepth(MAX_STACK_HEIGHT, depth1)(State[data_idx].stack, data[data_idx-1]);
-    is_key1_match_for_value[data_idx] <== Mux1()([is_key1_match_for_value[data_idx-1] * (1-is_next_pair_at_depth1[data_idx-1]), is_key1_match[data_idx-1] * (1-is_next_pair_at_depth1[data_idx-1])], is_key1_match[data_idx-1]);
-    */
-    {
-        circuit_buffer += r#"
-        // to get correct value, check:
-        // - key matches at current index and depth of key is as specified
-        // - whether next KV pair starts
-        // - whether key matched for a value (propogate key match until new KV pair of lower depth starts)
-"#;
-
-        for (i, key) in data.keys.iter().enumerate() {
-            match key {
-                Key::String(_) => {
-                    num_objects += 1;
-                    circuit_buffer += &format!("        is_key{}_match[data_idx] <== KeyMatchAtDepth(DATA_BYTES, MAX_STACK_HEIGHT, keyLen{}, depth{})(data, key{}, data_idx, parsing_key[data_idx], State[data_idx].next_stack);\n", i+1, i+1, i+1, i+1);
-                    circuit_buffer += &format!("        is_next_pair_at_depth{}[data_idx] <== NextKVPairAtDepth(MAX_STACK_HEIGHT)(State[data_idx].next_stack, data[data_idx], depth{});\n", i+1, i+1);
-                    circuit_buffer += &format!("        is_key{}_match_for_value[data_idx+1] <== Mux1()([is_key{}_match_for_value[data_idx] * (1-is_next_pair_at_depth{}[data_idx]), is_key{}_match[data_idx] * (1-is_next_pair_at_depth{}[data_idx])], is_key{}_match[data_idx]);\n", i+1, i+1, i+1, i+1, i+1, i+1);
-                    if debug {
-                        circuit_buffer += &format!("        // log(\"is_key{}_match_for_value\", is_key{}_match_for_value[data_idx+1]);\n\n", i + 1, i + 1);
-                    }
-                }
-                Key::Num(_) => (),
-            }
-        }
-    }
+        // - value_mask
+        // - mask
 
-    // is_value_match[data_idx] <== MultiAND(2)([is_key1_match_for_value[data_idx], is_key3_match_for_value[data_idx]]);
-    {
-        circuit_buffer += &format!(
-            "        is_value_match[data_idx] <== MultiAND({})([",
-            num_objects
-        );
-        for (i, key) in data.keys.iter().enumerate() {
-            match key {
-                Key::String(_) => {
-                    circuit_buffer += &format!("is_key{}_match_for_value[data_idx+1], ", i + 1)
-                }
-                Key::Num(_) => (),
-            }
-        }
+        // check if inside key or not
+        parsing_key[data_idx] <== InsideKeyAtTop(MAX_STACK_HEIGHT)(State[data_idx].next_stack, State[data_idx].next_parsing_string, State[data_idx].next_parsing_number);
+
+"#;
 
-        // remove last 2 chars `, ` from string buffer
-        circuit_buffer.pop();
-        circuit_buffer.pop();
-        circuit_buffer += "]);\n";
+    for extractor in extractors {
+        extractor.iteration(&mut circuit_buffer, debug);
     }
 
     // debugging and output bytes
     {
-        circuit_buffer += r#"
-        // mask = currently parsing value and all subsequent keys matched
-        mask[data_idx] <== parsing_value[data_idx] * is_value_match[data_idx];
-    }"#;
+        circuit_buffer += "    }";
 
         // Debugging
         if debug {
@@ -623,31 +1731,79 @@ fn build_json_circuit(
     "#;
         }
 
-        circuit_buffer += r#"
+        circuit_buffer += "\n";
+        for extractor in extractors {
+            extractor.starting_index(&mut circuit_buffer);
+        }
 
-    // find starting index of value in data by matching mask
-    signal is_zero_mask[DATA_BYTES];
-    signal is_prev_starting_index[DATA_BYTES];
-    value_starting_index[0] <== 0;
-    is_prev_starting_index[0] <== 0;
-    is_zero_mask[0] <== IsZero()(mask[0]);
-    for (var i=1 ; i<DATA_BYTES ; i++) {
-        is_zero_mask[i] <== IsZero()(mask[i]);
-        is_prev_starting_index[i] <== IsZero()(value_starting_index[i-1]);
-        value_starting_index[i] <== value_starting_index[i-1] + i * (1-is_zero_mask[i]) * is_prev_starting_index[i];
+        // template ends
+        circuit_buffer += "}\n";
     }
+
+    // template ExtractStringValue(DATA_BYTES, MAX_STACK_HEIGHT, keyLen1, depth1, ..., maxValueLen) {
+    {
+        let inputs = extractors.iter().flat_map(|extractor| {
+            extractor
+                .lockfile
+                .value_inputs(&extractor.prefix, extractor.padded_keys)
+        });
+        let inputs = std::iter::once(String::from("data"))
+            .chain(inputs)
+            .collect::<Vec<_>>();
+
+        circuit_buffer += &format!("\ntemplate {}({}) {{\n", config.template, params.join(", "));
+        circuit_buffer += "    signal input data[DATA_BYTES];\n\n";
+        if padded {
+            circuit_buffer += r#"    // Number of bytes in `data` before the zero padding
+    signal input dataLen;
+    component zeroPadding = AssertZeroPadded(DATA_BYTES);
+    zeroPadding.in     <== data;
+    zeroPadding.length <== dataLen;
+
 "#;
+        }
+        for extractor in extractors {
+            extractor.key_inputs(&mut circuit_buffer);
+            extractor.bound_inputs(&mut circuit_buffer);
+        }
+        circuit_buffer += "\n";
+
+        let mut outputs = vec![];
+        for extractor in extractors {
+            circuit_buffer += &format!(
+                "    signal {}value_starting_index[DATA_BYTES];\n",
+                extractor.prefix
+            );
+            if extractor.has_value_length() {
+                circuit_buffer += &format!("    signal {}value_length;\n", extractor.prefix);
+            }
+            outputs.append(&mut extractor.extract_value_outputs());
+        }
+
+        // value_starting_index <== ExtractValue(DATA_BYTES, MAX_STACK_HEIGHT, keyLen1, depth1, keyLen2, depth2, index3, depth3, index4, depth4, maxValueLen)(data, key1, key2);
+        let outputs = match outputs.len() {
+            1 => outputs.remove(0),
+            _ => format!("({})", outputs.join(", ")),
+        };
+        circuit_buffer += &format!(
+            "    {} <== ExtractValue({})({});\n",
+            outputs,
+            params.join(", "),
+            inputs.join(", "),
+        );
+
+        for extractor in extractors {
+            extractor.value(&mut circuit_buffer, debug);
+        }
 
-        // template ends
         circuit_buffer += "}\n";
     }
 
-    match data.value_type {
-        ValueType::String => extract_string(config, data, &mut circuit_buffer, debug),
-        ValueType::Number => extract_number(config, data, &mut circuit_buffer, debug),
-    }
+    circuit_buffer
+}
 
-    // write circuits to file
+/// Writes circuit to `circuits/main/{output_filename}.circom`
+fn write_circuit(circuit_buffer: &str, output_filename: &str) -> Result<(), Box<dyn Error>> {
     let mut file_path = std::env::current_dir()?;
     file_path.push("circuits");
     file_path.push("main");
@@ -673,13 +1829,28 @@ fn build_json_circuit(
 pub fn json_circuit_from_args(
     args: &ExtractorArgs,
 ) -> Result<CircomkitCircuitConfig, Box<dyn Error>> {
-    let lockfile: Lockfile = serde_json::from_slice(&fs::read(&args.lockfile)?)?;
+    let lockfile: ExtractorLockfile = serde_json::from_slice(&fs::read(&args.lockfile)?)?;
 
     let circuit_filename = format!("json_{}", args.circuit_name);
 
     let input = fs::read(&args.input_file)?;
 
-    let config = json_circuit_from_lockfile(&input, &lockfile, &circuit_filename, args.debug)?;
+    let config = match lockfile {
+        ExtractorLockfile::Single(lockfile) => json_circuit_from_lockfile(
+            &input,
+            &lockfile,
+            &circuit_filename,
+            &args.max_sizes,
+            args.debug,
+        )?,
+        ExtractorLockfile::Multi(lockfile) => json_circuit_from_multi_lockfile(
+            &input,
+            &lockfile,
+            &circuit_filename,
+            &args.max_sizes,
+            args.debug,
+        )?,
+    };
     config.write(&args.circuit_name)?;
 
     Ok(config)
@@ -689,17 +1860,89 @@ pub fn json_circuit_from_lockfile(
     input: &[u8],
     lockfile: &Lockfile,
     output_filename: &str,
+    max_sizes: &MaxSizes,
+    debug: bool,
+) -> Result<CircomkitCircuitConfig, Box<dyn Error>> {
+    let mut config = lockfile.build_circuit_config(input, output_filename)?;
+
+    // fail before compilation if the circuit wouldn't find the value
+    let states = trace(input, config.params[1])?;
+    predict_value_starting_index(input, &states, lockfile)?;
+    let params = max_sizes.key_params(lockfile.params(), &mut config.params);
+    max_sizes.apply(&params, &mut config.params)?;
+
+    let extractor = ValueExtractor {
+        prefix: String::new(),
+        output: match (lockfile.predicate, lockfile.commitment) {
+            (Some(_), _) => String::from("result"),
+            (_, Some(_)) => String::from("commitment"),
+            _ => String::from("value"),
+        },
+        lockfile,
+        padded: max_sizes.max_value_len.is_some(),
+        padded_keys: max_sizes.max_key_len.is_some(),
+    };
+    let circuit_buffer = build_json_circuit(
+        &config,
+        &params,
+        &[extractor],
+        lockfile,
+        max_sizes.is_padded(),
+        debug,
+    );
+    write_circuit(&circuit_buffer, output_filename)?;
+
+    Ok(config)
+}
+
+pub fn json_circuit_from_multi_lockfile(
+    input: &[u8],
+    lockfile: &MultiLockfile,
+    output_filename: &str,
+    max_sizes: &MaxSizes,
     debug: bool,
 ) -> Result<CircomkitCircuitConfig, Box<dyn Error>> {
-    let config = lockfile.build_circuit_config(input, output_filename)?;
+    let mut config = lockfile.build_circuit_config(input, output_filename)?;
+
+    // fail before compilation if the circuit wouldn't find a value
+    let states = trace(input, config.params[1])?;
+    for extraction in lockfile.extractions.iter() {
+        predict_value_starting_index(input, &states, &extraction.lockfile)
+            .map_err(|err| format!("extraction `{}`: {}", extraction.name, err))?;
+    }
+    let params = max_sizes.key_params(lockfile.params(), &mut config.params);
+    max_sizes.apply(&params, &mut config.params)?;
+
+    let extractors = lockfile
+        .extractions
+        .iter()
+        .map(|extraction| ValueExtractor {
+            prefix: extraction.prefix(),
+            output: extraction.name.clone(),
+            lockfile: &extraction.lockfile,
+            padded: max_sizes.max_value_len.is_some(),
+            padded_keys: max_sizes.max_key_len.is_some(),
+        })
+        .collect::<Vec<_>>();
+    let circuit_buffer = build_json_circuit(
+        &config,
+        &params,
+        &extractors,
+        lockfile,
+        max_sizes.is_padded(),
+        debug,
+    );
+    write_circuit(&circuit_buffer, output_filename)?;
 
-    build_json_circuit(&config, lockfile, output_filename, debug)?;
     Ok(config)
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::{machine, witness::poseidon_commitment};
+    use ark_bn254::Fr;
+    use std::str::FromStr;
 
     #[test]
     fn params() {
@@ -722,7 +1965,7 @@ mod test {
         ))
         .unwrap();
 
-        let inputs = lockfile.inputs();
+        let inputs = lockfile.inputs(false);
 
         assert_eq!(inputs.len(), 2);
         assert_eq!(inputs[0], "data");
@@ -768,13 +2011,795 @@ mod test {
         assert_eq!(value, "Taylor Swift");
     }
 
+    #[test]
+    fn json_literal_value() {
+        let input = include_bytes!("../../examples/json/test/value_literal.json");
+
+        let lockfile: Lockfile = serde_json::from_slice(include_bytes!(
+            "../../examples/json/lockfile/value_bool.json"
+        ))
+        .unwrap();
+        assert_eq!(lockfile.get_value(input).unwrap(), "false");
+        assert_eq!(
+            lockfile.populate_params(input).unwrap(),
+            [input.len(), 2, 1, 0, 0, 1, 5]
+        );
+
+        let lockfile: Lockfile = serde_json::from_slice(include_bytes!(
+            "../../examples/json/lockfile/value_null.json"
+        ))
+        .unwrap();
+        assert_eq!(lockfile.get_value(input).unwrap(), "null");
+        assert_eq!(
+            lockfile.populate_params(input).unwrap(),
+            [input.len(), 2, 1, 0, 1, 1, 4]
+        );
+
+        // value type mismatch
+        let lockfile: Lockfile =
+            serde_json::from_str(r#"{"keys": ["k"], "value_type": "string"}"#).unwrap();
+        assert!(lockfile.get_value(input).is_err());
+    }
+
+    #[test]
+    fn json_number_value() {
+        let input = include_bytes!("../../examples/json/test/value_number_signed.json");
+
+        let lockfile: Lockfile = serde_json::from_slice(include_bytes!(
+            "../../examples/json/lockfile/value_number_decimal.json"
+        ))
+        .unwrap();
+        assert_eq!(lockfile.get_value(input).unwrap(), "314");
+        assert_eq!(
+            lockfile.populate_params(input).unwrap(),
+            [input.len(), 2, 1, 0, 4]
+        );
+
+        let number = |keys: &str, scale: usize| {
+            let lockfile: Lockfile = serde_json::from_str(&format!(
+                r#"{{"keys": {}, "value_type": "number", "scale": {}}}"#,
+                keys, scale
+            ))
+            .unwrap();
+            lockfile
+                .get_value(input)
+                .map(|value| {
+                    (
+                        value,
+                        lockfile.populate_params(input).unwrap()[4..].to_vec(),
+                    )
+                })
+                .map_err(|err| err.to_string())
+        };
+
+        // `-` sign isn't part of the parsed number
+        assert_eq!(number(r#"["a"]"#, 0).unwrap(), ("-12".to_string(), vec![2]));
+        assert_eq!(number(r#"["b"]"#, 3).unwrap().0, "3140");
+        assert_eq!(
+            number(r#"["c", 0]"#, 1).unwrap(),
+            ("-5".to_string(), vec![0, 1, 3])
+        );
+        assert!(number(r#"["b"]"#, 1)
+            .unwrap_err()
+            .contains("more fraction digits"));
+        assert!(number(r#"["c", 1]"#, 0)
+            .unwrap_err()
+            .contains("exponent notation"));
+
+        // the circuit's `value_length` spans the decimal point, and its digits add up to the value
+        for (keys, scale) in [
+            (r#"["a"]"#, 0),
+            (r#"["b"]"#, 2),
+            (r#"["b"]"#, 3),
+            (r#"["c", 0]"#, 1),
+        ] {
+            let lockfile: Lockfile = serde_json::from_str(&format!(
+                r#"{{"keys": {}, "value_type": "number", "scale": {}}}"#,
+                keys, scale
+            ))
+            .unwrap();
+            let max_value_len = max_value_len(input, &lockfile);
+            let (value_length, output) = number_output(input, &lockfile, max_value_len).unwrap();
+            assert_eq!(value_length, max_value_len, "keys {}", keys);
+            assert_eq!(output, expected_number(input, &lockfile), "keys {}", keys);
+        }
+        let lockfile: Lockfile =
+            serde_json::from_str(r#"{"keys": ["c", 1], "value_type": "number"}"#).unwrap();
+        // `1e5` is 3 bytes long, but `e` isn't a digit
+        assert!(number_output(input, &lockfile, 3).is_err());
+
+        assert_eq!(scaled_number("0.05", 2).unwrap(), (false, "5".to_string()));
+        assert_eq!(scaled_number("-0", 0).unwrap(), (true, "0".to_string()));
+
+        // scale only applies to numbers
+        let lockfile: Lockfile =
+            serde_json::from_str(r#"{"keys": ["a"], "value_type": "string", "scale": 1}"#).unwrap();
+        assert!(lockfile.get_value(input).is_err());
+    }
+
+    /// `value_length`, and `value` and `value_sign` of the number extractor circuit of `lockfile`,
+    /// run on the `machine::json` simulator
+    fn number_output(
+        input: &[u8],
+        lockfile: &Lockfile,
+        max_value_len: usize,
+    ) -> Result<(usize, (Fr, bool)), Box<dyn Error>> {
+        let states = machine::json::trace(input, json_max_stack_height(input).unwrap()).unwrap();
+        let mask = machine::json::value_mask(input, &states, lockfile).unwrap();
+        let value_length = machine::json::value_length(&mask, &lockfile.value_type).unwrap();
+        let output = machine::json::extract_number(
+            input,
+            machine::json::value_starting_index(&mask),
+            value_length,
+            max_value_len,
+            lockfile.scale,
+        )?;
+        Ok((value_length, output))
+    }
+
+    /// `get_value` of a number as the circuit's `value` and `value_sign`
+    fn expected_number(input: &[u8], lockfile: &Lockfile) -> (Fr, bool) {
+        let value = lockfile.get_value(input).unwrap();
+        (
+            Fr::from_str(value.trim_start_matches('-')).unwrap(),
+            value.starts_with('-'),
+        )
+    }
+
+    /// `maxValueLen` param of `lockfile`
+    fn max_value_len(input: &[u8], lockfile: &Lockfile) -> usize {
+        *lockfile.populate_params(input).unwrap().last().unwrap()
+    }
+
+    /// Predicate result of the number extractor circuit of `lockfile` on the simulator, with
+    /// negative `bounds` given as `p - |bound|`
+    fn predicate_output(input: &[u8], lockfile: &Lockfile, bounds: &[i128]) -> bool {
+        let (_, (value, sign)) =
+            number_output(input, lockfile, max_value_len(input, lockfile)).unwrap();
+        let bounds: Vec<Fr> = bounds
+            .iter()
+            .map(|bound| match *bound < 0 {
+                true => -Fr::from(bound.unsigned_abs()),
+                false => Fr::from(bound.unsigned_abs()),
+            })
+            .collect();
+        machine::json::predicate(lockfile.predicate.unwrap(), value, sign, &bounds).unwrap()
+    }
+
+    /// `value` bytes selected by the string or raw extractor circuit of `lockfile` from `data`, run
+    /// on the `machine::json` simulator with its `MAX_STACK_HEIGHT` and `maxValueLen` params. A
+    /// `padded` circuit selects `value_length` bytes, zero padded up to `maxValueLen`.
+    fn selected_value(
+        data: &[u8],
+        lockfile: &Lockfile,
+        max_stack_height: usize,
+        max_value_len: usize,
+        padded: bool,
+    ) -> Result<Vec<u8>, Box<dyn Error>> {
+        let states = machine::json::trace(data, max_stack_height)?;
+        let mask = machine::json::value_mask(data, &states, lockfile)?;
+        let start = machine::json::value_starting_index(&mask);
+        let value_length = machine::json::value_length(&mask, &lockfile.value_type).unwrap();
+
+        // strings are selected after their opening quote, and unpadded raw values span exactly
+        // `maxValueLen`
+        let start = match lockfile.value_type {
+            ValueType::String => start + 1,
+            _ if !padded && value_length != max_value_len => {
+                return Err(format!("raw value is {} bytes long", value_length).into())
+            }
+            _ => start,
+        };
+        let length = match padded {
+            true if value_length > max_value_len => {
+                return Err(format!("value is {} bytes long", value_length).into())
+            }
+            true => value_length,
+            false => max_value_len,
+        };
+        Ok(machine::json::select_sub_array(
+            data,
+            start,
+            length,
+            max_value_len,
+        ))
+    }
+
+    /// Extractor circuit of `lockfile`, without the lockfile dump in its header
+    fn single_value_circuit(input: &[u8], lockfile: &Lockfile) -> String {
+        value_circuit(input, lockfile, &MaxSizes::default())
+    }
+
+    /// Extractor circuit of `lockfile` with `max_sizes`, without the lockfile dump in its header
+    fn value_circuit(input: &[u8], lockfile: &Lockfile, max_sizes: &MaxSizes) -> String {
+        let mut config = lockfile.build_circuit_config(input, "test").unwrap();
+        let params = max_sizes.key_params(lockfile.params(), &mut config.params);
+        let extractor = ValueExtractor {
+            prefix: String::new(),
+            output: String::from("value"),
+            lockfile,
+            padded: max_sizes.max_value_len.is_some(),
+            padded_keys: max_sizes.max_key_len.is_some(),
+        };
+        let circuit = build_json_circuit(
+            &config,
+            &params,
+            &[extractor],
+            lockfile,
+            max_sizes.is_padded(),
+            false,
+        );
+        circuit.split_once("*/\n").unwrap().1.to_string()
+    }
+
+    #[test]
+    fn json_number_circuit() {
+        let input = include_bytes!("../../examples/json/test/value_array.json");
+        let number = |index: usize| -> Lockfile {
+            serde_json::from_str(&format!(
+                r#"{{"keys": ["k", {}], "value_type": "number"}}"#,
+                index
+            ))
+            .unwrap()
+        };
+
+        // `420 ` is followed by whitespace, which isn't part of the value
+        assert_eq!(number(0).populate_params(input).unwrap()[6], 3);
+        assert_eq!(number(1).populate_params(input).unwrap()[6], 2);
+        assert_eq!(number(2).populate_params(input).unwrap()[6], 4);
+
+        // circuit only depends on the keys, so the one generated for `4200` also extracts `69`
+        // with `maxValueLen` 4
+        assert_eq!(
+            single_value_circuit(input, &number(2)),
+            single_value_circuit(input, &number(1))
+        );
+        for index in 0..4 {
+            let lockfile = number(index);
+            let (value_length, output) = number_output(input, &lockfile, 4).unwrap();
+            assert_eq!(value_length, max_value_len(input, &lockfile));
+            assert_eq!(output, expected_number(input, &lockfile));
+        }
+        // `4200` doesn't fit in `maxValueLen` 3
+        assert!(number_output(input, &number(2), 3).is_err());
+
+        // signed values and bounds are shifted by 2^251 before comparing, so negative ones compare
+        // as smaller than positive ones
+        let input = include_bytes!("../../examples/json/test/value_number_signed.json");
+        let predicate = |keys: &str, scale: usize, predicate: &str| -> Lockfile {
+            serde_json::from_str(&format!(
+                r#"{{"keys": {}, "value_type": "number", "scale": {}, "predicate": "{}"}}"#,
+                keys, scale, predicate
+            ))
+            .unwrap()
+        };
+        // `a` is -12, `b` is 314 with scale 2, and `c[0]` is -5 with scale 1
+        for (keys, scale) in [(r#"["a"]"#, 0), (r#"["b"]"#, 2), (r#"["c", 0]"#, 1)] {
+            let value: i128 = predicate(keys, scale, "eq")
+                .get_value(input)
+                .unwrap()
+                .parse()
+                .unwrap();
+            for name in ["gt", "gte", "lt", "lte", "eq"] {
+                let lockfile = predicate(keys, scale, name);
+                for bound in [
+                    value - 1,
+                    value,
+                    value + 1,
+                    -value,
+                    0,
+                    i64::MIN.into(),
+                    i64::MAX.into(),
+                ] {
+                    assert_eq!(
+                        predicate_output(input, &lockfile, &[bound]),
+                        lockfile.check_predicate(input, &[bound]).unwrap(),
+                        "{} {} with bound {}",
+                        keys,
+                        name,
+                        bound
+                    );
+                }
+            }
+
+            let lockfile = predicate(keys, scale, "in_range");
+            for bounds in [
+                [value, value],
+                [value - 1, value + 1],
+                [value + 1, value + 2],
+                [value - 2, value - 1],
+                [-value.abs(), value.abs()],
+            ] {
+                assert_eq!(
+                    predicate_output(input, &lockfile, &bounds),
+                    lockfile.check_predicate(input, &bounds).unwrap(),
+                    "{} in_range with bounds {:?}",
+                    keys,
+                    bounds
+                );
+            }
+        }
+
+        // at the bound
+        let lockfile = predicate(r#"["a"]"#, 0, "gt");
+        assert!(!predicate_output(input, &lockfile, &[-12]));
+        assert!(predicate_output(input, &lockfile, &[-13]));
+        let lockfile = predicate(r#"["a"]"#, 0, "lte");
+        assert!(predicate_output(input, &lockfile, &[-12]));
+        assert!(!predicate_output(input, &lockfile, &[-13]));
+    }
+
+    #[test]
+    fn json_number_predicate() {
+        let input = include_bytes!("../../examples/json/test/value_number_signed.json");
+        let lockfile: Lockfile = serde_json::from_slice(include_bytes!(
+            "../../examples/json/lockfile/value_number_predicate.json"
+        ))
+        .unwrap();
+
+        assert_eq!(lockfile.inputs(false), ["data", "key1", "bound"]);
+        let config = lockfile.build_circuit_config(input, "output").unwrap();
+        assert_eq!(config.template, "ExtractNumPredicate");
+        assert_eq!(config.pubs, ["bound"]);
+
+        // `a` is -12, and the circuit agrees with `check_predicate` around it
+        for (bound, expected) in [(-13, true), (-12, true), (-11, false), (12, false)] {
+            assert_eq!(
+                lockfile.check_predicate(input, &[bound]).unwrap(),
+                expected,
+                "bound {}",
+                bound
+            );
+            assert_eq!(
+                predicate_output(input, &lockfile, &[bound]),
+                expected,
+                "bound {}",
+                bound
+            );
+        }
+        assert!(lockfile.check_predicate(input, &[]).is_err());
+
+        let lockfile: Lockfile = serde_json::from_str(
+            r#"{"keys": ["c", 0], "value_type": "number", "scale": 1, "predicate": "in_range"}"#,
+        )
+        .unwrap();
+        assert_eq!(lockfile.public_inputs(), ["lower_bound", "upper_bound"]);
+        // `c[0]` is -0.5, i.e. -5 with scale 1
+        for (bounds, expected) in [
+            ([-5, 0], true),
+            ([-4, 0], false),
+            ([-6, -5], true),
+            ([-6, -6], false),
+        ] {
+            assert_eq!(lockfile.check_predicate(input, &bounds).unwrap(), expected);
+            assert_eq!(predicate_output(input, &lockfile, &bounds), expected);
+        }
+
+        // predicates only apply to numbers
+        let lockfile: Lockfile =
+            serde_json::from_str(r#"{"keys": ["a"], "value_type": "string", "predicate": "eq"}"#)
+                .unwrap();
+        assert!(lockfile.get_value(input).is_err());
+    }
+
+    #[test]
+    fn json_raw_value() {
+        let input = include_bytes!("../../examples/json/test/value_object.json");
+        let lockfile: Lockfile = serde_json::from_slice(include_bytes!(
+            "../../examples/json/lockfile/value_raw.json"
+        ))
+        .unwrap();
+
+        let value = lockfile.get_value(input).unwrap();
+        assert_eq!(value, r#"{ "h": { "a": "c" }}"#);
+
+        let params = lockfile.populate_params(input).unwrap();
+        assert_eq!(params[params.len() - 1], value.len());
+
+        let config = lockfile
+            .build_circuit_config(input, "output_filename")
+            .unwrap();
+        assert_eq!(config.template, "ExtractRawValue");
+
+        // `raw` value type only accepts objects and arrays
+        let lockfile: Lockfile =
+            serde_json::from_str(r#"{"keys": ["ab"], "value_type": "raw"}"#).unwrap();
+        assert!(lockfile.get_value(input).is_err());
+    }
+
+    #[test]
+    fn json_padded_values() {
+        let input = include_bytes!("../../examples/json/test/value_string.json");
+        let lockfile: Lockfile = serde_json::from_slice(include_bytes!(
+            "../../examples/json/lockfile/value_string.json"
+        ))
+        .unwrap();
+        let padded = MaxSizes {
+            max_data_bytes: Some(1024),
+            max_value_len: Some(64),
+            ..Default::default()
+        };
+
+        // values are selected by their length from zero padded data, and zero padded up to
+        // `maxValueLen`, which is the value of the witness
+        let padded_value = |input: &[u8], lockfile: &Lockfile, max_value_len: usize| {
+            let data = pad(input, padded.max_data_bytes, "max-data-bytes").unwrap();
+            let max_stack_height = lockfile.populate_params(input).unwrap()[1];
+            selected_value(&data, lockfile, max_stack_height, max_value_len, true)
+        };
+        let expected = |input: &[u8], lockfile: &Lockfile| {
+            pad(
+                &lockfile.get_value_bytes(input).unwrap(),
+                padded.max_value_len,
+                "max-value-len",
+            )
+            .unwrap()
+        };
+        assert_eq!(
+            padded_value(input, &lockfile, 64).unwrap(),
+            expected(input, &lockfile)
+        );
+        // value doesn't fit in a smaller `maxValueLen`
+        assert!(padded_value(input, &lockfile, 0).is_err());
+
+        // raw values no longer need to be exactly `maxValueLen` long
+        let input = include_bytes!("../../examples/json/test/value_object.json");
+        let lockfile: Lockfile = serde_json::from_slice(include_bytes!(
+            "../../examples/json/lockfile/value_raw.json"
+        ))
+        .unwrap();
+        assert_eq!(
+            padded_value(input, &lockfile, 64).unwrap(),
+            expected(input, &lockfile)
+        );
+        assert!(padded_value(input, &lockfile, 4).is_err());
+        let max_stack_height = lockfile.populate_params(input).unwrap()[1];
+        assert!(selected_value(input, &lockfile, max_stack_height, 64, false).is_err());
+
+        // bounds are checked against the exact params
+        let mut config = lockfile.build_circuit_config(input, "test").unwrap();
+        let max_sizes = MaxSizes {
+            max_value_len: Some(4),
+            ..Default::default()
+        };
+        assert!(max_sizes
+            .apply(&lockfile.params(), &mut config.params)
+            .is_err());
+    }
+
+    #[test]
+    fn json_padded_literals() {
+        let input = include_bytes!("../../examples/json/test/value_literal.json");
+        let bool_lockfile: Lockfile = serde_json::from_slice(include_bytes!(
+            "../../examples/json/lockfile/value_bool.json"
+        ))
+        .unwrap();
+        let null_lockfile: Lockfile = serde_json::from_slice(include_bytes!(
+            "../../examples/json/lockfile/value_null.json"
+        ))
+        .unwrap();
+        let max_sizes = MaxSizes {
+            max_data_bytes: Some(1024),
+            max_value_len: Some(12),
+            ..Default::default()
+        };
+
+        // `maxValueLen` of literals is padded like any other value
+        for lockfile in [&bool_lockfile, &null_lockfile] {
+            let mut config = lockfile.build_circuit_config(input, "test").unwrap();
+            max_sizes
+                .apply(&lockfile.params(), &mut config.params)
+                .unwrap();
+            assert_eq!(config.params.last(), Some(&12));
+            assert_ne!(
+                value_circuit(input, lockfile, &max_sizes),
+                single_value_circuit(input, lockfile)
+            );
+        }
+
+        // bytes selected at the literal, `maxValueLen` long
+        let value_string = |data: &[u8], lockfile: &Lockfile, max_value_len: usize| {
+            let states = machine::json::trace(data, 2).unwrap();
+            let mask = machine::json::value_mask(data, &states, lockfile).unwrap();
+            let start = machine::json::value_starting_index(&mask);
+            machine::json::select_sub_array(data, start, max_value_len, max_value_len)
+        };
+        let data = max_sizes.pad_data(input).unwrap();
+        for max_value_len in [5, 12] {
+            let selected = value_string(&data, &bool_lockfile, max_value_len);
+            assert!(!machine::json::extract_bool(&selected).unwrap());
+        }
+        for max_value_len in [4, 12] {
+            let selected = value_string(&data, &null_lockfile, max_value_len);
+            assert_eq!(&selected[..4], b"null");
+            machine::json::extract_null(&selected).unwrap();
+        }
+
+        // only the literal bytes are compared, but no literal byte may follow them
+        assert!(machine::json::extract_null(b"null ]\0\0").is_ok());
+        assert!(machine::json::extract_null(b"nulls ]\0").is_err());
+        assert!(machine::json::extract_null(b"nul\0").is_err());
+        assert!(machine::json::extract_bool(b"true, \0").unwrap());
+        assert!(machine::json::extract_bool(b"falsy\0").is_err());
+    }
+
+    #[test]
+    fn json_padded_keys() {
+        let key = |key: &str| -> Lockfile {
+            serde_json::from_str(&format!(
+                r#"{{"keys": ["{}"], "value_type": "string"}}"#,
+                key
+            ))
+            .unwrap()
+        };
+        let max_sizes = MaxSizes {
+            max_key_len: Some(8),
+            ..Default::default()
+        };
+
+        // keys are inputs up to `maxKeyLen`, so other keys at the same path share the circuit
+        let input = br#"{ "key1": "abc", "k": "x", "longkey": "yz" }"#;
+        let circuit = value_circuit(input, &key("key1"), &max_sizes);
+        assert_eq!(circuit, value_circuit(input, &key("k"), &max_sizes));
+        assert_eq!(circuit, value_circuit(input, &key("longkey"), &max_sizes));
+        assert_ne!(circuit, single_value_circuit(input, &key("key1")));
+
+        // with the padded keys of the witness, the circuit selects each key's value
+        let max_stack_height = json_max_stack_height(input).unwrap();
+        let states = machine::json::trace(input, max_stack_height).unwrap();
+        let padded_mask = |lockfile: &Lockfile, key_len: usize| {
+            let keys = lockfile.keys_as_bytes(max_sizes.max_key_len).unwrap();
+            let key_lens = HashMap::from([(String::from("keyLen1"), key_len)]);
+            machine::json::padded_value_mask(input, &states, lockfile, &keys, &key_lens)
+        };
+        for name in ["key1", "k", "longkey"] {
+            let lockfile = key(name);
+            let mask = padded_mask(&lockfile, name.len()).unwrap();
+            assert_eq!(
+                mask,
+                machine::json::value_mask(input, &states, &lockfile).unwrap()
+            );
+
+            let value_len = lockfile.get_value_bytes(input).unwrap().len();
+            let start = machine::json::value_starting_index(&mask) + 1;
+            assert_eq!(
+                machine::json::select_sub_array(input, start, value_len, value_len),
+                lockfile.get_value_bytes(input).unwrap(),
+                "key {}",
+                name
+            );
+        }
+
+        // a key matches only if followed by a quote, and `keyLen` is the length of the key bytes
+        let mask = padded_mask(&key("key"), 3).unwrap();
+        assert!(mask.iter().all(|mask| *mask == 0));
+        assert!(padded_mask(&key("key1"), 3).is_err());
+        assert!(padded_mask(&key("key1"), 5).is_err());
+        let keys = HashMap::from([(String::from("key1"), b"k\0y\0\0\0\0\0".to_vec())]);
+        let key_lens = HashMap::from([(String::from("keyLen1"), 1)]);
+        assert!(
+            machine::json::padded_value_mask(input, &states, &key("k"), &keys, &key_lens).is_err()
+        );
+
+        let lockfile = key("key1");
+        assert_eq!(lockfile.inputs(true), ["data", "key1", "keyLen1"]);
+        let keys = lockfile.keys_as_bytes(Some(8)).unwrap();
+        assert_eq!(keys["key1"], b"key1\0\0\0\0");
+        assert_eq!(lockfile.key_lens()["keyLen1"], 4);
+        assert!(lockfile.keys_as_bytes(Some(3)).is_err());
+    }
+
+    #[test]
+    fn json_value_commitment() {
+        let input = include_bytes!("../../examples/json/test/value_string.json");
+        let lockfile: Lockfile = serde_json::from_slice(include_bytes!(
+            "../../examples/json/lockfile/value_string_commitment.json"
+        ))
+        .unwrap();
+
+        assert_eq!(lockfile.get_value_bytes(input).unwrap(), b"v");
+        let config = lockfile.build_circuit_config(input, "output").unwrap();
+        assert_eq!(config.template, "ExtractStringCommitment");
+
+        // only the hash of the value bytes is output, which is the commitment of the witness
+        let commitment = |input: &[u8], lockfile: &Lockfile| {
+            let params = lockfile.populate_params(input).unwrap();
+            let value =
+                selected_value(input, lockfile, params[1], *params.last().unwrap(), false).unwrap();
+            assert_eq!(value, lockfile.get_value_bytes(input).unwrap());
+            poseidon_commitment(&value).unwrap()
+        };
+        assert_eq!(
+            commitment(input, &lockfile),
+            poseidon_commitment(b"v").unwrap()
+        );
+
+        let lockfile: Lockfile = serde_json::from_str(
+            r#"{"keys": ["g", "h"], "value_type": "raw", "commitment": "poseidon"}"#,
+        )
+        .unwrap();
+        let input = include_bytes!("../../examples/json/test/value_object.json");
+        assert_eq!(lockfile.get_value_bytes(input).unwrap(), br#"{ "a": "c" }"#);
+        assert_eq!(
+            lockfile
+                .build_circuit_config(input, "output")
+                .unwrap()
+                .template,
+            "ExtractRawCommitment"
+        );
+        assert_eq!(
+            commitment(input, &lockfile),
+            poseidon_commitment(br#"{ "a": "c" }"#).unwrap()
+        );
+        // a different value has a different commitment
+        let lockfile: Lockfile = serde_json::from_str(
+            r#"{"keys": ["a"], "value_type": "raw", "commitment": "poseidon"}"#,
+        )
+        .unwrap();
+        assert_ne!(
+            commitment(input, &lockfile),
+            poseidon_commitment(br#"{ "a": "c" }"#).unwrap()
+        );
+
+        // commitments only apply to byte values
+        let lockfile: Lockfile = serde_json::from_str(
+            r#"{"keys": ["a"], "value_type": "number", "commitment": "poseidon"}"#,
+        )
+        .unwrap();
+        let input = include_bytes!("../../examples/json/test/value_number_signed.json");
+        assert!(lockfile.get_value(input).is_err());
+    }
+
+    #[test]
+    fn multiple_values() {
+        let input = include_bytes!("../../examples/json/test/spotify.json");
+        let lockfile: ExtractorLockfile = serde_json::from_slice(include_bytes!(
+            "../../examples/json/lockfile/multiple_values.json"
+        ))
+        .unwrap();
+        let ExtractorLockfile::Multi(lockfile) = lockfile else {
+            panic!("expected multiple extractions");
+        };
+
+        let params = lockfile.params();
+        assert_eq!(params.len(), 2 + (2 * 5 + 1) + (2 * 4 + 1));
+        assert_eq!(params[2], "artist_keyLen1");
+        assert_eq!(params[params.len() - 1], "kind_maxValueLen");
+
+        let populated_params = lockfile.populate_params(input).unwrap();
+        assert_eq!(populated_params.len(), params.len());
+        assert_eq!(populated_params[12], "Taylor Swift".len());
+        assert_eq!(populated_params[21], "Artist".len());
+
+        let inputs = lockfile.inputs(false);
+        assert_eq!(inputs.len(), 1 + 4 + 3);
+        let keys = lockfile.keys_as_bytes(None).unwrap();
+        assert_eq!(keys.len(), inputs.len() - 1);
+        assert_eq!(keys["kind_key4"], b"data");
+        assert_eq!(lockfile.key_lens()["kind_keyLen4"], 4);
+
+        let values = lockfile.get_values(input).unwrap();
+        assert_eq!(
+            values[0],
+            ("artist".to_string(), "Taylor Swift".to_string())
+        );
+        assert_eq!(values[1], ("kind".to_string(), "Artist".to_string()));
+    }
+
+    #[test]
+    fn multiple_values_names() {
+        let lockfile: MultiLockfile = serde_json::from_str(
+            r#"{"extractions": [
+                {"name": "a", "keys": ["k"], "value_type": "string"},
+                {"name": "a", "keys": ["k"], "value_type": "string"}
+            ]}"#,
+        )
+        .unwrap();
+        assert!(lockfile.validate().is_err());
+
+        let lockfile: MultiLockfile = serde_json::from_str(
+            r#"{"extractions": [{"name": "1a", "keys": ["k"], "value_type": "string"}]}"#,
+        )
+        .unwrap();
+        assert!(lockfile.validate().is_err());
+
+        let lockfile: ExtractorLockfile = serde_json::from_slice(include_bytes!(
+            "../../examples/json/lockfile/value_string.json"
+        ))
+        .unwrap();
+        assert!(matches!(lockfile, ExtractorLockfile::Single(_)));
+    }
+
+    #[test]
+    fn key_path() {
+        let keys = parse_key_path("$.data.items[0].profile.name").unwrap();
+        let expected: Vec<Key> =
+            serde_json::from_slice(include_bytes!("../../examples/json/lockfile/spotify.json"))
+                .map(|lockfile: Lockfile| lockfile.keys)
+                .unwrap();
+        assert_eq!(format!("{:?}", keys), format!("{:?}", expected));
+
+        let keys = parse_key_path(r##"$["a.b"][12].'c[0]'."d\"e""##).unwrap();
+        assert_eq!(
+            format!("{:?}", keys),
+            r#"[String("a.b"), Num(12), String("c[0]"), String("d\"e")]"#
+        );
+
+        let lockfile: Lockfile =
+            serde_json::from_str(r#"{"keys": "$.k[2]", "value_type": "number"}"#).unwrap();
+        assert_eq!(format!("{:?}", lockfile.keys), r#"[String("k"), Num(2)]"#);
+    }
+
+    #[test]
+    fn key_path_errors() {
+        for (path, position) in [
+            ("data.items", 0),
+            ("$", 1),
+            ("$.", 2),
+            ("$.a..b", 4),
+            ("$.a[x]", 4),
+            ("$.a[0", 5),
+            ("$.a[0x]", 5),
+            ("$.a['b]", 4),
+            ("$.a b", 3),
+        ] {
+            let err = parse_key_path(path).unwrap_err().to_string();
+            assert!(
+                err.starts_with(&format!("invalid key path at position {}:", position)),
+                "{}: {}",
+                path,
+                err
+            );
+        }
+
+        let err = serde_json::from_str::<ExtractorLockfile>(
+            r#"{"keys": "$.a[", "value_type": "string"}"#,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("unclosed `[`"));
+    }
+
     #[test]
     fn max_stack_height() {
         let input = include_bytes!("../../examples/json/test/two_keys.json");
 
-        assert_eq!(json_max_stack_height(input), 1);
+        assert_eq!(json_max_stack_height(input).unwrap(), 1);
 
         let input = include_bytes!("../../examples/json/test/spotify.json");
-        assert_eq!(json_max_stack_height(input), 5);
+        assert_eq!(json_max_stack_height(input).unwrap(), 5);
+
+        // escaped quotes toggle strings in the circuit too
+        let input = include_bytes!("../../examples/json/test/string_escape.json");
+        assert_eq!(json_max_stack_height(input).unwrap(), 1);
+
+        // an escaped backslash doesn't escape the closing quote
+        assert_eq!(
+            json_max_stack_height(br#"{"a": "\\", "b": [1]}"#).unwrap(),
+            2
+        );
+        // the first byte is scanned too
+        assert_eq!(json_max_stack_height(b" [[], [[0]]]").unwrap(), 3);
+        assert_eq!(json_max_stack_height(br#"{"a": "]}"}"#).unwrap(), 1);
+        // circuit checks for overflow before ignoring `[` inside a string
+        assert_eq!(json_max_stack_height(br#"{"a": "["}"#).unwrap(), 2);
+    }
+
+    #[test]
+    fn max_stack_height_errors() {
+        for (input, error) in [
+            (&br#"{"a": 1}}"#[..], "unmatched `}` at byte 8"),
+            (br#"{"a": [1}"#, "`}` at byte 8 doesn't close `[` at byte 6"),
+            (br#"{"a": [1]"#, "unclosed `{` at byte 0"),
+            (br#"{"a": "b}"#, "unterminated string starting at byte 6"),
+            (br#""}""#, "unmatched `}` at byte 1"),
+            // circuit ends the string at the escaped quote, and `[` is left open
+            (
+                br#"{"a": "\"[\""}"#,
+                "`}` at byte 13 doesn't close `[` at byte 9",
+            ),
+        ] {
+            let err = json_max_stack_height(input).unwrap_err();
+            assert_eq!(err.to_string(), error);
+        }
     }
 }
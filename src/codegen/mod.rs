@@ -1,13 +1,24 @@
 pub mod http;
 pub mod integrated;
 pub mod json;
+pub mod nivc;
 use crate::FileType;
 
 use clap::Parser;
 use http::http_circuit_from_args;
 use integrated::integrated_circuit;
 use json::json_circuit_from_args;
-use std::path::PathBuf;
+use nivc::nivc_circuits;
+use serde::{Deserialize, Serialize};
+use std::{error::Error, path::PathBuf};
+
+/// Commitment output by a circuit instead of the extracted bytes
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Commitment {
+    /// `PoseidonModular` hash of the bytes, see `circuits/utils/hash.circom`
+    Poseidon,
+}
 
 #[derive(Parser, Debug)]
 /// JSON Extractor arguments
@@ -30,6 +41,133 @@ pub struct ExtractorArgs {
     /// Optional circuit debug logs
     #[arg(long, short, action = clap::ArgAction::SetTrue)]
     debug: bool,
+
+    #[command(flatten)]
+    max_sizes: MaxSizes,
+}
+
+/// Upper bounds used as circuit params instead of the exact sizes of the input, so that one circuit
+/// serves all inputs that fit. Inputs are zero padded up to the bounds.
+#[derive(clap::Args, Debug, Clone, Default)]
+pub struct MaxSizes {
+    /// Maximum length of the input, which is zero padded and passed with its length as `dataLen`
+    #[arg(long)]
+    pub max_data_bytes: Option<usize>,
+
+    /// Maximum length of the HTTP body, and of the payload of a chunked body
+    #[arg(long)]
+    pub max_content_length: Option<usize>,
+
+    /// Maximum stack height of the JSON parser
+    #[arg(long)]
+    pub max_stack_height: Option<usize>,
+
+    /// Maximum length of extracted JSON values
+    #[arg(long)]
+    pub max_value_len: Option<usize>,
+
+    /// Maximum length of JSON keys, which are zero padded and passed with their lengths as
+    /// `keyLen1`, ... instead of being fixed by the circuit
+    #[arg(long)]
+    pub max_key_len: Option<usize>,
+}
+
+impl MaxSizes {
+    /// Whether the input is zero padded, i.e. circuits take its length as `dataLen`
+    pub fn is_padded(&self) -> bool {
+        self.max_data_bytes.is_some()
+    }
+
+    /// Replaces the exact sizes in `params`, named by `names`, with their maximum sizes
+    pub fn apply(&self, names: &[String], params: &mut [usize]) -> Result<(), Box<dyn Error>> {
+        for (name, param) in names.iter().zip(params.iter_mut()) {
+            let (bound, flag) = match name.as_str() {
+                "DATA_BYTES" => (self.max_data_bytes, "max-data-bytes"),
+                "maxContentLength" | "maxPayloadLength" => {
+                    (self.max_content_length, "max-content-length")
+                }
+                "MAX_STACK_HEIGHT" => (self.max_stack_height, "max-stack-height"),
+                name if name.ends_with("maxValueLen") => (self.max_value_len, "max-value-len"),
+                name if name.ends_with("maxKeyLen") => (self.max_key_len, "max-key-len"),
+                _ => continue,
+            };
+            if let Some(bound) = bound {
+                if *param > bound {
+                    return Err(format!(
+                        "`{}` of the input is {}, more than `--{}` {}",
+                        name, param, flag, bound
+                    )
+                    .into());
+                }
+                *param = bound;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// With `max_key_len`, replaces the `keyLen{i}` params of each extraction with one `maxKeyLen` at
+    /// the position of its first key, set to the longest key length. Returns the names of `params`.
+    pub fn key_params(&self, names: Vec<String>, params: &mut Vec<usize>) -> Vec<String> {
+        if self.max_key_len.is_none() {
+            return names;
+        }
+
+        let mut key_names = Vec::<String>::new();
+        let mut key_params = Vec::new();
+        for (name, &param) in names.iter().zip(params.iter()) {
+            let name = match key_len_prefix(name) {
+                Some(prefix) => format!("{}maxKeyLen", prefix),
+                None => name.clone(),
+            };
+            match key_names.iter().position(|key_name| *key_name == name) {
+                Some(i) => key_params[i] = param.max(key_params[i]),
+                None => {
+                    key_names.push(name);
+                    key_params.push(param);
+                }
+            }
+        }
+        *params = key_params;
+
+        key_names
+    }
+
+    /// Names returned by `key_params`
+    pub fn key_param_names(&self, names: Vec<String>) -> Vec<String> {
+        let mut params = vec![0; names.len()];
+        self.key_params(names, &mut params)
+    }
+
+    /// `input` zero padded to `max_data_bytes`
+    pub fn pad_data(&self, input: &[u8]) -> Result<Vec<u8>, Box<dyn Error>> {
+        pad(input, self.max_data_bytes, "max-data-bytes")
+    }
+}
+
+/// Prefix of a `{prefix}keyLen{i}` param
+fn key_len_prefix(name: &str) -> Option<&str> {
+    let (prefix, i) = name.rsplit_once("keyLen")?;
+    (!i.is_empty() && i.bytes().all(|b| b.is_ascii_digit())).then_some(prefix)
+}
+
+/// `bytes` zero padded to `len`, if any. `flag` names the bound in errors.
+pub fn pad(bytes: &[u8], len: Option<usize>, flag: &str) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut padded = bytes.to_vec();
+    if let Some(len) = len {
+        if bytes.len() > len {
+            return Err(format!(
+                "input has {} bytes, more than `--{}` {}",
+                bytes.len(),
+                flag,
+                len
+            )
+            .into());
+        }
+        padded.resize(len, 0);
+    }
+
+    Ok(padded)
 }
 
 impl ExtractorArgs {
@@ -48,8 +186,96 @@ impl ExtractorArgs {
             FileType::Extended => {
                 integrated_circuit(self)?;
             }
+            FileType::Nivc => {
+                nivc_circuits(self)?;
+            }
         }
 
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn max_sizes() {
+        let names = [
+            "DATA_BYTES",
+            "maxContentLength",
+            "versionLen",
+            "kind_maxValueLen",
+        ]
+        .map(String::from);
+        let max_sizes = MaxSizes {
+            max_data_bytes: Some(1024),
+            max_value_len: Some(32),
+            ..Default::default()
+        };
+        assert!(max_sizes.is_padded());
+
+        // exact sizes are kept without a bound
+        let mut params = [100, 20, 8, 4];
+        max_sizes.apply(&names, &mut params).unwrap();
+        assert_eq!(params, [1024, 20, 8, 32]);
+
+        let mut params = [2000, 20, 8, 4];
+        let err = max_sizes.apply(&names, &mut params).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "`DATA_BYTES` of the input is 2000, more than `--max-data-bytes` 1024"
+        );
+
+        assert_eq!(max_sizes.pad_data(b"ab").unwrap().len(), 1024);
+        assert_eq!(pad(b"ab", Some(4), "flag").unwrap(), b"ab\0\0");
+        assert_eq!(pad(b"ab", None, "flag").unwrap(), b"ab");
+        assert!(pad(b"abc", Some(2), "flag").is_err());
+        assert!(!MaxSizes::default().is_padded());
+    }
+
+    #[test]
+    fn max_key_len() {
+        let names = [
+            "DATA_BYTES",
+            "a_keyLen1",
+            "a_depth1",
+            "a_keyLen3",
+            "a_depth3",
+            "b_keyLen1",
+            "b_maxValueLen",
+        ]
+        .map(String::from)
+        .to_vec();
+        let mut params = vec![100, 3, 0, 7, 2, 5, 10];
+
+        // exact key lengths are kept without a bound
+        let mut exact = params.clone();
+        assert_eq!(
+            MaxSizes::default().key_params(names.clone(), &mut exact),
+            names
+        );
+        assert_eq!(exact, params);
+
+        let max_sizes = MaxSizes {
+            max_key_len: Some(8),
+            ..Default::default()
+        };
+        let names = max_sizes.key_params(names, &mut params);
+        assert_eq!(
+            names,
+            [
+                "DATA_BYTES",
+                "a_maxKeyLen",
+                "a_depth1",
+                "a_depth3",
+                "b_maxKeyLen",
+                "b_maxValueLen"
+            ]
+        );
+        assert_eq!(params, [100, 7, 0, 2, 5, 10]);
+
+        max_sizes.apply(&names, &mut params).unwrap();
+        assert_eq!(params, [100, 8, 0, 2, 8, 10]);
+    }
+}
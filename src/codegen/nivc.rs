@@ -0,0 +1,331 @@
+use super::{
+    http::HeaderMode,
+    integrated::ExtendedLockfile,
+    json::{json_max_stack_height, Key},
+    MaxSizes,
+};
+use crate::{circuit_config::CircomkitCircuitConfig, ExtractorArgs, FileType};
+use std::error::Error;
+
+/// Step of a NIVC chain, folded in the order of [`nivc_steps`]. Every step reads the `step_out` of
+/// the previous step as its `step_in`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NivcStep {
+    /// Parses the HTTP data and locks its start line
+    ParseAndLockStartLine,
+    /// Locks the `i`th header of the lockfile
+    LockHeader(usize),
+    /// Masks everything but the HTTP body
+    MaskBody,
+    /// Parses the masked body as JSON
+    JsonParse,
+    /// Masks the value of the `i`th key of the JSON lockfile
+    MaskObject(usize),
+    /// Masks the element at the `i`th key of the JSON lockfile, an array index
+    MaskArrayIndex(usize),
+    /// Outputs the masked value
+    ExtractValue,
+}
+
+/// Sizes of the step circuits. `DATA_BYTES` and `MAX_STACK_HEIGHT` size `step_in` and `step_out`,
+/// so they're shared by all steps.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NivcParams {
+    pub data_bytes: usize,
+    pub max_stack_height: usize,
+    /// `MAX_BEGINNING_LENGTH`, `MAX_MIDDLE_LENGTH` and `MAX_FINAL_LENGTH` of the start line
+    pub start_line_lens: [usize; 3],
+    pub max_header_name_len: usize,
+    pub max_header_value_len: usize,
+    pub max_key_len: usize,
+    /// length of the extracted value, including the quotes of a string
+    pub max_value_len: usize,
+}
+
+impl NivcStep {
+    /// Name of the step circuit, shared by all steps with the same template
+    pub fn name(&self) -> &'static str {
+        match self {
+            NivcStep::ParseAndLockStartLine => "parse_and_lock_start_line",
+            NivcStep::LockHeader(_) => "lock_header",
+            NivcStep::MaskBody => "body_mask",
+            NivcStep::JsonParse => "json_parse",
+            NivcStep::MaskObject(_) => "json_mask_object",
+            NivcStep::MaskArrayIndex(_) => "json_mask_array_index",
+            NivcStep::ExtractValue => "extract_value",
+        }
+    }
+
+    fn file(&self) -> &'static str {
+        match self {
+            NivcStep::ParseAndLockStartLine => "http/nivc/parse_and_lock_start_line",
+            NivcStep::LockHeader(_) => "http/nivc/lock_header",
+            NivcStep::MaskBody => "http/nivc/body_mask",
+            NivcStep::JsonParse => "json/nivc/parse",
+            NivcStep::MaskObject(_) | NivcStep::MaskArrayIndex(_) => "json/nivc/masker",
+            NivcStep::ExtractValue => "json/nivc/extractor",
+        }
+    }
+
+    fn template(&self) -> &'static str {
+        match self {
+            NivcStep::ParseAndLockStartLine => "ParseAndLockStartLine",
+            NivcStep::LockHeader(_) => "LockHeader",
+            NivcStep::MaskBody => "HTTPMaskBodyNIVC",
+            NivcStep::JsonParse => "JsonParseNIVC",
+            NivcStep::MaskObject(_) => "JsonMaskObjectNIVC",
+            NivcStep::MaskArrayIndex(_) => "JsonMaskArrayIndexNIVC",
+            NivcStep::ExtractValue => "MaskExtractFinal",
+        }
+    }
+
+    fn params(&self, params: &NivcParams) -> Vec<usize> {
+        let mut step_params = vec![params.data_bytes, params.max_stack_height];
+        match self {
+            NivcStep::ParseAndLockStartLine => step_params.extend(params.start_line_lens),
+            NivcStep::LockHeader(_) => {
+                step_params.push(params.max_header_name_len);
+                step_params.push(params.max_header_value_len);
+            }
+            NivcStep::MaskObject(_) => step_params.push(params.max_key_len),
+            NivcStep::ExtractValue => step_params.push(params.max_value_len),
+            NivcStep::MaskBody | NivcStep::JsonParse | NivcStep::MaskArrayIndex(_) => {}
+        }
+        step_params
+    }
+
+    /// circomkit config of the step circuit, which folds `step_in` publicly
+    pub fn circuit_config(&self, params: &NivcParams) -> CircomkitCircuitConfig {
+        CircomkitCircuitConfig {
+            file: self.file().to_string(),
+            template: self.template().to_string(),
+            params: self.params(params),
+            pubs: vec![String::from("step_in")],
+        }
+    }
+}
+
+/// Lockfile features the step circuits can't prove
+fn unsupported_feature(
+    lockfile: &ExtendedLockfile,
+) -> Result<Option<&'static str>, Box<dyn Error>> {
+    let http = &lockfile.http;
+    let feature = if http.chunked() {
+        Some("`chunked` bodies")
+    } else if http.commitment().is_some() || lockfile.json.commitment.is_some() {
+        Some("`commitment`")
+    } else if http.content_length_header()?.is_some() {
+        Some("`checkContentLength`")
+    } else if http.case_insensitive_headers() {
+        Some("`caseInsensitiveHeaders`")
+    } else if !http.forbidden_headers().is_empty() {
+        Some("`forbiddenHeaders`")
+    } else if !http.query_params().is_empty() {
+        Some("`queryParams`")
+    } else if http
+        .headers()
+        .iter()
+        .any(|header| header.mode != HeaderMode::Exact)
+    {
+        Some("header modes other than `exact`")
+    } else if lockfile.json.predicate.is_some() {
+        Some("`predicate`")
+    } else {
+        None
+    };
+
+    Ok(feature)
+}
+
+/// Steps proving `lockfile`: the HTTP steps lock the start line and each header and mask the body,
+/// then the JSON steps parse the body, mask the value at each key in turn and output it
+pub fn nivc_steps(lockfile: &ExtendedLockfile) -> Result<Vec<NivcStep>, Box<dyn Error>> {
+    if let Some(feature) = unsupported_feature(lockfile)? {
+        return Err(format!("NIVC steps don't support {}", feature).into());
+    }
+
+    let mut steps = vec![NivcStep::ParseAndLockStartLine];
+    steps.extend((0..lockfile.http.headers().len()).map(NivcStep::LockHeader));
+    steps.push(NivcStep::MaskBody);
+    steps.push(NivcStep::JsonParse);
+    for (i, key) in lockfile.json.keys.iter().enumerate() {
+        match key {
+            Key::String(_) => steps.push(NivcStep::MaskObject(i)),
+            Key::Num(_) => steps.push(NivcStep::MaskArrayIndex(i)),
+        }
+    }
+    steps.push(NivcStep::ExtractValue);
+
+    Ok(steps)
+}
+
+impl NivcParams {
+    /// Sizes of `input`, replaced by `max_sizes` if any
+    pub fn populate(
+        lockfile: &ExtendedLockfile,
+        input: Vec<u8>,
+        max_sizes: &MaxSizes,
+    ) -> Result<Self, Box<dyn Error>> {
+        let payload = lockfile.http.parse_payload(input.clone())?;
+        let value = lockfile.json.get_raw_value(&payload)?;
+        let data_bytes = input.len();
+
+        // fail before compilation if a step wouldn't match the input
+        lockfile.http.populate_params(input)?;
+
+        let headers = lockfile.http.headers();
+        let max_key_len = lockfile
+            .json
+            .keys
+            .iter()
+            .filter_map(|key| match key {
+                Key::String(key) => Some(key.len()),
+                Key::Num(_) => None,
+            })
+            .max()
+            .unwrap_or(0);
+
+        // masking steps need a stack of at least 2
+        let max_stack_height = json_max_stack_height(&payload)?.max(2);
+
+        let names =
+            ["DATA_BYTES", "MAX_STACK_HEIGHT", "maxKeyLen", "maxValueLen"].map(String::from);
+        let mut params = [data_bytes, max_stack_height, max_key_len, value.get().len()];
+        max_sizes.apply(&names, &mut params)?;
+        let [data_bytes, max_stack_height, max_key_len, max_value_len] = params;
+
+        Ok(NivcParams {
+            data_bytes,
+            max_stack_height,
+            start_line_lens: lockfile.http.start_line().map(str::len),
+            max_header_name_len: headers.iter().map(|h| h.name.len()).max().unwrap_or(0),
+            max_header_value_len: headers.iter().map(|h| h.value.len()).max().unwrap_or(0),
+            max_key_len,
+            max_value_len,
+        })
+    }
+}
+
+/// Adds the step circuits proving an extended lockfile to `circuits.json` as
+/// `{circuit_name}_{step}`, and writes the circuit of each step in order to
+/// `circuits/main/{circuit_name}_nivc.json`
+pub fn nivc_circuits(args: &ExtractorArgs) -> Result<(), Box<dyn Error>> {
+    let lockfile: ExtendedLockfile = serde_json::from_slice(&std::fs::read(&args.lockfile)?)?;
+    let input = FileType::Http.read_input(&args.input_file)?;
+
+    let steps = nivc_steps(&lockfile)?;
+    let params = NivcParams::populate(&lockfile, input, &args.max_sizes)?;
+
+    let mut chain = vec![];
+    for step in steps.iter() {
+        let name = format!("{}_{}", args.circuit_name, step.name());
+        if !chain.contains(&name) {
+            step.circuit_config(&params).write(&name)?;
+        }
+        chain.push(name);
+    }
+
+    let mut file_path = std::env::current_dir()?;
+    file_path.push("circuits");
+    file_path.push("main");
+    std::fs::create_dir_all(&file_path)?;
+    file_path.push(format!("{}_nivc.json", args.circuit_name));
+    std::fs::write(&file_path, serde_json::to_string_pretty(&chain)?)?;
+
+    println!("NIVC steps written at: {}", file_path.display());
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::path::Path;
+
+    fn spotify_lockfile() -> ExtendedLockfile {
+        serde_json::from_slice(include_bytes!(
+            "../../examples/http/lockfile/spotify_extended.lock.json"
+        ))
+        .unwrap()
+    }
+
+    fn spotify_input() -> Vec<u8> {
+        FileType::Http
+            .read_input(Path::new("examples/http/spotify_top_artists_response.http"))
+            .unwrap()
+    }
+
+    #[test]
+    fn steps() {
+        let steps = nivc_steps(&spotify_lockfile()).unwrap();
+        assert_eq!(
+            steps,
+            [
+                NivcStep::ParseAndLockStartLine,
+                NivcStep::LockHeader(0),
+                NivcStep::MaskBody,
+                NivcStep::JsonParse,
+                NivcStep::MaskObject(0),
+                NivcStep::MaskObject(1),
+                NivcStep::MaskArrayIndex(2),
+                NivcStep::MaskObject(3),
+                NivcStep::MaskObject(4),
+                NivcStep::ExtractValue,
+            ]
+        );
+
+        let chunked: ExtendedLockfile = serde_json::from_slice(include_bytes!(
+            "../../examples/http/lockfile/chunked_extended.lock.json"
+        ))
+        .unwrap();
+        assert_eq!(
+            nivc_steps(&chunked).unwrap_err().to_string(),
+            "NIVC steps don't support `chunked` bodies"
+        );
+    }
+
+    #[test]
+    fn params() {
+        let lockfile = spotify_lockfile();
+        let input = spotify_input();
+        let data_bytes = input.len();
+
+        let params = NivcParams::populate(&lockfile, input.clone(), &MaxSizes::default()).unwrap();
+        assert_eq!(params.data_bytes, data_bytes);
+        assert_eq!(params.start_line_lens, [8, 3, 2]);
+        assert_eq!(params.max_header_name_len, "content-type".len());
+        assert_eq!(params.max_key_len, "profile".len());
+        assert_eq!(params.max_value_len, "\"Taylor Swift\"".len());
+
+        let config = NivcStep::LockHeader(0).circuit_config(&params);
+        assert_eq!(config.template, "LockHeader");
+        assert_eq!(
+            config.params,
+            [
+                data_bytes,
+                params.max_stack_height,
+                "content-type".len(),
+                "application/json; charset=utf-8".len()
+            ]
+        );
+        assert_eq!(config.pubs, ["step_in"]);
+        assert_eq!(
+            NivcStep::JsonParse.circuit_config(&params).params,
+            [data_bytes, params.max_stack_height]
+        );
+
+        let max_sizes = MaxSizes {
+            max_data_bytes: Some(1024),
+            max_key_len: Some(16),
+            ..Default::default()
+        };
+        let params = NivcParams::populate(&lockfile, input.clone(), &max_sizes).unwrap();
+        assert_eq!((params.data_bytes, params.max_key_len), (1024, 16));
+
+        let max_sizes = MaxSizes {
+            max_key_len: Some(4),
+            ..Default::default()
+        };
+        assert!(NivcParams::populate(&lockfile, input, &max_sizes).is_err());
+    }
+}
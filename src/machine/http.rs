@@ -0,0 +1,480 @@
+//! HTTP parser state machine, mirroring `HttpStateUpdate` in `circuits/http/parser/machine.circom`,
+//! and how the generated locking circuits locate the start line, headers and body with it.
+
+use std::error::Error;
+
+/// Parser state after reading a byte, i.e. the `next_*` outputs of `HttpStateUpdate`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HttpState {
+    /// `1`, `2`, `3` in the first, second and third part of the start line, incremented on the
+    /// first two SP, and `0` after the start line
+    pub parsing_start: i64,
+    /// `k` in the `k`-th header line, `0` outside of headers
+    pub parsing_header: i64,
+    pub parsing_field_name: i64,
+    /// incremented on each `:` in a header line
+    pub parsing_field_value: i64,
+    pub parsing_body: i64,
+    /// bytes of CRLF CRLF read so far
+    pub line_status: i64,
+}
+
+impl Default for HttpState {
+    /// Initial state of the locking circuits, parsing the start line
+    fn default() -> Self {
+        Self {
+            parsing_start: 1,
+            parsing_header: 0,
+            parsing_field_name: 0,
+            parsing_field_value: 0,
+            parsing_body: 0,
+            line_status: 0,
+        }
+    }
+}
+
+/// `LessThan(n)([a, b])`, or `None` where its `Num2Bits` constraint fails
+fn less_than(n: u32, a: i64, b: i64) -> Option<i64> {
+    let value = a + (1 << n) - b;
+    (0..1 << (n + 1))
+        .contains(&value)
+        .then_some(1 - (value >> n))
+}
+
+/// `GreaterEqThan(n)([a, b])`, i.e. `LessThan(n)([b, a + 1])`
+fn greater_eq_than(n: u32, a: i64, b: i64) -> Option<i64> {
+    less_than(n, b, a + 1)
+}
+
+impl HttpState {
+    /// `HttpStateUpdate`: returns the state after reading `byte`, or an error where the
+    /// circuit's constraints fail.
+    pub fn update(&self, byte: u8) -> Result<Self, String> {
+        let read = |c: u8| i64::from(byte == c);
+        let read_sp = read(b' ');
+        let read_colon = read(b':');
+        let read_cr = read(b'\r');
+        let read_lf = read(b'\n');
+        let not_cr_and_lf = (1 - read_cr) * (1 - read_lf);
+
+        let read_crlf = i64::from(self.line_status == 1) * read_lf;
+        let read_crlf_crlf = i64::from(self.line_status == 3) * read_lf;
+
+        // `StateChange`
+        let (start, header) = (self.parsing_start, self.parsing_header);
+        let is_parsing_start = greater_eq_than(2, start, 1).ok_or_else(|| {
+            format!(
+                "`parsing_start` is {}, start line has too many spaces",
+                start
+            )
+        })?;
+        // SP in a response's reason phrase doesn't start a new part
+        let is_before_last_value = less_than(2, start, 3)
+            .ok_or_else(|| format!("`parsing_start` is {}, out of range", start))?;
+        let increment_parsing_start = read_sp * is_before_last_value * is_parsing_start;
+        let disable_parsing_start = read_crlf * start;
+        let enable_parsing_header = read_crlf * is_parsing_start;
+        let is_parsing_header = greater_eq_than(3, header, 1)
+            .ok_or_else(|| format!("`parsing_header` is {}, too many header lines", header))?;
+        let increment_parsing_header = read_crlf * is_parsing_header;
+        let disable_parsing_header = read_crlf_crlf * header;
+        let is_parsing_field_value = is_parsing_header * read_colon;
+        let enable_parsing_body = read_crlf_crlf * is_parsing_header;
+
+        Ok(Self {
+            parsing_start: start + increment_parsing_start - disable_parsing_start,
+            parsing_header: header + enable_parsing_header + increment_parsing_header
+                - disable_parsing_header,
+            parsing_field_name: self.parsing_field_name
+                + enable_parsing_header
+                + increment_parsing_header
+                - is_parsing_field_value
+                - enable_parsing_body,
+            parsing_field_value: self.parsing_field_value + is_parsing_field_value
+                - increment_parsing_header,
+            parsing_body: self.parsing_body + enable_parsing_body,
+            line_status: self.line_status + read_cr + read_crlf + read_crlf_crlf
+                - self.line_status * not_cr_and_lf,
+        })
+    }
+}
+
+/// Runs the parser over `input`. Returns the state after each byte, i.e. `State[i].next_*` in the
+/// circuit, or the byte where the circuit's constraints fail.
+pub fn trace(input: &[u8]) -> Result<Vec<HttpState>, Box<dyn Error>> {
+    let mut state = HttpState::default();
+    let mut states = Vec::with_capacity(input.len());
+    for (i, byte) in input.iter().enumerate() {
+        state = state
+            .update(*byte)
+            .map_err(|err| format!("HTTP parser fails at byte {}: {}", i, err))?;
+        states.push(state);
+    }
+
+    Ok(states)
+}
+
+/// Header name and value
+pub type Header<'a> = (&'a [u8], &'a [u8]);
+
+/// Parts of an HTTP message, as located by the locking circuits
+#[derive(Debug, PartialEq, Eq)]
+pub struct HttpParts<'a> {
+    /// method, target and version of a request, or version, status and message of a response
+    pub start_line: [&'a [u8]; 3],
+    /// header names and values in input order
+    pub headers: Vec<Header<'a>>,
+    pub body: &'a [u8],
+}
+
+impl HttpParts<'_> {
+    /// Whether `chunked` is the last coding in `Transfer-Encoding`
+    pub fn is_chunked(&self) -> bool {
+        self.headers.iter().any(|(name, value)| {
+            name.eq_ignore_ascii_case(b"transfer-encoding")
+                && value
+                    .rsplit(|byte| *byte == b',')
+                    .next()
+                    .is_some_and(|coding| coding.trim_ascii().eq_ignore_ascii_case(b"chunked"))
+        })
+    }
+}
+
+/// Query parameter name and value
+pub type QueryPair<'a> = (&'a [u8], &'a [u8]);
+
+/// Splits a request target into its path and the `name=value` pairs of its query, as located by
+/// the locking circuits: the query starts after the first `?`, pairs are separated by `&`, and pairs
+/// without `=` are skipped.
+pub fn split_target(target: &[u8]) -> (&[u8], Option<Vec<QueryPair<'_>>>) {
+    let Some(query_start) = target.iter().position(|byte| *byte == b'?') else {
+        return (target, None);
+    };
+
+    let pairs = target[query_start + 1..]
+        .split(|byte| *byte == b'&')
+        .filter_map(|pair| {
+            let name_end = pair.iter().position(|byte| *byte == b'=')?;
+            Some((&pair[..name_end], &pair[name_end + 1..]))
+        })
+        .collect();
+
+    (&target[..query_start], Some(pairs))
+}
+
+/// Splits `input` into its start line, headers and body by running the parser, failing where the
+/// locking circuits would.
+pub fn parse(input: &[u8]) -> Result<HttpParts<'_>, Box<dyn Error>> {
+    let states = trace(input)?;
+
+    // start line parts are located by counting bytes in each part, like the locking circuits do
+    let count = |part: i64| {
+        states
+            .iter()
+            .filter(|state| state.parsing_start == part)
+            .count()
+    };
+    let (first, second, third) = (count(1), count(2), count(3));
+    // second and third parts start with SP, and the third ends with CR. Only the third part, a
+    // response's reason phrase, may be empty (RFC 9112).
+    if first < 1 || second < 2 || third < 2 || input[first + second + third - 1] != b'\r' {
+        return Err(String::from(
+            "start line should have 3 parts separated by single spaces and end with CRLF",
+        )
+        .into());
+    }
+    let start_line = [
+        &input[..first],
+        &input[first + 1..first + second],
+        &input[first + second + 1..first + second + third - 1],
+    ];
+
+    // a header line starts at the LF incrementing `parsing_header`, and the empty line ending the
+    // headers disables it
+    let mut headers = vec![];
+    let mut line_start = None;
+    for (i, state) in states.iter().enumerate() {
+        let previous = match i {
+            0 => HttpState::default().parsing_header,
+            _ => states[i - 1].parsing_header,
+        };
+        if state.parsing_header == previous {
+            continue;
+        }
+
+        match line_start {
+            Some(start) if state.parsing_header > 0 => headers.push(header_line(input, start, i)?),
+            _ => {}
+        }
+        line_start = (state.parsing_header > 0).then_some(i + 1);
+    }
+    if let Some(start) = line_start {
+        // input ends inside the headers
+        if start < input.len() {
+            headers.push(header_line(input, start, input.len())?);
+        }
+    }
+
+    // body mask starts at the LF of CRLF CRLF
+    let body = match states.iter().position(|state| state.parsing_body == 1) {
+        Some(i) => &input[i + 1..],
+        None => &[],
+    };
+
+    Ok(HttpParts {
+        start_line,
+        headers,
+        body,
+    })
+}
+
+/// Removes the chunked transfer coding framing from `body` like `DechunkBody` in
+/// `circuits/http/chunked.circom`, returning the chunk data. Fails where the circuit's constraints
+/// would.
+pub fn dechunk(body: &[u8]) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut payload = vec![];
+    let mut reading_size = true;
+    let mut size = 0usize;
+    let mut remaining = 0;
+
+    for (i, byte) in body.iter().enumerate() {
+        if remaining > 0 {
+            payload.push(*byte);
+            remaining -= 1;
+            continue;
+        }
+
+        match (reading_size, byte) {
+            // a chunk with size 0 is the last one, and trailer fields are ignored
+            (true, b'\n') if size == 0 => return Ok(payload),
+            (true, b'\n') => {
+                (remaining, size, reading_size) = (size, 0, false);
+            }
+            (_, b'\r') => {}
+            (true, _) => {
+                let digit = (*byte as char).to_digit(16).ok_or_else(|| {
+                    format!(
+                        "byte {} of chunked body should be a chunk size in hex followed by CRLF, chunk extensions aren't supported",
+                        i
+                    )
+                })?;
+                size = size
+                    .checked_mul(16)
+                    .and_then(|size| size.checked_add(digit as usize))
+                    .ok_or_else(|| format!("chunk size at byte {} is too large", i))?;
+            }
+            (false, b'\n') => reading_size = true,
+            (false, _) => {
+                return Err(
+                    format!("byte {} of chunked body should be CRLF after chunk data", i).into(),
+                )
+            }
+        }
+    }
+
+    Err(String::from("chunked body should end with a chunk of size 0").into())
+}
+
+/// Splits the header line `input[start..end]`, ending with CR, into name and value. The header
+/// circuits match the value two bytes after the name's `:`.
+fn header_line(input: &[u8], start: usize, end: usize) -> Result<Header<'_>, Box<dyn Error>> {
+    let line = &input[start..end];
+    let line = line.strip_suffix(b"\r").unwrap_or(line);
+
+    let colon = line.iter().position(|byte| *byte == b':');
+    match colon {
+        Some(colon) if line.get(colon + 1) == Some(&b' ') => {
+            Ok((&line[..colon], &line[colon + 2..]))
+        }
+        _ => Err(format!(
+            "header line `{}` at byte {} should be `name: value`",
+            String::from_utf8_lossy(line),
+            start
+        )
+        .into()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const RESPONSE: &[u8] = b"HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: 19\r\n\r\n{\"success\":\"true\"}";
+
+    #[test]
+    fn trace_response() {
+        let states = trace(RESPONSE).unwrap();
+        let state_after = |pattern: &[u8]| {
+            let i = RESPONSE
+                .windows(pattern.len())
+                .position(|window| window == pattern)
+                .unwrap();
+            states[i + pattern.len() - 1]
+        };
+
+        // SP moves to the next start line part
+        assert_eq!(state_after(b"HTTP/1.1").parsing_start, 1);
+        assert_eq!(state_after(b"HTTP/1.1 ").parsing_start, 2);
+        assert_eq!(state_after(b"200 ").parsing_start, 3);
+
+        // CRLF starts a header, and its name
+        let state = state_after(b"OK\r\n");
+        assert_eq!(
+            state,
+            HttpState {
+                parsing_start: 0,
+                parsing_header: 1,
+                parsing_field_name: 1,
+                parsing_field_value: 0,
+                parsing_body: 0,
+                line_status: 2,
+            }
+        );
+        let state = state_after(b"Content-Type:");
+        assert_eq!(
+            (state.parsing_field_name, state.parsing_field_value),
+            (0, 1)
+        );
+        let state = state_after(b"json\r\n");
+        assert_eq!(
+            (
+                state.parsing_header,
+                state.parsing_field_name,
+                state.parsing_field_value
+            ),
+            (2, 1, 0)
+        );
+
+        // CRLF CRLF starts the body
+        let state = state_after(b"19\r\n\r\n");
+        assert_eq!(
+            state,
+            HttpState {
+                parsing_start: 0,
+                parsing_header: 0,
+                parsing_field_name: 0,
+                parsing_field_value: 0,
+                parsing_body: 1,
+                line_status: 4,
+            }
+        );
+        assert_eq!(states.last().unwrap().line_status, 0);
+    }
+
+    #[test]
+    fn parse_response() {
+        let parts = parse(RESPONSE).unwrap();
+        assert_eq!(parts.start_line, [&b"HTTP/1.1"[..], b"200", b"OK"]);
+        assert_eq!(
+            parts.headers,
+            [
+                (&b"Content-Type"[..], &b"application/json"[..]),
+                (b"Content-Length", b"19"),
+            ]
+        );
+        assert_eq!(parts.body, br#"{"success":"true"}"#);
+
+        // headers may run until the end of the input, and a `:` in a value is kept
+        let parts = parse(b"GET /api HTTP/1.1\r\nHost: localhost:8080").unwrap();
+        assert_eq!(parts.start_line, [&b"GET"[..], b"/api", b"HTTP/1.1"]);
+        assert_eq!(parts.headers, [(&b"Host"[..], &b"localhost:8080"[..])]);
+        assert!(parts.body.is_empty());
+    }
+
+    #[test]
+    fn parse_reason_phrase() {
+        // SP after the status code is part of the reason phrase
+        let parts =
+            parse(b"HTTP/1.1 500 Internal Server Error\r\nContent-Length: 0\r\n\r\n").unwrap();
+        assert_eq!(
+            parts.start_line,
+            [&b"HTTP/1.1"[..], b"500", b"Internal Server Error"]
+        );
+        assert_eq!(parts.headers, [(&b"Content-Length"[..], &b"0"[..])]);
+
+        // empty reason phrase
+        let parts = parse(b"HTTP/1.1 204 \r\n\r\n").unwrap();
+        assert_eq!(parts.start_line, [&b"HTTP/1.1"[..], b"204", b""]);
+        assert!(parts.headers.is_empty());
+
+        let states = trace(b"HTTP/1.1 404 Not Found").unwrap();
+        assert_eq!(states.last().unwrap().parsing_start, 3);
+    }
+
+    #[test]
+    fn split_request_target() {
+        assert_eq!(split_target(b"/api"), (&b"/api"[..], None));
+        assert_eq!(split_target(b"/api?"), (&b"/api"[..], Some(vec![])));
+
+        let (path, pairs) = split_target(b"/v1/accounts?account=123&flag&limit=50&q=a=b?c");
+        assert_eq!(path, b"/v1/accounts");
+        assert_eq!(
+            pairs.unwrap(),
+            [
+                (&b"account"[..], &b"123"[..]),
+                (b"limit", b"50"),
+                (b"q", b"a=b?c")
+            ]
+        );
+    }
+
+    #[test]
+    fn dechunk_body() {
+        let input = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: gzip, chunked\r\n\r\n5\r\nhello\r\nA\r\n, chunked!\r\n0\r\n\r\n";
+        let parts = parse(input).unwrap();
+        assert!(parts.is_chunked());
+        assert_eq!(dechunk(parts.body).unwrap(), b"hello, chunked!");
+
+        // `chunked` should be the last coding
+        let parts = parse(b"HTTP/1.1 200 OK\r\ntransfer-encoding: chunked, gzip\r\n\r\n").unwrap();
+        assert!(!parts.is_chunked());
+
+        // trailer fields and padding after the last chunk are ignored
+        assert_eq!(
+            dechunk(b"1\r\n{\r\n0\r\nExpires: never\r\n\r\n\0\0").unwrap(),
+            b"{"
+        );
+
+        for (body, error) in [
+            (
+                &b"5;ext=1\r\nhello\r\n0\r\n\r\n"[..],
+                "byte 1 of chunked body should be a chunk size in hex followed by CRLF, chunk extensions aren't supported",
+            ),
+            (
+                b"3\r\nhello\r\n0\r\n\r\n",
+                "byte 6 of chunked body should be CRLF after chunk data",
+            ),
+            (b"5\r\nhello\r\n", "chunked body should end with a chunk of size 0"),
+        ] {
+            assert_eq!(dechunk(body).unwrap_err().to_string(), error);
+        }
+    }
+
+    #[test]
+    fn parse_errors() {
+        const START_LINE_ERROR: &str =
+            "start line should have 3 parts separated by single spaces and end with CRLF";
+
+        for (input, error) in [
+            (&b"HTTP/1.1  200 OK\r\n\r\n"[..], START_LINE_ERROR),
+            (b"HTTP/1.1 200\r\n\r\n", START_LINE_ERROR),
+            (b"GET /api HTTP/1.1\n\n", START_LINE_ERROR),
+            (
+                b"HTTP/1.1 200 OK\r\nHost:localhost\r\n\r\n",
+                "header line `Host:localhost` at byte 17 should be `name: value`",
+            ),
+        ] {
+            assert_eq!(parse(input).unwrap_err().to_string(), error);
+        }
+
+        // `parsing_header` is range checked with 3 bits
+        let mut input = b"HTTP/1.1 200 OK\r\n".to_vec();
+        for i in 0..7 {
+            input.extend(format!("Header{}: value\r\n", i).as_bytes());
+        }
+        assert!(parse(&input).is_ok());
+        input.extend(b"Header7: value\r\n\r\n");
+        let err = parse(&input).unwrap_err();
+        assert!(err.to_string().contains("too many header lines"));
+    }
+}
@@ -0,0 +1,829 @@
+//! JSON parser state machine, mirroring `StateUpdate` in `circuits/json/parser/machine.circom`,
+//! and the `circuits/json/interpreter.circom` templates used by the generated extractor circuits.
+
+use ark_bn254::Fr;
+use ark_ff::{BigInteger, Field, PrimeField};
+use std::{collections::HashMap, error::Error};
+
+use crate::codegen::json::{Key, Lockfile, Predicate, ValueType};
+
+/// `Command()` instructions: `[read_write_value, parsing_string, parsing_number]`
+const START_BRACE: [i64; 3] = [1, 0, 0];
+const END_BRACE: [i64; 3] = [-1, 0, -1];
+const START_BRACKET: [i64; 3] = [2, 0, 0];
+const END_BRACKET: [i64; 3] = [-2, 0, -1];
+const QUOTE: [i64; 3] = [0, 1, 0];
+const COLON: [i64; 3] = [3, 0, 0];
+const COMMA: [i64; 3] = [4, 0, -1];
+const NUMBER: [i64; 3] = [256, 0, 1];
+
+/// Parser state after reading a byte, i.e. the `next_stack`, `next_parsing_string` and
+/// `next_parsing_number` outputs of `StateUpdate`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JsonState {
+    /// `[value, index]` pairs: `[1, 0]` inside an object key, `[1, 1]` inside an object value,
+    /// `[2, i]` inside element `i` of an array, and `[0, 0]` if unallocated
+    pub stack: Vec<[i64; 2]>,
+    pub parsing_string: i64,
+    pub parsing_number: i64,
+}
+
+impl JsonState {
+    /// Initial state with `max_stack_height` unallocated stack entries
+    pub fn new(max_stack_height: usize) -> Self {
+        Self {
+            stack: vec![[0, 0]; max_stack_height],
+            parsing_string: 0,
+            parsing_number: 0,
+        }
+    }
+
+    /// `GetTopOfStack`: returns the topmost value and the number of allocated stack entries.
+    pub fn top(&self) -> ([i64; 2], usize) {
+        let pointer = self.stack.iter().filter(|value| **value != [0, 0]).count();
+        match pointer {
+            0 => ([0, 0], 0),
+            pointer => (self.stack[pointer - 1], pointer),
+        }
+    }
+
+    /// `StateUpdate`: returns the state after reading `byte`, or an error where the circuit's
+    /// constraints fail.
+    pub fn update(&self, byte: u8) -> Result<Self, String> {
+        let read = |c: u8| i64::from(byte == c);
+        let read_start_brace = read(b'{');
+        let read_end_brace = read(b'}');
+        let read_start_bracket = read(b'[');
+        let read_end_bracket = read(b']');
+        let read_colon = read(b':');
+        let read_comma = read(b',');
+        let read_delimiter = read_start_brace
+            + read_end_brace
+            + read_start_bracket
+            + read_end_bracket
+            + read_colon
+            + read_comma;
+        let read_number = i64::from(byte.is_ascii_digit());
+        let read_quote = read(b'"');
+        let read_other = i64::from(read_delimiter + read_number + read_quote == 0);
+
+        let mut instruction = [0; 3];
+        for (flag, command) in [
+            (read_start_brace, START_BRACE),
+            (read_end_brace, END_BRACE),
+            (read_start_bracket, START_BRACKET),
+            (read_end_bracket, END_BRACKET),
+            (read_colon, COLON),
+            (read_comma, COMMA),
+            (read_number, NUMBER),
+            (read_quote, QUOTE),
+        ] {
+            for (value, command) in instruction.iter_mut().zip(command) {
+                *value += flag * command;
+            }
+        }
+
+        // `StateToMask`
+        let (parsing_string, parsing_number) = (self.parsing_string, self.parsing_number);
+        let mask = [
+            1 - parsing_string,
+            1 - 2 * parsing_string,
+            (1 - parsing_string)
+                * (parsing_number * read_delimiter + (1 - parsing_number) * read_number)
+                + parsing_number * (1 - read_number) * (1 - read_delimiter),
+        ];
+        let read_write_value = mask[0] * instruction[0];
+
+        // `RewriteStack`, note that delimiters inside strings still move the stack pointer
+        let (current_value, pointer) = self.top();
+        let pointer = pointer as i64;
+        let in_array = i64::from(current_value[0] == 2);
+        let is_push = i64::from(read_start_brace + read_start_bracket == 1);
+        let is_pop = i64::from(read_end_brace + read_end_bracket == 1);
+
+        let height = pointer - is_pop + is_push;
+        if height < 0 {
+            return Err(String::from("stack underflow"));
+        }
+        if height > self.stack.len() as i64 {
+            return Err(format!(
+                "stack overflow, MAX_STACK_HEIGHT is {}",
+                self.stack.len()
+            ));
+        }
+
+        // `pointer` points to the first unallocated entry
+        let target = pointer - is_pop - read_colon - read_comma;
+        let change = [
+            (is_push + is_pop) * read_write_value,
+            read_colon + read_comma * in_array - read_comma * (1 - in_array),
+        ];
+        let stack = self
+            .stack
+            .iter()
+            .enumerate()
+            .map(|(i, value)| {
+                let indicator = i64::from(i as i64 == target);
+                let second_index_clear = value[1] * (read_end_brace + read_end_bracket);
+                [
+                    value[0] + indicator * change[0],
+                    value[1] + indicator * (change[1] - second_index_clear),
+                ]
+            })
+            .collect();
+
+        Ok(Self {
+            stack,
+            parsing_string: parsing_string + mask[1] * instruction[1],
+            parsing_number: parsing_number + mask[2] * (instruction[2] - read_other),
+        })
+    }
+
+    /// `InsideKeyAtTop`
+    pub fn inside_key(&self) -> i64 {
+        let (value, _) = self.top();
+        value[0] * (1 - value[1]) * self.parsing_string * (1 - self.parsing_number)
+    }
+
+    /// `NextKVPairAtDepth`
+    pub fn next_kv_pair_at_depth(&self, byte: u8, depth: usize) -> i64 {
+        let (value, pointer) = self.top();
+        i64::from(value == [1, 0] && byte == b',' && pointer as i64 - 1 <= depth as i64)
+    }
+}
+
+/// `InsideValue`
+fn inside_value(stack: [i64; 2], parsing_string: i64, parsing_number: i64) -> i64 {
+    stack[0] * stack[1] * xor(parsing_string, parsing_number)
+}
+
+/// `InsideArrayIndex`
+fn inside_array_index(
+    stack: [i64; 2],
+    index: usize,
+    parsing_string: i64,
+    parsing_number: i64,
+) -> i64 {
+    i64::from(stack[0] == 2 && stack[1] == index as i64) * (parsing_string + parsing_number)
+}
+
+/// `KeyMatchAtDepth` for the key starting at `index`
+fn key_match_at_depth(
+    input: &[u8],
+    key: &[u8],
+    index: usize,
+    parsing_key: i64,
+    state: &JsonState,
+    depth: usize,
+) -> i64 {
+    let (_, pointer) = state.top();
+    // `IndexSelector` outputs 0 outside of `input`
+    let byte_at = |i: Option<usize>| i.and_then(|i| input.get(i)).copied().unwrap_or(0);
+    let is_key_between_quotes =
+        byte_at(index.checked_sub(1)) == b'"' && byte_at(index.checked_add(key.len())) == b'"';
+    let substring_match = input.get(index..index + key.len()) == Some(key);
+
+    i64::from(substring_match && is_key_between_quotes && pointer as i64 - 1 == depth as i64)
+        * parsing_key
+}
+
+/// `KeyMatchAtDepthPadded` for the key starting at `index`: `key` is zero padded to `maxKeyLen`,
+/// and its first `key_len` bytes are followed by a quote in `input`
+fn padded_key_match_at_depth(
+    input: &[u8],
+    key: &[u8],
+    key_len: usize,
+    index: usize,
+    parsing_key: i64,
+    state: &JsonState,
+    depth: usize,
+) -> i64 {
+    let (_, pointer) = state.top();
+    // bytes past the end of `input` are zero
+    let byte_at = |i: usize| input.get(i).copied().unwrap_or(0);
+    let is_start_of_key_equal_to_quote = index > 0 && byte_at(index - 1) == b'"';
+
+    // `MatchPaddedKey`: padding matches any byte, and the byte at `keyLen` is a quote
+    let padded_key = key.iter().chain([&0]);
+    let substring_match = padded_key
+        .enumerate()
+        .all(|(i, byte)| *byte == 0 || *byte == byte_at(index + i))
+        && key_len <= key.len()
+        && byte_at(index + key_len) == b'"';
+
+    i64::from(
+        substring_match && is_start_of_key_equal_to_quote && pointer as i64 - 1 == depth as i64,
+    ) * parsing_key
+}
+
+/// `AssertPaddedKey`: `key` is `key_len` nonzero bytes followed by zeros
+pub fn assert_padded_key(key: &[u8], key_len: usize) -> Result<(), Box<dyn Error>> {
+    let is_padded = key_len <= key.len()
+        && key[..key_len].iter().all(|byte| *byte != 0)
+        && key[key_len..].iter().all(|byte| *byte == 0);
+    match is_padded {
+        true => Ok(()),
+        false => Err(format!(
+            "key {:?} isn't {} bytes followed by zero padding",
+            key, key_len
+        )
+        .into()),
+    }
+}
+
+fn xor(a: i64, b: i64) -> i64 {
+    a + b - 2 * a * b
+}
+
+/// Runs the parser over `input`. Returns the state after each byte, i.e. `State[i].next_*` in the
+/// circuit, or the byte where the circuit's constraints fail.
+pub fn trace(input: &[u8], max_stack_height: usize) -> Result<Vec<JsonState>, Box<dyn Error>> {
+    let mut state = JsonState::new(max_stack_height);
+    let mut states = Vec::with_capacity(input.len());
+    for (i, byte) in input.iter().enumerate() {
+        state = state
+            .update(*byte)
+            .map_err(|err| format!("JSON parser fails at byte {}: {}", i, err))?;
+        states.push(state.clone());
+    }
+
+    Ok(states)
+}
+
+/// `mask` signals of `ExtractValue` for the value at the keys of `lockfile`, given the parser
+/// `states` of `input`. Bytes of the value are nonzero.
+pub fn value_mask(
+    input: &[u8],
+    states: &[JsonState],
+    lockfile: &Lockfile,
+) -> Result<Vec<i64>, Box<dyn Error>> {
+    let key_inputs: Vec<(&[u8], Option<usize>)> = lockfile
+        .keys
+        .iter()
+        .map(|key| match key {
+            Key::String(key) => (key.as_bytes(), None),
+            Key::Num(_) => (&[][..], None),
+        })
+        .collect();
+    mask(input, states, lockfile, &key_inputs)
+}
+
+/// `mask` of `ExtractValue` with zero padded keys, given the `key{i}` and `keyLen{i}` inputs of the
+/// witness, or an error if `AssertPaddedKey` fails for a key
+pub fn padded_value_mask(
+    input: &[u8],
+    states: &[JsonState],
+    lockfile: &Lockfile,
+    keys: &HashMap<String, Vec<u8>>,
+    key_lens: &HashMap<String, usize>,
+) -> Result<Vec<i64>, Box<dyn Error>> {
+    let mut key_inputs: Vec<(&[u8], Option<usize>)> = vec![];
+    for (i, key) in lockfile.keys.iter().enumerate() {
+        match key {
+            Key::String(_) => {
+                let key = keys
+                    .get(&format!("key{}", i + 1))
+                    .ok_or_else(|| format!("missing input `key{}`", i + 1))?;
+                let key_len = *key_lens
+                    .get(&format!("keyLen{}", i + 1))
+                    .ok_or_else(|| format!("missing input `keyLen{}`", i + 1))?;
+                assert_padded_key(key, key_len)?;
+                key_inputs.push((key, Some(key_len)));
+            }
+            Key::Num(_) => key_inputs.push((&[], None)),
+        }
+    }
+    mask(input, states, lockfile, &key_inputs)
+}
+
+/// `mask` of `ExtractValue`, matching each string key with its `key_inputs` bytes, padded if they
+/// come with a `keyLen`
+fn mask(
+    input: &[u8],
+    states: &[JsonState],
+    lockfile: &Lockfile,
+    key_inputs: &[(&[u8], Option<usize>)],
+) -> Result<Vec<i64>, Box<dyn Error>> {
+    let keys = &lockfile.keys;
+    let max_stack_height = states.first().map_or(0, |state| state.stack.len());
+    // raw values also read their own stack entry
+    let stack_entries = match lockfile.value_type {
+        ValueType::Raw => keys.len() + 1,
+        _ => keys.len(),
+    };
+    if stack_entries > max_stack_height {
+        return Err(format!(
+            "keys are nested deeper than MAX_STACK_HEIGHT {}",
+            max_stack_height
+        )
+        .into());
+    }
+
+    let mut is_key_match_for_value = vec![0; keys.len()];
+    let mut mask = Vec::with_capacity(states.len());
+    for (idx, (state, byte)) in states.iter().zip(input).enumerate() {
+        // flags replacing `parsing_string` and `parsing_number`, see `ValueExtractor::parsing_flags`
+        let (parsing_string, parsing_number) = match lockfile.value_type {
+            ValueType::Boolean | ValueType::Null => (
+                i64::from(byte.is_ascii_lowercase()) * (1 - state.parsing_string),
+                0,
+            ),
+            ValueType::Raw => {
+                let is_unallocated = |state: &JsonState| i64::from(state.stack[keys.len()][0] == 0);
+                let parsing_raw = match idx {
+                    0 => 1 - is_unallocated(state),
+                    _ => 1 - is_unallocated(&states[idx - 1]) * is_unallocated(state),
+                };
+                (parsing_raw, 0)
+            }
+            ValueType::String | ValueType::Number => (state.parsing_string, state.parsing_number),
+        };
+
+        let mut parsing_value = 1;
+        for (depth, key) in keys.iter().enumerate() {
+            // first byte is checked against the bottom of the stack for every key
+            let stack = match idx {
+                0 => state.stack[0],
+                _ => state.stack[depth],
+            };
+            parsing_value *= match key {
+                Key::String(_) => inside_value(stack, parsing_string, parsing_number),
+                Key::Num(index) => {
+                    inside_array_index(stack, *index, parsing_string, parsing_number)
+                }
+            };
+        }
+
+        let parsing_key = state.inside_key();
+        let mut is_value_match = 1;
+        for (depth, key) in keys.iter().enumerate() {
+            let Key::String(_) = key else {
+                continue;
+            };
+            let is_key_match = match key_inputs[depth] {
+                (key, None) => key_match_at_depth(input, key, idx, parsing_key, state, depth),
+                (key, Some(key_len)) => {
+                    padded_key_match_at_depth(input, key, key_len, idx, parsing_key, state, depth)
+                }
+            };
+            let not_next_pair = 1 - state.next_kv_pair_at_depth(*byte, depth);
+            let previous = is_key_match_for_value[depth] * not_next_pair;
+            // `Mux1` selected by `is_key_match`
+            is_key_match_for_value[depth] =
+                (is_key_match * not_next_pair - previous) * is_key_match + previous;
+            is_value_match *= is_key_match_for_value[depth];
+        }
+
+        mask.push(parsing_value * is_value_match);
+    }
+
+    Ok(mask)
+}
+
+/// `value_starting_index` output of `ExtractValue`: the first masked byte, ignoring byte 0, or 0
+/// if no byte is masked.
+pub fn value_starting_index(mask: &[i64]) -> usize {
+    mask.iter()
+        .enumerate()
+        .skip(1)
+        .find(|(_, mask)| **mask != 0)
+        .map_or(0, |(i, _)| i)
+}
+
+/// Predicts `value_starting_index` of the extractor circuit for `lockfile`, failing if the circuit
+/// wouldn't find a value at its keys.
+pub fn predict_value_starting_index(
+    input: &[u8],
+    states: &[JsonState],
+    lockfile: &Lockfile,
+) -> Result<usize, Box<dyn Error>> {
+    match value_starting_index(&value_mask(input, states, lockfile)?) {
+        0 => Err(format!(
+            "extractor circuit doesn't find a value at keys {:?}",
+            lockfile.keys
+        )
+        .into()),
+        index => Ok(index),
+    }
+}
+
+/// `value_length` output of `ExtractValue`: the masked bytes of a raw value, or of a string without
+/// its opening quote, and the span from the first to the last masked digit of a number, which
+/// includes its decimal point. Literals don't output a length.
+pub fn value_length(mask: &[i64], value_type: &ValueType) -> Option<usize> {
+    let masked = mask.iter().filter(|mask| **mask != 0).count();
+    match value_type {
+        ValueType::Raw => Some(masked),
+        ValueType::String => Some(masked.saturating_sub(1)),
+        ValueType::Number => {
+            let end = mask
+                .iter()
+                .enumerate()
+                .skip(1)
+                .rfind(|(_, mask)| **mask != 0)
+                .map_or(0, |(i, _)| i);
+            Some(end + 1 - value_starting_index(mask))
+        }
+        ValueType::Boolean | ValueType::Null => None,
+    }
+}
+
+/// `SelectSubArray`: `length` bytes of `data` from `start`, followed by zeros up to `max_len`.
+/// `VarShiftLeft` wraps around the end of `data`.
+pub fn select_sub_array(data: &[u8], start: usize, length: usize, max_len: usize) -> Vec<u8> {
+    (0..max_len)
+        .map(|i| match i < length {
+            true => data[(start + i) % data.len()],
+            false => 0,
+        })
+        .collect()
+}
+
+/// `value` and `value_sign` of the number extractor for the number at `value_starting_index`,
+/// `value_length` bytes long: its digits, without the decimal point, scaled by `10^scale`, and
+/// whether it has a `-` sign. Accumulated as a field element, like the circuit, or fails where the
+/// circuit's constraints would.
+pub fn extract_number(
+    input: &[u8],
+    value_starting_index: usize,
+    value_length: usize,
+    max_value_len: usize,
+    scale: usize,
+) -> Result<(Fr, bool), Box<dyn Error>> {
+    if value_length > max_value_len {
+        return Err(format!(
+            "number length {} is above maxValueLen {}",
+            value_length, max_value_len
+        )
+        .into());
+    }
+
+    // the byte before the number is selected to read the `-` sign
+    let value_string = select_sub_array(
+        input,
+        value_starting_index.wrapping_sub(1),
+        max_value_len + 1,
+        max_value_len + 1,
+    );
+    let digits = &value_string[1..=value_length];
+    if !digits.first().is_some_and(u8::is_ascii_digit) {
+        return Err(String::from("number doesn't start with a digit").into());
+    }
+
+    let mut number = Fr::from(0u8);
+    let mut fraction_digits = None;
+    for byte in digits {
+        match (byte, fraction_digits) {
+            (b'0'..=b'9', _) => {
+                number = number * Fr::from(10u8) + Fr::from(byte - b'0');
+                fraction_digits = fraction_digits.map(|digits| digits + 1);
+            }
+            (b'.', None) => fraction_digits = Some(0),
+            (b'.', Some(_)) => return Err(String::from("number has two decimal points").into()),
+            _ => {
+                return Err(format!("number has `{}`, not a digit", char::from(*byte)).into());
+            }
+        }
+    }
+
+    let fraction_digits = fraction_digits.unwrap_or(0);
+    if fraction_digits > scale {
+        return Err(format!(
+            "number has {} fraction digits, more than scale {}",
+            fraction_digits, scale
+        )
+        .into());
+    }
+    for _ in fraction_digits..scale {
+        number *= Fr::from(10u8);
+    }
+
+    Ok((number, value_string[0] == b'-'))
+}
+
+/// Output of the boolean extractor for `value_string` selected at the value, `maxValueLen` bytes
+/// long, or fails where the circuit's constraints would
+pub fn extract_bool(value_string: &[u8]) -> Result<bool, Box<dyn Error>> {
+    let value = value_string[0] == b't';
+    let literal: &[u8] = match value {
+        true => b"true",
+        false => b"false",
+    };
+    if value_string[..4] != literal[..4] || (!value && value_string[4] != b'e') {
+        return Err(String::from("value isn't `true` or `false`").into());
+    }
+
+    Ok(value)
+}
+
+/// Checks of the null extractor on `value_string` selected at the value, `maxValueLen` bytes long
+pub fn extract_null(value_string: &[u8]) -> Result<(), Box<dyn Error>> {
+    if value_string[..4] != *b"null" {
+        return Err(String::from("value isn't `null`").into());
+    }
+    if value_string.get(4).is_some_and(u8::is_ascii_lowercase) {
+        return Err(String::from("`null` is followed by a literal byte").into());
+    }
+
+    Ok(())
+}
+
+/// `LessThan(252)`, failing like its `Num2Bits(253)` if the inputs are too far apart
+fn less_than(a: Fr, b: Fr) -> Result<bool, Box<dyn Error>> {
+    let difference = (a + Fr::from(2u8).pow([252]) - b).into_bigint();
+    if difference.num_bits() > 253 {
+        return Err(String::from("comparator inputs are out of range").into());
+    }
+    Ok(!difference.get_bit(252))
+}
+
+/// Result of `predicate` on the number extractor's `value` and `value_sign`. The signed value and
+/// `bounds` are shifted by 2^251 before comparing, like the circuit, so negative bounds are given as
+/// `p - |bound|`, like their inputs.
+pub fn predicate(
+    predicate: Predicate,
+    value: Fr,
+    sign: bool,
+    bounds: &[Fr],
+) -> Result<bool, Box<dyn Error>> {
+    if bounds.len() != predicate.bounds().len() {
+        return Err(format!(
+            "predicate `{:?}` expects {} bounds, got {}",
+            predicate,
+            predicate.bounds().len(),
+            bounds.len()
+        )
+        .into());
+    }
+
+    let shift = Fr::from(2u8).pow([251]);
+    let one = Fr::from(1u8);
+    let value = match sign {
+        true => -value,
+        false => value,
+    } + shift;
+    let bound = |i: usize| bounds[i] + shift;
+    match predicate {
+        // `GreaterThan`, `GreaterEqThan` and `LessEqThan` are built on `LessThan`
+        Predicate::Gt => less_than(bound(0), value),
+        Predicate::Gte => less_than(bound(0), value + one),
+        Predicate::Lt => less_than(value, bound(0)),
+        Predicate::Lte => less_than(value, bound(0) + one),
+        Predicate::Eq => Ok(value == bound(0)),
+        Predicate::InRange => {
+            Ok(less_than(bound(0), value + one)? && less_than(value, bound(1) + one)?)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::codegen::json::json_max_stack_height;
+    use std::str::FromStr;
+
+    fn state(stack: [[i64; 2]; 4], parsing_string: i64, parsing_number: i64) -> JsonState {
+        JsonState {
+            stack: stack.to_vec(),
+            parsing_string,
+            parsing_number,
+        }
+    }
+
+    #[test]
+    fn state_update() {
+        // cases from `circuits/test/json/parser/stack.test.ts` and `parsing_types.test.ts`
+        let empty = [[0, 0]; 4];
+        let in_object = [[1, 0], [0, 0], [0, 0], [0, 0]];
+        let in_value = [[1, 1], [0, 0], [0, 0], [0, 0]];
+        for (before, byte, after) in [
+            (state(empty, 0, 0), 0, state(empty, 0, 0)),
+            (state(empty, 0, 0), b'{', state(in_object, 0, 0)),
+            (
+                state(in_object, 0, 0),
+                b'{',
+                state([[1, 0], [1, 0], [0, 0], [0, 0]], 0, 0),
+            ),
+            (state(in_object, 0, 0), b'}', state(empty, 0, 0)),
+            (
+                state(in_object, 0, 0),
+                b'[',
+                state([[1, 0], [2, 0], [0, 0], [0, 0]], 0, 0),
+            ),
+            (
+                state([[1, 0], [2, 0], [0, 0], [0, 0]], 0, 0),
+                b']',
+                state(in_object, 0, 0),
+            ),
+            (state(in_object, 0, 0), b':', state(in_value, 0, 0)),
+            (state(in_value, 0, 0), b',', state(in_object, 0, 0)),
+            (state(in_value, 0, 0), b'}', state(empty, 0, 0)),
+            (state(in_value, 0, 1), b',', state(in_object, 0, 0)),
+            (state(in_object, 0, 0), b'"', state(in_object, 1, 0)),
+            (state(in_object, 1, 0), b' ', state(in_object, 1, 0)),
+            (state(in_object, 1, 0), b'"', state(in_object, 0, 0)),
+            (state(in_value, 0, 0), b'1', state(in_value, 0, 1)),
+            (state(in_value, 0, 1), b'2', state(in_value, 0, 1)),
+            (
+                state([[1, 1], [2, 0], [0, 0], [0, 0]], 0, 0),
+                b',',
+                state([[1, 1], [2, 1], [0, 0], [0, 0]], 0, 0),
+            ),
+        ] {
+            assert_eq!(before.update(byte).unwrap(), after, "read `{}`", byte);
+        }
+
+        // stack underflow and overflow
+        assert!(state(empty, 0, 0).update(b'}').is_err());
+        assert!(state([[1, 0]; 4], 0, 0).update(b'{').is_err());
+        // pointer moves inside strings too
+        assert!(state([[1, 0]; 4], 1, 0).update(b'{').is_err());
+        assert_eq!(
+            state(in_value, 1, 0).update(b'}').unwrap(),
+            state(in_object, 1, 0)
+        );
+    }
+
+    #[test]
+    fn trace_max_stack_height() {
+        for input in [
+            &include_bytes!("../../examples/json/test/example.json")[..],
+            include_bytes!("../../examples/json/test/spotify.json"),
+            include_bytes!("../../examples/json/test/string_escape.json"),
+            include_bytes!("../../examples/json/test/value_array_object_array.json"),
+            include_bytes!("../../examples/json/test/value_object.json"),
+            br#"{"a": "[{"}"#,
+        ] {
+            let max_stack_height = json_max_stack_height(input).unwrap();
+            let states = trace(input, max_stack_height).unwrap();
+            assert_eq!(states.len(), input.len());
+            assert_eq!(states.last().unwrap(), &JsonState::new(max_stack_height));
+
+            let err = trace(input, max_stack_height - 1).unwrap_err();
+            assert!(err.to_string().contains("stack overflow"));
+        }
+    }
+
+    #[test]
+    fn value_starting_indices() {
+        for (input, lockfile) in [
+            (
+                &include_bytes!("../../examples/json/test/spotify.json")[..],
+                &include_bytes!("../../examples/json/lockfile/spotify.json")[..],
+            ),
+            (
+                include_bytes!("../../examples/json/test/spotify.json"),
+                include_bytes!("../../examples/json/lockfile/key_path.json"),
+            ),
+            (
+                include_bytes!("../../examples/json/test/two_keys.json"),
+                include_bytes!("../../examples/json/lockfile/two_keys.json"),
+            ),
+            (
+                include_bytes!("../../examples/json/test/value_array.json"),
+                include_bytes!("../../examples/json/lockfile/value_array_number.json"),
+            ),
+            (
+                include_bytes!("../../examples/json/test/value_array.json"),
+                include_bytes!("../../examples/json/lockfile/value_array_string.json"),
+            ),
+            (
+                include_bytes!("../../examples/json/test/value_array_nested.json"),
+                include_bytes!("../../examples/json/lockfile/value_array_nested.json"),
+            ),
+            (
+                include_bytes!("../../examples/json/test/value_array_object.json"),
+                include_bytes!("../../examples/json/lockfile/value_array_object.json"),
+            ),
+            (
+                include_bytes!("../../examples/json/test/value_literal.json"),
+                include_bytes!("../../examples/json/lockfile/value_bool.json"),
+            ),
+            (
+                include_bytes!("../../examples/json/test/value_literal.json"),
+                include_bytes!("../../examples/json/lockfile/value_null.json"),
+            ),
+            (
+                include_bytes!("../../examples/json/test/value_number.json"),
+                include_bytes!("../../examples/json/lockfile/value_number.json"),
+            ),
+            (
+                include_bytes!("../../examples/json/test/value_number_signed.json"),
+                include_bytes!("../../examples/json/lockfile/value_number_predicate.json"),
+            ),
+            (
+                include_bytes!("../../examples/json/test/value_object.json"),
+                include_bytes!("../../examples/json/lockfile/value_object.json"),
+            ),
+            (
+                include_bytes!("../../examples/json/test/value_object.json"),
+                include_bytes!("../../examples/json/lockfile/value_raw.json"),
+            ),
+            (
+                include_bytes!("../../examples/json/test/value_string.json"),
+                include_bytes!("../../examples/json/lockfile/value_string.json"),
+            ),
+        ] {
+            let lockfile: Lockfile = serde_json::from_slice(lockfile).unwrap();
+            let states = trace(input, json_max_stack_height(input).unwrap()).unwrap();
+
+            // strings start at the opening quote, and numbers at the first digit
+            let raw_value = lockfile.get_raw_value(input).unwrap().get();
+            let offset = raw_value.as_ptr() as usize - input.as_ptr() as usize;
+            let expected = offset + usize::from(raw_value.starts_with('-'));
+
+            assert_eq!(
+                predict_value_starting_index(input, &states, &lockfile).unwrap(),
+                expected,
+                "keys {:?}",
+                lockfile.keys
+            );
+        }
+    }
+
+    #[test]
+    fn value_mask_not_found() {
+        let input = include_bytes!("../../examples/json/test/value_string.json");
+        let states = trace(input, 1).unwrap();
+
+        let lockfile: Lockfile =
+            serde_json::from_str(r#"{"keys": ["v"], "value_type": "string"}"#).unwrap();
+        assert!(value_mask(input, &states, &lockfile)
+            .unwrap()
+            .iter()
+            .all(|mask| *mask == 0));
+        assert!(predict_value_starting_index(input, &states, &lockfile).is_err());
+
+        // raw value's own stack entry doesn't fit
+        let lockfile: Lockfile =
+            serde_json::from_str(r#"{"keys": ["k"], "value_type": "raw"}"#).unwrap();
+        assert!(value_mask(input, &states, &lockfile).is_err());
+    }
+
+    #[test]
+    fn number_digits() {
+        let input = include_bytes!("../../examples/json/test/value_number_signed.json");
+        let states = trace(input, json_max_stack_height(input).unwrap()).unwrap();
+        let number = |keys: &str, scale: usize| {
+            let lockfile: Lockfile = serde_json::from_str(&format!(
+                r#"{{"keys": {}, "value_type": "number", "scale": {}}}"#,
+                keys, scale
+            ))
+            .unwrap();
+            let start = predict_value_starting_index(input, &states, &lockfile).unwrap();
+            // every byte of the number is accumulated, up to `maxValueLen`
+            let max_value_len = *lockfile.populate_params(input).unwrap().last().unwrap();
+            let (value, sign) =
+                extract_number(input, start, max_value_len, max_value_len, scale).unwrap();
+
+            let expected = lockfile.get_value(input).unwrap();
+            assert_eq!(
+                (value, sign),
+                (
+                    Fr::from_str(expected.trim_start_matches('-')).unwrap(),
+                    expected.starts_with('-')
+                ),
+                "keys {}",
+                keys
+            );
+            (start, max_value_len)
+        };
+
+        // `3.14` with scale 2 is 314, skipping the decimal point
+        let (start, max_value_len) = number(r#"["b"]"#, 2);
+        assert_eq!(
+            extract_number(input, start, max_value_len, max_value_len, 2).unwrap(),
+            (Fr::from(314u16), false)
+        );
+        // scale pads fraction digits with zeros, and fewer fraction digits than in the number fail
+        assert_eq!(
+            extract_number(input, start, max_value_len, max_value_len, 3)
+                .unwrap()
+                .0,
+            Fr::from(3140u16)
+        );
+        assert!(extract_number(input, start, max_value_len, max_value_len, 1).is_err());
+        // stopping before the last digit drops it
+        assert_eq!(
+            extract_number(input, start, max_value_len - 1, max_value_len, 2)
+                .unwrap()
+                .0,
+            Fr::from(310u16)
+        );
+
+        // `-0.5` with scale 1 is -5
+        let (start, max_value_len) = number(r#"["c", 0]"#, 1);
+        assert_eq!(
+            extract_number(input, start, max_value_len, max_value_len, 1).unwrap(),
+            (Fr::from(5u8), true)
+        );
+        number(r#"["a"]"#, 0);
+
+        // bytes other than digits and one decimal point fail
+        assert!(extract_number(b" 1e5 ", 1, 3, 3, 0).is_err());
+        assert!(extract_number(b" 1.2.3 ", 1, 5, 5, 2).is_err());
+        assert!(extract_number(b" .5 ", 1, 2, 2, 1).is_err());
+        assert!(extract_number(b" 12 ", 1, 0, 2, 0).is_err());
+        assert!(extract_number(b" 123 ", 1, 3, 2, 0).is_err());
+    }
+}
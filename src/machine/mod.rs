@@ -0,0 +1,9 @@
+//! Reference implementations of the parser circuits, computing the same per-byte state as the
+//! circuits do, so that inputs can be checked without compiling and running circom.
+//!
+//! Signals are tracked as `i64` instead of field elements. For the inputs the circuits accept,
+//! every signal is a small integer, and negative values stand for `p - x` in the field.
+
+pub mod http;
+pub mod json;
+pub mod nivc;
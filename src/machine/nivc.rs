@@ -0,0 +1,401 @@
+//! NIVC step circuits of `circuits/http/nivc` and `circuits/json/nivc`, computing the `step_out` of
+//! each step from its `step_in`.
+//!
+//! `step_in` and `step_out` have `TOTAL_BYTES_ACROSS_NIVC` signals: the data bytes, followed by the
+//! parser state of each byte and, in the last signal, the depth the JSON steps masked so far. HTTP
+//! steps store 5 parser flags per byte, JSON steps the stack, `parsing_string` and `parsing_number`.
+
+use std::error::Error;
+
+use super::{http, json};
+use crate::codegen::{
+    integrated::ExtendedLockfile,
+    json::Key,
+    nivc::{NivcParams, NivcStep},
+};
+
+/// `parsing_start`, `parsing_header`, `parsing_field_name`, `parsing_field_value` and
+/// `parsing_body` are stored for each byte by the HTTP steps
+const HTTP_STATE_LEN: usize = 5;
+
+/// `TOTAL_BYTES_ACROSS_NIVC` of the step circuits
+pub fn total_bytes(params: &NivcParams) -> usize {
+    params.data_bytes * (params.max_stack_height * 2 + 2 + 1) + 1
+}
+
+/// `step_in` of the first step: `data` followed by zeros
+pub fn initial_step_in(data: &[u8], params: &NivcParams) -> Vec<i64> {
+    let mut step_in: Vec<i64> = data.iter().map(|byte| i64::from(*byte)).collect();
+    step_in.resize(total_bytes(params), 0);
+    step_in
+}
+
+/// Data bytes at the start of `step_in`
+fn data(step_in: &[i64], params: &NivcParams) -> Result<Vec<u8>, Box<dyn Error>> {
+    step_in[..params.data_bytes]
+        .iter()
+        .enumerate()
+        .map(|(i, byte)| {
+            u8::try_from(*byte)
+                .map_err(|_| format!("`step_in[{}]` is {}, not a byte", i, byte).into())
+        })
+        .collect()
+}
+
+/// `ParseAndLockStartLine`
+fn parse_and_lock_start_line(
+    step_in: &[i64],
+    params: &NivcParams,
+    start_line: [&str; 3],
+) -> Result<Vec<i64>, Box<dyn Error>> {
+    let data = data(step_in, params)?;
+    let locked = start_line.join(" ");
+    if !data.starts_with(format!("{}\r\n", locked).as_bytes()) {
+        return Err(format!("start line isn't `{}`", locked).into());
+    }
+
+    let db = params.data_bytes;
+    let mut step_out = vec![0; total_bytes(params)];
+    step_out[..db].copy_from_slice(&step_in[..db]);
+    for (i, state) in http::trace(&data)?.iter().enumerate() {
+        let offset = db + i * HTTP_STATE_LEN;
+        step_out[offset..offset + HTTP_STATE_LEN].copy_from_slice(&[
+            state.parsing_start,
+            state.parsing_header,
+            state.parsing_field_name,
+            state.parsing_field_value,
+            state.parsing_body,
+        ]);
+    }
+
+    Ok(step_out)
+}
+
+/// `LockHeader`
+fn lock_header(
+    step_in: &[i64],
+    params: &NivcParams,
+    name: &str,
+    value: &str,
+) -> Result<Vec<i64>, Box<dyn Error>> {
+    let data = data(step_in, params)?;
+    let db = params.data_bytes;
+
+    // `FirstStringMatch` locates the first occurrence of the name anywhere in `data`
+    let location = data
+        .windows(name.len())
+        .position(|window| window == name.as_bytes())
+        .ok_or_else(|| format!("header `{}` isn't in the data", name))?;
+    let value_start = location + name.len() + 2;
+    if data.get(location + name.len()) != Some(&b':')
+        || data.get(value_start..value_start + value.len()) != Some(value.as_bytes())
+    {
+        return Err(format!("header `{}` doesn't have value `{}`", name, value).into());
+    }
+    if step_in[db + location * HTTP_STATE_LEN + 1] <= 0 {
+        return Err(format!("`{}` isn't in a header line", name).into());
+    }
+
+    let http_state_bytes = db * (HTTP_STATE_LEN + 1);
+    let mut step_out = vec![0; total_bytes(params)];
+    step_out[..http_state_bytes].copy_from_slice(&step_in[..http_state_bytes]);
+
+    Ok(step_out)
+}
+
+/// `HTTPMaskBodyNIVC`
+fn mask_body(step_in: &[i64], params: &NivcParams) -> Vec<i64> {
+    let db = params.data_bytes;
+    let mut step_out = vec![0; total_bytes(params)];
+    for (i, byte) in step_out[..db].iter_mut().enumerate() {
+        *byte = step_in[i] * step_in[db + i * HTTP_STATE_LEN + 4];
+    }
+    step_out
+}
+
+/// `JsonParseNIVC`
+fn json_parse(step_in: &[i64], params: &NivcParams) -> Result<Vec<i64>, Box<dyn Error>> {
+    let data = data(step_in, params)?;
+    let db = params.data_bytes;
+
+    let mut step_out = Vec::with_capacity(total_bytes(params));
+    step_out.extend_from_slice(&step_in[..db]);
+    for state in json::trace(&data, params.max_stack_height)? {
+        step_out.extend(state.stack.iter().flatten());
+        step_out.push(state.parsing_string);
+        step_out.push(state.parsing_number);
+    }
+    // depth masked so far
+    step_out.push(0);
+
+    Ok(step_out)
+}
+
+/// Parser states stored in `step_in` by `JsonParseNIVC`
+fn json_states(step_in: &[i64], params: &NivcParams) -> Vec<json::JsonState> {
+    let stack_len = params.max_stack_height * 2;
+    step_in[params.data_bytes..total_bytes(params) - 1]
+        .chunks(stack_len + 2)
+        .map(|state| json::JsonState {
+            stack: state[..stack_len]
+                .chunks(2)
+                .map(|value| [value[0], value[1]])
+                .collect(),
+            parsing_string: state[stack_len],
+            parsing_number: state[stack_len + 1],
+        })
+        .collect()
+}
+
+/// Depth masked so far, which must leave a stack entry above it
+fn masked_depth(step_in: &[i64], params: &NivcParams) -> Result<usize, Box<dyn Error>> {
+    let depth = step_in[total_bytes(params) - 1];
+    match usize::try_from(depth) {
+        Ok(depth) if depth < params.max_stack_height => Ok(depth),
+        _ => Err(format!(
+            "masked depth is {}, keys are nested deeper than MAX_STACK_HEIGHT {}",
+            depth, params.max_stack_height
+        )
+        .into()),
+    }
+}
+
+/// Stack entries at `depth` and the one above, selected by the masking steps with `ArraySelector`
+/// from the stack with an extra unallocated entry on top
+fn stack_at(state: &json::JsonState, depth: usize) -> ([i64; 2], [i64; 2]) {
+    let entry = |i: usize| state.stack.get(i).copied().unwrap_or([0, 0]);
+    (entry(depth), entry(depth + 1))
+}
+
+/// Whether a byte is in a value with `next` stack entry above it, i.e. in a nested object or
+/// array, or in a string or number otherwise. Shared by `InsideValueObject` and
+/// `InsideArrayIndexObject`.
+fn inside_object_or_string_value(next: [i64; 2], state: &json::JsonState) -> i64 {
+    match next[0] {
+        1 | 2 => 1,
+        _ => {
+            state.parsing_string + state.parsing_number
+                - 2 * state.parsing_string * state.parsing_number
+        }
+    }
+}
+
+/// `step_out` of the masking steps: bytes of `data` at a match or right after one, followed by the
+/// parser states of `step_in` and the depth incremented
+fn masked(step_in: &[i64], params: &NivcParams, data: &[u8], is_match: &[i64]) -> Vec<i64> {
+    let mut step_out = step_in.to_vec();
+    for (i, byte) in data.iter().enumerate() {
+        let previous = match i {
+            0 => 0,
+            _ => is_match[i - 1],
+        };
+        // `OR`
+        let or = is_match[i] + previous - is_match[i] * previous;
+        step_out[i] = i64::from(*byte) * or;
+    }
+    step_out[total_bytes(params) - 1] += 1;
+    step_out
+}
+
+/// `JsonMaskObjectNIVC`
+fn mask_object(
+    step_in: &[i64],
+    params: &NivcParams,
+    key: &[u8],
+) -> Result<Vec<i64>, Box<dyn Error>> {
+    let data = data(step_in, params)?;
+    let states = json_states(step_in, params);
+    let depth = masked_depth(step_in, params)?;
+    // `paddedData` is zero after `data`
+    let byte_at = |i: usize| data.get(i).copied().unwrap_or(0);
+
+    // byte 0 is never a key or value
+    let mut is_key_match_for_value = 0;
+    let mut is_value_match = vec![0; data.len()];
+    for i in 1..data.len() {
+        let state = &states[i];
+        let (current, next) = stack_at(state, depth);
+        // `InsideKey` and `InsideValueObject`
+        let parsing_key =
+            current[0] * (1 - current[1]) * state.parsing_string * (1 - state.parsing_number);
+        let parsing_value = current[0] * current[1] * inside_object_or_string_value(next, state);
+
+        // `KeyMatchAtIndex`
+        let is_key_match = i64::from(
+            byte_at(i - 1) == b'"'
+                && data.get(i..i + key.len()) == Some(key)
+                && byte_at(i + key.len()) == b'"',
+        ) * parsing_key;
+        let not_next_pair = 1 - state.next_kv_pair_at_depth(data[i], depth);
+        let previous = is_key_match_for_value * not_next_pair;
+        // `Mux1` selected by `is_key_match`
+        is_key_match_for_value =
+            (is_key_match * not_next_pair - previous) * is_key_match + previous;
+        is_value_match[i] = is_key_match_for_value * parsing_value;
+    }
+
+    Ok(masked(step_in, params, &data, &is_value_match))
+}
+
+/// `JsonMaskArrayIndexNIVC`
+fn mask_array_index(
+    step_in: &[i64],
+    params: &NivcParams,
+    index: usize,
+) -> Result<Vec<i64>, Box<dyn Error>> {
+    let data = data(step_in, params)?;
+    let depth = masked_depth(step_in, params)?;
+
+    // `InsideArrayIndexObject`
+    let parsing_array: Vec<i64> = json_states(step_in, params)
+        .iter()
+        .map(|state| {
+            let (current, next) = stack_at(state, depth);
+            i64::from(current == [2, index as i64]) * inside_object_or_string_value(next, state)
+        })
+        .collect();
+
+    Ok(masked(step_in, params, &data, &parsing_array))
+}
+
+/// `MaskExtractFinal`: `MAX_VALUE_LENGTH` bytes from the first nonzero byte after byte 0
+fn extract_value(step_in: &[i64], params: &NivcParams) -> Result<Vec<i64>, Box<dyn Error>> {
+    let data = data(step_in, params)?;
+    let mask: Vec<i64> = data.iter().map(|byte| i64::from(*byte)).collect();
+    let start = json::value_starting_index(&mask);
+
+    let mut step_out = vec![0; total_bytes(params)];
+    for (i, byte) in step_out[..params.max_value_len].iter_mut().enumerate() {
+        *byte = mask.get(start + i).copied().unwrap_or(0);
+    }
+
+    Ok(step_out)
+}
+
+/// `step_out` of `step` proving `lockfile`, or an error where the step circuit's constraints fail
+pub fn step_out(
+    step: NivcStep,
+    step_in: &[i64],
+    params: &NivcParams,
+    lockfile: &ExtendedLockfile,
+) -> Result<Vec<i64>, Box<dyn Error>> {
+    if step_in.len() != total_bytes(params) {
+        return Err(format!(
+            "`step_in` has {} signals, TOTAL_BYTES_ACROSS_NIVC is {}",
+            step_in.len(),
+            total_bytes(params)
+        )
+        .into());
+    }
+
+    match step {
+        NivcStep::ParseAndLockStartLine => {
+            parse_and_lock_start_line(step_in, params, lockfile.http.start_line())
+        }
+        NivcStep::LockHeader(i) => {
+            let header = &lockfile.http.headers()[i];
+            lock_header(step_in, params, &header.name, &header.value)
+        }
+        NivcStep::MaskBody => Ok(mask_body(step_in, params)),
+        NivcStep::JsonParse => json_parse(step_in, params),
+        NivcStep::MaskObject(i) | NivcStep::MaskArrayIndex(i) => match &lockfile.json.keys[i] {
+            Key::String(key) => mask_object(step_in, params, key.as_bytes()),
+            Key::Num(index) => mask_array_index(step_in, params, *index),
+        },
+        NivcStep::ExtractValue => extract_value(step_in, params),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        codegen::{nivc::nivc_steps, MaxSizes},
+        FileType,
+    };
+    use std::path::Path;
+
+    fn spotify() -> (ExtendedLockfile, Vec<u8>) {
+        let lockfile = serde_json::from_slice(include_bytes!(
+            "../../examples/http/lockfile/spotify_extended.lock.json"
+        ))
+        .unwrap();
+        let input = FileType::Http
+            .read_input(Path::new("examples/http/spotify_top_artists_response.http"))
+            .unwrap();
+        (lockfile, input)
+    }
+
+    /// `step_out` of each step of the chain
+    fn chain(lockfile: &ExtendedLockfile, input: &[u8]) -> Result<Vec<Vec<i64>>, Box<dyn Error>> {
+        let params = NivcParams::populate(lockfile, input.to_vec(), &MaxSizes::default())?;
+        let mut step_in = initial_step_in(input, &params);
+        let mut step_outs = vec![];
+        for step in nivc_steps(lockfile)? {
+            step_in = step_out(step, &step_in, &params, lockfile)?;
+            step_outs.push(step_in.clone());
+        }
+        Ok(step_outs)
+    }
+
+    #[test]
+    fn nivc_chain() {
+        let (lockfile, input) = spotify();
+        let step_outs = chain(&lockfile, &input).unwrap();
+        let body_start = input.len() - lockfile.http.parse_payload(input.clone()).unwrap().len();
+
+        // start line and header steps carry the HTTP parser state
+        assert_eq!(step_outs[0][..input.len()], step_outs[1][..input.len()]);
+        let body = &step_outs[2][..input.len()];
+        // `parsing_body` is set on the last byte of the CRLF before the body
+        assert!(body[..body_start - 1].iter().all(|byte| *byte == 0));
+        assert_eq!(body[body_start - 1], i64::from(b'\n'));
+        assert_eq!(body[body_start], i64::from(b'{'));
+
+        // each mask keeps less of the body, and increments the depth
+        let total = step_outs[3].len();
+        assert_eq!(step_outs[3][total - 1], 0);
+        for (i, step_out) in step_outs[4..9].iter().enumerate() {
+            assert_eq!(step_out[total - 1], i as i64 + 1);
+        }
+        let masked = step_outs[8][..input.len()]
+            .iter()
+            .filter(|byte| **byte != 0)
+            .map(|byte| *byte as u8)
+            .collect::<Vec<_>>();
+        assert_eq!(masked, b"\"Taylor Swift\"");
+
+        let value = &step_outs[9][.."\"Taylor Swift\"".len()];
+        assert_eq!(
+            value.iter().map(|byte| *byte as u8).collect::<Vec<_>>(),
+            b"\"Taylor Swift\""
+        );
+        assert!(step_outs[9][value.len()..].iter().all(|byte| *byte == 0));
+    }
+
+    #[test]
+    fn nivc_chain_errors() {
+        let (mut lockfile, input) = spotify();
+        let params = NivcParams::populate(&lockfile, input.clone(), &MaxSizes::default()).unwrap();
+        let step_in = initial_step_in(&input, &params);
+        assert!(step_out(NivcStep::MaskBody, &step_in[1..], &params, &lockfile).is_err());
+
+        let step_in = step_out(
+            NivcStep::ParseAndLockStartLine,
+            &step_in,
+            &params,
+            &lockfile,
+        )
+        .unwrap();
+        let crate::codegen::http::HttpData::Response(response) = &mut lockfile.http else {
+            unreachable!()
+        };
+        response.headers[0].value = String::from("text/html");
+        assert_eq!(
+            step_out(NivcStep::LockHeader(0), &step_in, &params, &lockfile)
+                .unwrap_err()
+                .to_string(),
+            "header `content-type` doesn't have value `text/html`"
+        );
+    }
+}
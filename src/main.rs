@@ -3,9 +3,11 @@ use std::{error::Error, path::PathBuf};
 
 pub mod circuit_config;
 pub mod codegen;
+pub mod machine;
+pub mod poseidon;
 pub mod witness;
 
-use crate::codegen::ExtractorArgs;
+use crate::codegen::{ExtractorArgs, MaxSizes};
 
 #[derive(Parser, Debug)]
 #[command(name = "pabuild")]
@@ -27,6 +29,7 @@ pub enum FileType {
     Json,
     Http,
     Extended,
+    Nivc,
 }
 
 #[derive(Debug, Parser)]
@@ -66,6 +69,10 @@ pub struct ExtractorWitnessArgs {
     /// Path to the lockfile
     #[arg(long)]
     lockfile: PathBuf,
+
+    /// Maximum sizes the circuit was generated with, to zero pad the input to
+    #[command(flatten)]
+    max_sizes: MaxSizes,
 }
 
 pub fn main() -> Result<(), Box<dyn Error>> {
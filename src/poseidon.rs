@@ -0,0 +1,223 @@
+//! Poseidon hash over the BN254 scalar field, matching circomlib's `Poseidon(nInputs)` template.
+//!
+//! Round constants and MDS matrices are derived like circomlib's, using the Grain LFSR from the
+//! reference `generate_parameters_grain.sage` script with `x^5` S-box and 8 full rounds.
+
+use ark_bn254::Fr;
+use ark_ff::{Field, PrimeField};
+use std::collections::VecDeque;
+
+const FULL_ROUNDS: usize = 8;
+
+/// Partial rounds for state width `t = 2..=17`, i.e. `1..=16` inputs
+const PARTIAL_ROUNDS: [usize; 16] = [
+    56, 57, 56, 60, 60, 63, 64, 63, 60, 66, 60, 65, 70, 60, 64, 68,
+];
+
+/// Maximum number of inputs of a single Poseidon hash
+pub const MAX_INPUTS: usize = PARTIAL_ROUNDS.len();
+
+/// Poseidon permutation parameters for a fixed number of inputs
+pub struct Poseidon {
+    width: usize,
+    partial_rounds: usize,
+    round_constants: Vec<Fr>,
+    mds: Vec<Vec<Fr>>,
+}
+
+impl Poseidon {
+    /// Generates parameters for hashing `inputs` elements, which should be in `1..=16`.
+    pub fn new(inputs: usize) -> Self {
+        assert!(
+            (1..=MAX_INPUTS).contains(&inputs),
+            "poseidon supports 1 to {} inputs, got {}",
+            MAX_INPUTS,
+            inputs
+        );
+
+        let width = inputs + 1;
+        let partial_rounds = PARTIAL_ROUNDS[inputs - 1];
+        let mut grain = Grain::new(width, partial_rounds);
+
+        let round_constants = (0..(FULL_ROUNDS + partial_rounds) * width)
+            .map(|_| grain.field_element())
+            .collect();
+        let mds = grain.cauchy_matrix(width);
+
+        Self {
+            width,
+            partial_rounds,
+            round_constants,
+            mds,
+        }
+    }
+
+    /// Hashes `inputs`, like `Poseidon(inputs.len())(inputs)` in circom.
+    pub fn hash(&self, inputs: &[Fr]) -> Fr {
+        assert_eq!(
+            inputs.len(),
+            self.width - 1,
+            "wrong number of poseidon inputs"
+        );
+
+        let mut state = vec![Fr::from(0u8)];
+        state.extend_from_slice(inputs);
+
+        let rounds = FULL_ROUNDS + self.partial_rounds;
+        for round in 0..rounds {
+            for (i, element) in state.iter_mut().enumerate() {
+                *element += self.round_constants[round * self.width + i];
+            }
+
+            let is_full_round =
+                round < FULL_ROUNDS / 2 || round >= FULL_ROUNDS / 2 + self.partial_rounds;
+            for element in state
+                .iter_mut()
+                .take(if is_full_round { self.width } else { 1 })
+            {
+                *element = element.pow([5]);
+            }
+
+            state = self
+                .mds
+                .iter()
+                .map(|row| row.iter().zip(state.iter()).map(|(m, s)| *m * s).sum())
+                .collect();
+        }
+
+        state[0]
+    }
+}
+
+/// Grain LFSR used to derive Poseidon parameters
+struct Grain {
+    state: VecDeque<bool>,
+}
+
+impl Grain {
+    fn new(width: usize, partial_rounds: usize) -> Self {
+        let mut state = VecDeque::with_capacity(80);
+        // (value, bits): prime field, `x^alpha` S-box, field size, width, full and partial rounds
+        for (value, bits) in [
+            (1, 2),
+            (0, 4),
+            (Fr::MODULUS_BIT_SIZE as usize, 12),
+            (width, 12),
+            (FULL_ROUNDS, 10),
+            (partial_rounds, 10),
+        ] {
+            for i in (0..bits).rev() {
+                state.push_back((value >> i) & 1 == 1);
+            }
+        }
+        state.extend([true; 30]);
+
+        let mut grain = Self { state };
+        for _ in 0..160 {
+            grain.next_raw_bit();
+        }
+        grain
+    }
+
+    fn next_raw_bit(&mut self) -> bool {
+        let bit = self.state[62]
+            ^ self.state[51]
+            ^ self.state[38]
+            ^ self.state[23]
+            ^ self.state[13]
+            ^ self.state[0];
+        self.state.pop_front();
+        self.state.push_back(bit);
+        bit
+    }
+
+    /// Bits are generated in pairs, and the second bit is output only if the first one is set
+    fn next_bit(&mut self) -> bool {
+        loop {
+            let first = self.next_raw_bit();
+            let second = self.next_raw_bit();
+            if first {
+                return second;
+            }
+        }
+    }
+
+    /// Big-endian `Fr::MODULUS_BIT_SIZE` bits, padded to 32 bytes
+    fn random_bytes(&mut self) -> [u8; 32] {
+        let bits = Fr::MODULUS_BIT_SIZE as usize;
+        let mut bytes = [0u8; 32];
+        for i in (256 - bits)..256 {
+            if self.next_bit() {
+                bytes[i / 8] |= 0x80 >> (i % 8);
+            }
+        }
+        bytes
+    }
+
+    /// Uniformly random field element, rejecting values above the modulus
+    fn field_element(&mut self) -> Fr {
+        loop {
+            let bytes = self.random_bytes();
+            let mut bits = ark_ff::BigInt::<4>::default();
+            for (i, limb) in bits.0.iter_mut().enumerate() {
+                let start = 32 - (i + 1) * 8;
+                *limb = u64::from_be_bytes(bytes[start..start + 8].try_into().unwrap());
+            }
+            if bits < Fr::MODULUS {
+                return Fr::from_bigint(bits).unwrap();
+            }
+        }
+    }
+
+    /// MDS matrix `M[i][j] = 1 / (x_i + y_j)` from `2 * width` distinct random elements
+    fn cauchy_matrix(&mut self, width: usize) -> Vec<Vec<Fr>> {
+        loop {
+            let elements: Vec<Fr> = (0..2 * width)
+                .map(|_| Fr::from_be_bytes_mod_order(&self.random_bytes()))
+                .collect();
+            let is_distinct = elements
+                .iter()
+                .enumerate()
+                .all(|(i, a)| elements[..i].iter().all(|b| a != b));
+            if !is_distinct {
+                continue;
+            }
+
+            let (xs, ys) = elements.split_at(width);
+            let matrix: Option<Vec<Vec<Fr>>> = xs
+                .iter()
+                .map(|x| ys.iter().map(|y| (*x + y).inverse()).collect())
+                .collect();
+            if let Some(matrix) = matrix {
+                return matrix;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn circomlib_vectors() {
+        for (inputs, expected) in [
+            (
+                1,
+                "18586133768512220936620570745912940619677854269274689475585506675881198879027",
+            ),
+            (
+                2,
+                "7853200120776062878684798364095072458815029376092732009249414926327459813530",
+            ),
+            (
+                16,
+                "9989051620750914585850546081941653841776809718687451684622678807385399211877",
+            ),
+        ] {
+            let elements: Vec<Fr> = (1..=inputs as u64).map(Fr::from).collect();
+            let hash = Poseidon::new(inputs).hash(&elements);
+            assert_eq!(hash.to_string(), expected);
+        }
+    }
+}
@@ -1,11 +1,19 @@
+use ark_bn254::Fr;
 use serde::Serialize;
 
 use crate::{
     codegen::{
-        http::HttpData,
+        http::{HeaderMode, HttpData},
         integrated::ExtendedLockfile,
-        json::{json_max_stack_height, Lockfile},
+        json::{json_max_stack_height, ExtractorLockfile, Key, Lockfile},
+        nivc::{nivc_steps, NivcParams, NivcStep},
+        pad, MaxSizes,
     },
+    machine::{
+        self,
+        json::{predict_value_starting_index, trace},
+    },
+    poseidon::{self, Poseidon},
     ExtractorWitnessArgs, FileType, ParserWitnessArgs,
 };
 use std::{collections::HashMap, io::Write, path::Path};
@@ -19,14 +27,26 @@ pub struct ParserWitness {
 pub struct JsonExtractorWitness {
     data: Vec<u8>,
 
+    /// Length of `data` before the zero padding, for circuits with maximum sizes
+    #[serde(rename = "dataLen", skip_serializing_if = "Option::is_none")]
+    data_len: Option<usize>,
+
     #[serde(flatten)]
     keys: HashMap<String, Vec<u8>>,
+
+    /// Lengths of the zero padded keys, for circuits with `--max-key-len`
+    #[serde(flatten)]
+    key_lens: HashMap<String, usize>,
 }
 
 #[derive(Serialize)]
 pub struct HttpExtractorWitness {
     data: Vec<u8>,
 
+    /// Length of `data` before the zero padding, for circuits with maximum sizes
+    #[serde(rename = "dataLen", skip_serializing_if = "Option::is_none")]
+    data_len: Option<usize>,
+
     #[serde(flatten)]
     http_data: HttpData,
 }
@@ -37,6 +57,8 @@ pub struct ExtendedWitness {
     http_witness: HttpExtractorWitness,
     #[serde(flatten)]
     keys: HashMap<String, Vec<u8>>,
+    #[serde(flatten)]
+    key_lens: HashMap<String, usize>,
 }
 
 fn print_boxed_output(lines: Vec<String>) {
@@ -59,7 +81,7 @@ impl FileType {
     pub fn read_input(&self, input: &Path) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
         match self {
             FileType::Json => Ok(std::fs::read(input)?),
-            FileType::Http | FileType::Extended => {
+            FileType::Http | FileType::Extended | FileType::Nivc => {
                 let mut data = std::fs::read(input)?;
                 let mut i = 0;
                 // convert LF to CRLF
@@ -77,7 +99,111 @@ impl FileType {
     }
 }
 
+/// Commitment to `bytes` output by circuits with `"commitment": "poseidon"`. Computed like
+/// `PoseidonModular` in `circuits/utils/hash.circom`: bytes are hashed in chunks of 16, and chunk
+/// hashes are chained with `Poseidon(2)`.
+pub fn poseidon_commitment(bytes: &[u8]) -> Result<Fr, Box<dyn std::error::Error>> {
+    let elements: Vec<Fr> = bytes.iter().map(|byte| Fr::from(*byte)).collect();
+
+    let mut hashers = HashMap::new();
+    let mut commitment = None;
+    for chunk in elements.chunks(poseidon::MAX_INPUTS) {
+        let chunk_hash = hashers
+            .entry(chunk.len())
+            .or_insert_with(|| Poseidon::new(chunk.len()))
+            .hash(chunk);
+        commitment = match commitment {
+            None => Some(chunk_hash),
+            Some(previous) => Some(
+                hashers
+                    .entry(2)
+                    .or_insert_with(|| Poseidon::new(2))
+                    .hash(&[previous, chunk_hash]),
+            ),
+        };
+    }
+
+    commitment.ok_or_else(|| String::from("can't commit to empty value").into())
+}
+
+/// Commitment to the value extracted by `lockfile` from `input`, if the lockfile has one. The
+/// value is zero padded to `max_sizes.max_value_len` like in the circuit.
+fn json_value_commitment(
+    lockfile: &Lockfile,
+    input: &[u8],
+    max_sizes: &MaxSizes,
+) -> Result<Option<Fr>, Box<dyn std::error::Error>> {
+    match lockfile.commitment {
+        Some(_) => {
+            let value = pad(
+                &lockfile.get_value_bytes(input)?,
+                max_sizes.max_value_len,
+                "max-value-len",
+            )?;
+            Ok(Some(poseidon_commitment(&value)?))
+        }
+        None => Ok(None),
+    }
+}
+
+/// Length of `data` before the zero padding, if the circuit has maximum sizes
+fn data_len(data: &[u8], max_sizes: &MaxSizes) -> Option<usize> {
+    max_sizes.is_padded().then_some(data.len())
+}
+
+/// `key_lens` if keys are zero padded, circuits with exact keys take no key lengths
+fn padded_key_lens(
+    key_lens: HashMap<String, usize>,
+    max_sizes: &MaxSizes,
+) -> HashMap<String, usize> {
+    match max_sizes.max_key_len {
+        Some(_) => key_lens,
+        None => HashMap::new(),
+    }
+}
+
+/// Lines with the header and query parameter values output by `http_data`'s circuit for `input`
+fn extracted_value_lines(
+    http_data: &HttpData,
+    input: &[u8],
+) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let parts = machine::http::parse(input)?;
+    let values = http_data.header_values(&parts)?;
+
+    let mut lines = vec![];
+    for (header, value) in http_data.headers().iter().zip(values) {
+        if header.mode == HeaderMode::Extract {
+            lines.push(format!(
+                "{} value: {}",
+                header.name,
+                String::from_utf8_lossy(value)
+            ));
+        }
+    }
+    let values = http_data.query_values(&parts)?;
+    for (param, value) in http_data.query_params().iter().zip(values) {
+        if param.value.is_none() {
+            lines.push(format!(
+                "{} query value: {}",
+                param.name,
+                String::from_utf8_lossy(value)
+            ));
+        }
+    }
+
+    Ok(lines)
+}
+
 fn write_witness(circuit_name: &str, witness: &[u8]) -> Result<String, Box<dyn std::error::Error>> {
+    write_witness_file(circuit_name, "inputs.json", witness)
+}
+
+/// Writes `witness` to `inputs/{circuit_name}/{file_name}`
+fn write_witness_file(
+    circuit_name: &str,
+    file_name: &str,
+    witness: &[u8],
+) -> Result<String, Box<dyn std::error::Error>> {
     let mut output_dir = std::env::current_dir()?;
     output_dir.push("inputs");
     output_dir.push(circuit_name);
@@ -86,7 +212,7 @@ fn write_witness(circuit_name: &str, witness: &[u8]) -> Result<String, Box<dyn s
         std::fs::create_dir_all(&output_dir)?;
     }
 
-    let output_file = output_dir.join("inputs.json");
+    let output_file = output_dir.join(file_name);
     let mut file = std::fs::File::create(&output_file)?;
 
     file.write_all(witness)?;
@@ -112,7 +238,7 @@ pub fn parser_witness(args: ParserWitnessArgs) -> Result<(), Box<dyn std::error:
     if args.subcommand == FileType::Json {
         lines.push(format!(
             "Max stack height: {}",
-            json_max_stack_height(&data)
+            json_max_stack_height(&data)?
         ))
     }
 
@@ -129,12 +255,64 @@ fn json_extractor_witness(args: ExtractorWitnessArgs) -> Result<(), Box<dyn std:
     let input_data = args.subcommand.read_input(&args.input_file)?;
 
     let lockfile_data = std::fs::read(&args.lockfile)?;
-    let lockfile: Lockfile = serde_json::from_slice(&lockfile_data)?;
+    let lockfile: ExtractorLockfile = serde_json::from_slice(&lockfile_data)?;
+
+    let max_key_len = args.max_sizes.max_key_len;
+    let (keys, key_lens, mut values, public_inputs) = match &lockfile {
+        ExtractorLockfile::Single(lockfile) => (
+            lockfile.keys_as_bytes(max_key_len)?,
+            lockfile.key_lens(),
+            vec![(String::from("Value"), lockfile.get_value(&input_data)?)],
+            lockfile.public_inputs(),
+        ),
+        ExtractorLockfile::Multi(lockfile) => (
+            lockfile.keys_as_bytes(max_key_len)?,
+            lockfile.key_lens(),
+            lockfile.get_values(&input_data)?,
+            lockfile.public_inputs(),
+        ),
+    };
+
+    // where the circuit finds each value, predicted by running the parser in Rust
+    let max_stack_height = json_max_stack_height(&input_data)?;
+    let states = trace(&input_data, max_stack_height)?;
+    let mut starting_indices = vec![];
+
+    match &lockfile {
+        ExtractorLockfile::Single(lockfile) => {
+            starting_indices.push((
+                String::from("Value starting index"),
+                predict_value_starting_index(&input_data, &states, lockfile)?,
+            ));
+            if let Some(commitment) = json_value_commitment(lockfile, &input_data, &args.max_sizes)?
+            {
+                values.push((String::from("Commitment"), commitment.to_string()));
+            }
+        }
+        ExtractorLockfile::Multi(lockfile) => {
+            for extraction in lockfile.extractions.iter() {
+                starting_indices.push((
+                    format!("{} starting index", extraction.name),
+                    predict_value_starting_index(&input_data, &states, &extraction.lockfile)?,
+                ));
+                if let Some(commitment) =
+                    json_value_commitment(&extraction.lockfile, &input_data, &args.max_sizes)?
+                {
+                    values.push((
+                        format!("{} commitment", extraction.name),
+                        commitment.to_string(),
+                    ));
+                }
+            }
+        }
+    }
 
     // create extractor witness data
     let witness = JsonExtractorWitness {
-        data: input_data.clone(),
-        keys: lockfile.keys_as_bytes(),
+        data: args.max_sizes.pad_data(&input_data)?,
+        data_len: data_len(&input_data, &args.max_sizes),
+        keys,
+        key_lens: padded_key_lens(key_lens, &args.max_sizes),
     };
 
     let output = write_witness(
@@ -145,10 +323,17 @@ fn json_extractor_witness(args: ExtractorWitnessArgs) -> Result<(), Box<dyn std:
     // Prepare lines to print
     let mut lines = Vec::new();
     lines.push(format!("Data length: {}", input_data.len()));
-    lines.push(format!(
-        "Max stack height: {}",
-        json_max_stack_height(&input_data)
-    ));
+    lines.push(format!("Max stack height: {}", max_stack_height));
+    for (name, value) in values {
+        lines.push(format!("{}: {}", name, value));
+    }
+    for (name, index) in starting_indices {
+        lines.push(format!("{}: {}", name, index));
+    }
+    // predicate bounds are chosen by the verifier, so they aren't part of the witness
+    if !public_inputs.is_empty() {
+        lines.push(format!("Public inputs: {}", public_inputs.join(", ")));
+    }
 
     lines.push(output);
 
@@ -165,9 +350,22 @@ fn http_extractor_witness(args: ExtractorWitnessArgs) -> Result<(), Box<dyn std:
     let lockfile_data = std::fs::read(&args.lockfile)?;
     let http_data: HttpData = serde_json::from_slice(&lockfile_data)?;
 
+    // fails where the circuit's parser would
+    let (_, body) = http_data.parse_input(data.clone())?;
+    let body_commitment = match http_data.commitment() {
+        Some(_) => Some(poseidon_commitment(&pad(
+            &body,
+            args.max_sizes.max_content_length,
+            "max-content-length",
+        )?)?),
+        None => None,
+    };
+    let header_lines = extracted_value_lines(&http_data, &data)?;
+
     // create witness data
     let witness = HttpExtractorWitness {
-        data: data.clone(),
+        data: args.max_sizes.pad_data(&data)?,
+        data_len: data_len(&data, &args.max_sizes),
         http_data,
     };
 
@@ -179,6 +377,11 @@ fn http_extractor_witness(args: ExtractorWitnessArgs) -> Result<(), Box<dyn std:
     // Prepare lines to print
     let mut lines = Vec::new();
     lines.push(format!("Data length: {}", data.len()));
+    lines.push(format!("Body length: {}", body.len()));
+    if let Some(commitment) = body_commitment {
+        lines.push(format!("Body commitment: {}", commitment));
+    }
+    lines.extend(header_lines);
 
     lines.push(output);
 
@@ -197,13 +400,21 @@ fn extended_extractor_witness(
     let lockfile_data = std::fs::read(&args.lockfile)?;
     let lockfile: ExtendedLockfile = serde_json::from_slice(&lockfile_data)?;
 
+    // JSON is extracted from the chunk data of a chunked body
+    let payload = lockfile.http.parse_payload(data.clone())?;
+    let commitment = json_value_commitment(&lockfile.json, &payload, &args.max_sizes)?;
+    let chunked = lockfile.http.chunked();
+    let header_lines = extracted_value_lines(&lockfile.http, &data)?;
+
     // create witness data
     let witness = ExtendedWitness {
         http_witness: HttpExtractorWitness {
-            data: data.clone(),
+            data: args.max_sizes.pad_data(&data)?,
+            data_len: data_len(&data, &args.max_sizes),
             http_data: lockfile.http,
         },
-        keys: lockfile.json.keys_as_bytes(),
+        keys: lockfile.json.keys_as_bytes(args.max_sizes.max_key_len)?,
+        key_lens: padded_key_lens(lockfile.json.key_lens(), &args.max_sizes),
     };
 
     let output = write_witness(
@@ -214,6 +425,13 @@ fn extended_extractor_witness(
     // Prepare lines to print
     let mut lines = Vec::new();
     lines.push(format!("Data length: {}", data.len()));
+    if chunked {
+        lines.push(format!("Payload length: {}", payload.len()));
+    }
+    if let Some(commitment) = commitment {
+        lines.push(format!("Commitment: {}", commitment));
+    }
+    lines.extend(header_lines);
 
     lines.push(output);
 
@@ -223,10 +441,154 @@ fn extended_extractor_witness(
     Ok(())
 }
 
+/// Private inputs of `step` besides `step_in`, zero padded to the step circuit's params
+fn nivc_step_inputs(
+    step: NivcStep,
+    lockfile: &ExtendedLockfile,
+    params: &NivcParams,
+) -> serde_json::Map<String, serde_json::Value> {
+    let padded = |bytes: &str, len: usize| {
+        let mut bytes = bytes.as_bytes().to_vec();
+        bytes.resize(len, 0);
+        serde_json::json!(bytes)
+    };
+
+    let mut inputs = serde_json::Map::new();
+    match step {
+        NivcStep::ParseAndLockStartLine => {
+            let start_line = lockfile.http.start_line();
+            for (i, name) in ["beginning", "middle", "final"].into_iter().enumerate() {
+                inputs.insert(
+                    name.to_string(),
+                    padded(start_line[i], params.start_line_lens[i]),
+                );
+                inputs.insert(format!("{}_length", name), start_line[i].len().into());
+            }
+        }
+        NivcStep::LockHeader(i) => {
+            let header = &lockfile.http.headers()[i];
+            inputs.insert(
+                String::from("header"),
+                padded(&header.name, params.max_header_name_len),
+            );
+            inputs.insert(String::from("headerNameLength"), header.name.len().into());
+            inputs.insert(
+                String::from("value"),
+                padded(&header.value, params.max_header_value_len),
+            );
+            inputs.insert(String::from("headerValueLength"), header.value.len().into());
+        }
+        NivcStep::MaskObject(i) | NivcStep::MaskArrayIndex(i) => match &lockfile.json.keys[i] {
+            Key::String(key) => {
+                inputs.insert(String::from("key"), padded(key, params.max_key_len));
+                inputs.insert(String::from("keyLen"), key.len().into());
+            }
+            Key::Num(index) => {
+                inputs.insert(String::from("index"), (*index).into());
+            }
+        },
+        NivcStep::MaskBody | NivcStep::JsonParse | NivcStep::ExtractValue => (),
+    }
+
+    inputs
+}
+
+/// Writes the `step_in` and private inputs of each NIVC step to
+/// `inputs/{circuit_name}_{step}/step{n}.json`, along with the expected `step_out` at
+/// `step{n}_out.json`, checking every step as its circuit would.
+fn nivc_extractor_witness(args: ExtractorWitnessArgs) -> Result<(), Box<dyn std::error::Error>> {
+    // read input and lockfile
+    let data = args.subcommand.read_input(&args.input_file)?;
+
+    let lockfile_data = std::fs::read(&args.lockfile)?;
+    let lockfile: ExtendedLockfile = serde_json::from_slice(&lockfile_data)?;
+
+    let steps = nivc_steps(&lockfile)?;
+    let params = NivcParams::populate(&lockfile, data.clone(), &args.max_sizes)?;
+    let payload = lockfile.http.parse_payload(data.clone())?;
+    let value = lockfile.json.get_raw_value(&payload)?;
+
+    let mut step_in = machine::nivc::initial_step_in(&args.max_sizes.pad_data(&data)?, &params);
+    let mut outputs = vec![];
+    for (n, step) in steps.into_iter().enumerate() {
+        let step_out = machine::nivc::step_out(step, &step_in, &params, &lockfile)
+            .map_err(|e| format!("step {} `{}`: {}", n, step.name(), e))?;
+
+        let circuit_name = format!("{}_{}", args.circuit_name, step.name());
+        let mut inputs = nivc_step_inputs(step, &lockfile, &params);
+        inputs.insert(String::from("step_in"), serde_json::json!(step_in));
+        outputs.push(write_witness_file(
+            &circuit_name,
+            &format!("step{}.json", n),
+            serde_json::to_string_pretty(&inputs)?.as_bytes(),
+        )?);
+        write_witness_file(
+            &circuit_name,
+            &format!("step{}_out.json", n),
+            serde_json::to_string_pretty(&serde_json::json!({ "step_out": step_out }))?.as_bytes(),
+        )?;
+
+        step_in = step_out;
+    }
+
+    // last step outputs the value zero padded to `maxValueLen`
+    let extracted: Vec<u8> = step_in[..value.get().len()]
+        .iter()
+        .map(|byte| *byte as u8)
+        .collect();
+    if extracted != value.get().as_bytes() {
+        return Err(format!(
+            "NIVC steps extract `{}` instead of `{}`",
+            String::from_utf8_lossy(&extracted),
+            value.get()
+        )
+        .into());
+    }
+
+    // Prepare lines to print
+    let mut lines = Vec::new();
+    lines.push(format!("Data length: {}", data.len()));
+    lines.push(format!("Value: {}", value.get()));
+    lines.extend(outputs);
+
+    // Print the output inside a nicely formatted box
+    print_boxed_output(lines);
+
+    Ok(())
+}
+
 pub fn extractor_witness(args: ExtractorWitnessArgs) -> Result<(), Box<dyn std::error::Error>> {
     match args.subcommand {
         FileType::Json => json_extractor_witness(args),
         FileType::Http => http_extractor_witness(args),
         FileType::Extended => extended_extractor_witness(args),
+        FileType::Nivc => nivc_extractor_witness(args),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn poseidon_commitment_chunks() {
+        let bytes: Vec<u8> = (1..=20).collect();
+        let elements: Vec<Fr> = bytes.iter().map(|byte| Fr::from(*byte)).collect();
+
+        // a single chunk is hashed directly
+        assert_eq!(
+            poseidon_commitment(&bytes[..3]).unwrap(),
+            Poseidon::new(3).hash(&elements[..3])
+        );
+
+        // chunk hashes are chained
+        let first = Poseidon::new(16).hash(&elements[..16]);
+        let second = Poseidon::new(4).hash(&elements[16..]);
+        assert_eq!(
+            poseidon_commitment(&bytes).unwrap(),
+            Poseidon::new(2).hash(&[first, second])
+        );
+
+        assert!(poseidon_commitment(&[]).is_err());
     }
 }